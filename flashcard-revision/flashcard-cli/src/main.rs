@@ -0,0 +1,542 @@
+use std::error::Error;
+use std::io::{self, BufRead, Write};
+use std::process::ExitCode;
+use std::time::Instant; // Handles response times
+
+use chrono::{Local, TimeZone}; // Handles due dates
+
+use clap::{Parser, Subcommand}; // Handles command line arguments
+
+use rusqlite::Connection; // Handles SQLite database
+
+use flashcard_core::database::{self, Subject};
+use flashcard_core::deck::{Deck, SubjectStats};
+use flashcard_core::flashcards::{self, FlashcardEdit, FlashcardSnapshot};
+use flashcard_core::formats::{self, anki::{self, AnkiExport, AnkiPackage, AnkiReport}, delimited::{self, DelimitedFile}, json::{self, JsonDeck, JsonReport}, markdown::{self, MarkdownDeck, MarkdownReport}, plain, ImportCard, PreviewCounts, PreviewRow, RowStatus};
+use flashcard_core::matching::{AnswerMatch, MatchOptions};
+use flashcard_core::revision::{RevisionSession, RevisionSummary};
+use flashcard_core::scheduler::{self, Algorithm, Grade};
+use flashcard_core::settings;
+
+/* **Explanation of the command line**
+The same database as the window, for scripts and SSH sessions:
+	flashcards subjects list/add/rm
+	flashcards cards list/add/edit/rm (Cards are picked by the id shown by `cards list`)
+	flashcards import FILE (.csv, .tsv, .md, .json, .apkg, .colpkg, anything else is read as pasted text)
+	flashcards export SUBJECT FILE (.csv, .tsv, .md, .json or .apkg)
+	flashcards review SUBJECT (Revises the cards due today, like the revision stage)
+	flashcards stats [SUBJECT]
+Lists are tab separated so they can be cut/awk'd. Errors go to stderr with a failing exit code.
+*/
+type CommandResult = Result<(), Box<dyn Error>>;
+
+#[derive(Parser)]
+#[command(name = "flashcards", version, about = "Revise and manage flashcards without opening the window")]
+struct Cli {
+	#[arg(long, global = true, default_value = database::DATABASE_PATH, help = "Database to use")]
+	database: String,
+	#[command(subcommand)]
+	command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+	#[command(subcommand, about = "List, add and remove subjects")]
+	Subjects(SubjectsCommand),
+	#[command(subcommand, about = "List, add, edit and remove flashcards")]
+	Cards(CardsCommand),
+	#[command(about = "Import a file (Its format is picked from its extension)")]
+	Import {
+		file: String,
+		#[arg(long, help = "Subject to import into (Made if there isn't one). Decks naming their own subject go there otherwise")]
+		subject: Option<String>,
+		#[arg(long, help = "Show what would be imported without changing anything")]
+		dry_run: bool,
+		#[arg(long, help = "Pasted text: between term and definition (\\t = tab), detected if not given")]
+		term_separator: Option<String>,
+		#[arg(long, help = "Pasted text: between cards (\\n = new line), detected if not given")]
+		card_separator: Option<String>,
+	},
+	#[command(about = "Export a subject (Its format is picked from the file's extension)")]
+	Export { subject: String, file: String },
+	#[command(about = "Revise the cards of a subject due today")]
+	Review {
+		subject: String,
+		#[arg(long, help = "Stop after this many cards")]
+		limit: Option<usize>,
+	},
+	#[command(about = "Show cards, tiers, due cards and accuracy of every subject, or of one")]
+	Stats { subject: Option<String> },
+}
+
+#[derive(Subcommand)]
+enum SubjectsCommand {
+	#[command(about = "List every subject")]
+	List,
+	#[command(about = "Add a subject")]
+	Add {
+		name: String,
+		#[arg(long, default_value = "sm2", help = "Scheduling algorithm: sm2 or fsrs")]
+		algorithm: String,
+		#[arg(long = "tag", help = "Tag of the subject, can be given more than once")]
+		tags: Vec<String>,
+	},
+	#[command(about = "Remove a subject with all of its cards and review history")]
+	Rm {
+		name: String,
+		#[arg(long, help = "Don't ask before removing")]
+		yes: bool,
+	},
+}
+
+#[derive(Subcommand)]
+enum CardsCommand {
+	#[command(about = "List the cards of a subject")]
+	List { subject: String },
+	#[command(about = "Add a card. Leave out the answer to add a cloze text ({{c1::...}})")]
+	Add {
+		subject: String,
+		question: String,
+		answer: Option<String>,
+		#[arg(long = "alternate", help = "Other accepted answer, can be given more than once")]
+		alternates: Vec<String>,
+		#[arg(long, help = "Also add the answer to question card")]
+		reverse: bool,
+		#[arg(long, help = "Answer by picking from options instead of typing")]
+		multiple_choice: bool,
+		#[arg(long = "distractor", help = "Wrong option of a multiple choice card, can be given more than once")]
+		distractors: Vec<String>,
+	},
+	#[command(about = "Edit a card, keeping whatever isn't given")]
+	Edit {
+		id: i32,
+		#[arg(long)]
+		question: Option<String>,
+		#[arg(long)]
+		answer: Option<String>,
+		#[arg(long = "alternate", help = "Replaces the other accepted answers, can be given more than once")]
+		alternates: Vec<String>,
+		#[arg(long, help = "Removes the other accepted answers")]
+		no_alternates: bool,
+		#[arg(long, help = "true to answer by picking from options, false to answer by typing")]
+		multiple_choice: Option<bool>,
+		#[arg(long = "distractor", help = "Replaces the wrong options, can be given more than once")]
+		distractors: Vec<String>,
+	},
+	#[command(about = "Remove a card (A whole note for cards made from one)")]
+	Rm { id: i32 },
+}
+
+fn main() -> ExitCode {
+	let cli: Cli = Cli::parse();
+	let result: CommandResult = Deck::open(&cli.database)
+		.map_err(|e| format!("Couldn't open {}: {}", cli.database, e).into())
+		.and_then(|deck| run(&deck, cli.command));
+	match result {
+		Ok(()) => ExitCode::SUCCESS,
+		Err(e) => {
+			eprintln!("Error: {}", e);
+			ExitCode::FAILURE
+		}
+	}
+}
+
+fn run(deck: &Deck, command: Command) -> CommandResult {
+	match command {
+		Command::Subjects(SubjectsCommand::List) => list_subjects(deck),
+		Command::Subjects(SubjectsCommand::Add { name, algorithm, tags }) => add_subject(deck, &name, &algorithm, &tags),
+		Command::Subjects(SubjectsCommand::Rm { name, yes }) => remove_subject(deck, &name, yes),
+		Command::Cards(CardsCommand::List { subject }) => list_cards(deck, &subject),
+		Command::Cards(CardsCommand::Add { subject, question, answer, alternates, reverse, multiple_choice, distractors }) => {
+			add_card(deck, &subject, &question, answer.as_deref().unwrap_or(""), &alternates, reverse, multiple_choice, &distractors)
+		},
+		Command::Cards(CardsCommand::Edit { id, question, answer, alternates, no_alternates, multiple_choice, distractors }) => {
+			let alternates: Option<Vec<String>> = if no_alternates { Some(Vec::new()) } else if alternates.is_empty() { None } else { Some(alternates) };
+			let distractors: Option<Vec<String>> = if distractors.is_empty() { None } else { Some(distractors) };
+			edit_card(deck, id, question, answer, alternates, multiple_choice, distractors)
+		},
+		Command::Cards(CardsCommand::Rm { id }) => remove_card(deck, id),
+		Command::Import { file, subject, dry_run, term_separator, card_separator } => {
+			import(deck, &file, subject.as_deref(), dry_run, term_separator.as_deref(), card_separator.as_deref())
+		},
+		Command::Export { subject, file } => export(deck, &subject, &file),
+		Command::Review { subject, limit } => review(deck, &subject, limit),
+		Command::Stats { subject } => stats(deck, subject.as_deref()),
+	}
+}
+
+//* The subject with this name, or an error naming it */
+fn subject_named(deck: &Deck, name: &str) -> Result<Subject, Box<dyn Error>> {
+	deck.find_subject(name)?.ok_or_else(|| format!("There is no subject called {}. Add it with `flashcards subjects add`.", name.trim()).into())
+}
+
+//* Date a card is due (Local time), or "new" for cards never scheduled */
+fn due_date(due: i64) -> String {
+	match (due, Local.timestamp_opt(due, 0).single()) {
+		(0, _) | (_, None) => "new".to_owned(),
+		(_, Some(date)) => date.format("%Y-%m-%d").to_string(),
+	}
+}
+
+//* Reads one line from stdin without its line ending, or None at the end of the input */
+fn read_line(prompt: &str) -> io::Result<Option<String>> {
+	print!("{}", prompt);
+	io::stdout().flush()?;
+	let mut line: String = String::new();
+	if io::stdin().lock().read_line(&mut line)? == 0 {
+		return Ok(None);
+	}
+	Ok(Some(line.trim_end_matches(['\r', '\n']).to_owned()))
+}
+
+fn list_subjects(deck: &Deck) -> CommandResult {
+	for subject in deck.subjects()? {
+		let (cards, due): (usize, usize) = deck.count_flashcards(subject.id)?;
+		println!("{}\t{}\t{} cards\t{} due", subject.name, subject.algorithm.name(), cards, due);
+	}
+	Ok(())
+}
+
+fn add_subject(deck: &Deck, name: &str, algorithm: &str, tags: &[String]) -> CommandResult {
+	let name: &str = name.trim();
+	if name.is_empty() {
+		return Err("The subject needs a name.".into());
+	}
+	let algorithm: Algorithm = Algorithm::from_name(algorithm).ok_or("The algorithm must be sm2 or fsrs.")?;
+	match deck.add_subject(name, algorithm, tags)? {
+		Some(subject) => println!("Added {} ({}).", subject.name, algorithm.display_name()),
+		None => return Err(format!("There is already a subject called {}.", name).into()),
+	}
+	Ok(())
+}
+
+fn remove_subject(deck: &Deck, name: &str, yes: bool) -> CommandResult {
+	let subject: Subject = subject_named(deck, name)?;
+	let (cards, _): (usize, usize) = deck.count_flashcards(subject.id)?;
+	if !yes {
+		let prompt: String = format!("Remove {} and its {} cards? This can't be undone. (y/N) ", subject.name, cards); // Default no
+		if read_line(&prompt)?.map(|answer| answer.trim().to_lowercase()) != Some("y".to_owned()) {
+			println!("{} was not removed.", subject.name);
+			return Ok(());
+		}
+	}
+	deck.remove_subject(subject.id)?;
+	println!("Removed {} and its {} cards.", subject.name, cards);
+	Ok(())
+}
+
+fn list_cards(deck: &Deck, subject: &str) -> CommandResult {
+	let subject: Subject = subject_named(deck, subject)?;
+	for flashcard in deck.flashcards(subject.id)? {
+		println!(
+			"{}\t{}\t{}\t{}\t{}",
+			flashcard.primary_key, scheduler::tier_name(flashcard.category()), due_date(flashcard.schedule.due),
+			flashcard.display_question(), flashcard.accepted_answers().join(" / ")
+		);
+	}
+	Ok(())
+}
+
+#[allow(clippy::too_many_arguments)] // One per option of `cards add`
+fn add_card(
+	deck: &Deck, subject: &str, question: &str, answer: &str, alternates: &[String],
+	reverse: bool, multiple_choice: bool, distractors: &[String],
+) -> CommandResult {
+	let subject: Subject = subject_named(deck, subject)?;
+	let mut card: ImportCard = ImportCard::new(question.trim(), answer.trim());
+	if let Some(problem) = card.problem() {
+		return Err(format!("{}. Leave out the answer only for cloze texts ({{{{c1::...}}}}).", problem).into());
+	}
+	card.alternates = alternates.to_vec();
+	card.multiple_choice = multiple_choice;
+	card.distractors = distractors.to_vec();
+
+	let primary_keys: Vec<i64> = deck.add_flashcard(subject.id, &card, reverse)?;
+	let ids: Vec<String> = primary_keys.iter().map(|primary_key| primary_key.to_string()).collect();
+	println!("Added {} flashcards to {} (Ids {}).", primary_keys.len(), subject.name, ids.join(", "));
+	Ok(())
+}
+
+fn edit_card(
+	deck: &Deck, id: i32, question: Option<String>, answer: Option<String>, alternates: Option<Vec<String>>,
+	multiple_choice: Option<bool>, distractors: Option<Vec<String>>,
+) -> CommandResult {
+	let before: FlashcardSnapshot = deck.flashcard(id)?.ok_or(format!("There is no card with id {}.", id))?;
+	let edit: FlashcardEdit = FlashcardEdit { question, answer, alternates, multiple_choice, distractors };
+	deck.edit_flashcard(&before, &edit)?;
+	println!("Edited card {}.", id);
+	Ok(())
+}
+
+fn remove_card(deck: &Deck, id: i32) -> CommandResult {
+	let card: FlashcardSnapshot = deck.flashcard(id)?.ok_or(format!("There is no card with id {}.", id))?;
+	deck.remove_flashcard(id)?;
+	match card.note_id {
+		Some(_) => println!("Removed the note that card {} was made from, with all of its cards.", id),
+		None => println!("Removed card {}.", id),
+	}
+	Ok(())
+}
+
+//* Prints the rows of a preview that won't simply be added, so problems in a file can be found */
+fn print_skipped(rows: &[PreviewRow]) {
+	for row in rows {
+		match &row.status {
+			RowStatus::Invalid(problem) => eprintln!("{}: skipped, {}", row.line, problem),
+			RowStatus::Duplicate => eprintln!("{}: skipped, already in the subject ({})", row.line, row.card.question),
+			_ => {},
+		}
+	}
+}
+
+fn import(
+	deck: &Deck, file: &str, subject: Option<&str>, dry_run: bool, term_separator: Option<&str>, card_separator: Option<&str>,
+) -> CommandResult {
+	let lowercase: String = file.to_lowercase();
+	if lowercase.ends_with(".apkg") || lowercase.ends_with(".colpkg") {
+		return import_anki(deck.conn(), file, dry_run);
+	}
+
+	let text: String = std::fs::read_to_string(file).map_err(|e| format!("Couldn't open {}: {}", file, e))?;
+	let text: &str = text.trim_start_matches('\u{feff}'); // Spreadsheet apps often start files with a byte order mark
+	if lowercase.ends_with(".md") || lowercase.ends_with(".markdown") {
+		import_markdown(deck, file, text, subject, dry_run)
+	} else if lowercase.ends_with(".json") {
+		import_json(deck, text, subject, dry_run)
+	} else {
+		let name: &str = subject.ok_or("Give the subject to import into with --subject.")?.trim();
+		let rows: Vec<PreviewRow> = if lowercase.ends_with(".csv") || lowercase.ends_with(".tsv") {
+			DelimitedFile::parse(text)?.preview()
+		} else {
+			// Anything else is read like pasted text
+			let (detected_term, detected_card): (String, String) = plain::detect_separators(text);
+			let term_separator: String = term_separator.map(plain::parse_separator).unwrap_or(detected_term);
+			let card_separator: String = card_separator.map(plain::parse_separator).unwrap_or(detected_card);
+			println!("Term: {}, cards: {}.", plain::separator_name(&term_separator), plain::separator_name(&card_separator));
+			plain::preview(text, &term_separator, &card_separator)
+		};
+		import_rows(deck, name, rows, dry_run)
+	}
+}
+
+//* Adds the ready rows of a CSV/TSV file or pasted text to a subject, made if there isn't one with that name */
+fn import_rows(deck: &Deck, name: &str, mut rows: Vec<PreviewRow>, dry_run: bool) -> CommandResult {
+	let conn: &Connection = deck.conn();
+	match deck.find_subject(name)? {
+		Some(subject) => formats::mark_duplicates(conn, subject.id, &mut rows)?,
+		None => formats::mark_duplicates_within(&mut rows),
+	}
+	print_skipped(&rows);
+	let counts: PreviewCounts = formats::count_rows(&rows);
+	if dry_run {
+		println!("{} rows ready to import into {}. {} duplicates and {} invalid rows would be skipped.", counts.ready, name, counts.duplicates, counts.invalid);
+		return Ok(());
+	}
+
+	let subject_id: i64 = database::get_or_add_subject(conn, name)?;
+	let added: usize = formats::import_rows(conn, subject_id, &rows)?;
+	println!("Imported {} flashcards into {}. Skipped {} duplicates and {} invalid rows.", added, name, counts.duplicates, counts.invalid);
+	Ok(())
+}
+
+fn import_anki(conn: &Connection, file: &str, dry_run: bool) -> CommandResult {
+	let package: AnkiPackage = AnkiPackage::read(file)?;
+	if dry_run {
+		for line in package.summary() {
+			println!("{}", line);
+		}
+		println!("{} notes and {} cards. Each deck would be imported into the subject of the same name.", package.note_count(), package.card_count());
+		return Ok(());
+	}
+	let AnkiReport { cards, duplicates, skipped, media, subjects } = anki::import_package(conn, &package)?;
+	println!(
+		"Imported {} flashcards into {}. Skipped {} duplicate and {} unusable notes. Copied {} media files.",
+		cards, if subjects.is_empty() { "no subjects".to_owned() } else { subjects.join(", ") }, duplicates, skipped, media
+	);
+	Ok(())
+}
+
+fn import_markdown(deck: &Deck, file: &str, text: &str, subject: Option<&str>, dry_run: bool) -> CommandResult {
+	let conn: &Connection = deck.conn();
+	let file_deck: MarkdownDeck = MarkdownDeck::parse(text)?;
+	let name: String = match (subject, &file_deck.subject) {
+		(Some(name), _) => name.trim().to_owned(),
+		(None, Some(name)) => name.clone(),
+		(None, None) => return Err("The deck has no \"# \" heading naming its subject, give one with --subject.".into()),
+	};
+	let rows: Vec<PreviewRow> = file_deck.preview(conn, deck.find_subject(&name)?.map(|subject| subject.id))?;
+	print_skipped(&rows);
+	if dry_run {
+		let counts: PreviewCounts = formats::count_rows(&rows);
+		println!(
+			"Into {}: {} new, {} changed and {} unchanged cards. {} duplicates and {} invalid cards would be skipped.",
+			name, counts.ready, counts.updates, counts.unchanged, counts.duplicates, counts.invalid
+		);
+		return Ok(());
+	}
+
+	let MarkdownReport { added, updated, unchanged, skipped, ids } = markdown::import_deck(conn, &file_deck, &rows, &name)?;
+	println!("Into {}: added {}, changed {}, left {} unchanged and skipped {} cards.", name, added, updated, unchanged, skipped);
+	// The new cards' ids go into the file, so importing it again updates them
+	if !ids.is_empty() {
+		match std::fs::write(file, markdown::write_ids(text, &ids)) {
+			Ok(()) => println!("Wrote {} card ids into {}.", ids.len(), file),
+			Err(e) => eprintln!("Couldn't write the card ids into {} ({}), importing it again will add its new cards again.", file, e),
+		}
+	}
+	Ok(())
+}
+
+fn import_json(deck: &Deck, text: &str, subject: Option<&str>, dry_run: bool) -> CommandResult {
+	let conn: &Connection = deck.conn();
+	let mut file_deck: JsonDeck = JsonDeck::parse(text)?;
+	if let Some(name) = subject {
+		file_deck.subject.name = name.trim().to_owned();
+	}
+	let name: String = file_deck.subject.name.trim().to_owned();
+	let rows: Vec<PreviewRow> = file_deck.preview(conn, deck.find_subject(&name)?.map(|subject| subject.id))?;
+	if dry_run {
+		let counts: PreviewCounts = formats::count_rows(&rows);
+		println!("Into {}: {} new cards. {} cards already imported or duplicated would be skipped.", name, counts.ready, counts.duplicates);
+		return Ok(());
+	}
+
+	let JsonReport { added, reviews, skipped } = json::import_deck(conn, &file_deck, &rows)?;
+	println!("Into {}: added {} flashcards with {} reviews. Skipped {} cards already imported or duplicated.", name, added, reviews, skipped);
+	Ok(())
+}
+
+fn export(deck: &Deck, subject: &str, file: &str) -> CommandResult {
+	let conn: &Connection = deck.conn();
+	let subject: Subject = subject_named(deck, subject)?;
+	let lowercase: String = file.to_lowercase();
+	if lowercase.ends_with(".apkg") {
+		let AnkiExport { cards, media } = anki::export_package(conn, &subject, file)?;
+		println!("Exported {} flashcards and {} media files to {}.", cards, media, file);
+		return Ok(());
+	}
+
+	let (text, count): (String, usize) = if lowercase.ends_with(".md") || lowercase.ends_with(".markdown") {
+		markdown::export(conn, &subject)?
+	} else if lowercase.ends_with(".json") {
+		json::export(conn, &subject)?
+	} else {
+		delimited::export(conn, subject.id, delimited::delimiter_for_path(file))?
+	};
+	std::fs::write(file, text).map_err(|e| format!("Couldn't write {}: {}", file, e))?;
+	println!("Exported {} flashcards to {}.", count, file);
+	Ok(())
+}
+
+//* Asks for a grade, with Enter picking the suggested one. None at the end of the input */
+fn read_grade(suggested: Option<Grade>) -> io::Result<Option<Grade>> {
+	let prompt: String = match suggested {
+		Some(grade) => format!("Grade: 1 Again, 2 Hard, 3 Good, 4 Easy [{}] ", grade as i32),
+		None => "Grade: 1 Again, 2 Hard, 3 Good, 4 Easy ".to_owned(),
+	};
+	loop {
+		let Some(typed) = read_line(&prompt)? else { return Ok(None) };
+		match (typed.trim().parse::<i32>().ok().and_then(Grade::from_number), suggested) {
+			(Some(grade), _) => return Ok(Some(grade)),
+			(None, Some(grade)) if typed.trim().is_empty() => return Ok(Some(grade)),
+			_ => println!("Type a number from 1 to 4."),
+		}
+	}
+}
+
+fn review(deck: &Deck, subject: &str, limit: Option<usize>) -> CommandResult {
+	let conn: &Connection = deck.conn();
+	let subject: Subject = subject_named(deck, subject)?;
+	// Answer checking follows the window's settings, without making a settings file if there isn't one
+	let matching: MatchOptions = settings::read_settings().0.matching;
+	let mut session: RevisionSession = RevisionSession::new(conn, &subject)?;
+	let total: usize = limit.unwrap_or(usize::MAX).min(session.flashcards.len());
+	if total == 0 {
+		match flashcards::next_due(conn, subject.id)? {
+			Some(due) => println!("Nothing due in {}. The next card is due {}.", subject.name, due_date(due)),
+			None => println!("{} has no cards yet.", subject.name),
+		}
+		return Ok(());
+	}
+	println!("Revising {} cards of {}. Type q to stop early.", total, subject.name);
+
+	for number in 1..=total {
+		let Some(flashcard) = session.next_flashcard() else { break };
+		println!("\n[{}/{}] {}", number, total, flashcard.display_question());
+		let options: Vec<String> = session.options(&matching);
+		for (position, option) in options.iter().enumerate() {
+			println!("  {}. {}", position + 1, option);
+		}
+
+		let shown: Instant = Instant::now();
+		let Some(typed) = read_line("> ")? else { break };
+		if typed.trim() == "q" {
+			break;
+		}
+		let response_ms: i64 = shown.elapsed().as_millis() as i64;
+		// Options can be picked by number
+		let typed: String = match typed.trim().parse::<usize>() {
+			Ok(position) if (1..=options.len()).contains(&position) => options[position - 1].clone(),
+			_ => typed,
+		};
+
+		let (result, closest): (AnswerMatch, String) = session.check_answer(&typed, &matching);
+		let suggested: Option<Grade> = match result {
+			AnswerMatch::Exact => {
+				println!("Correct!");
+				Some(Grade::Good)
+			},
+			AnswerMatch::Close => {
+				println!("Close enough! The answer is {}.", closest);
+				Some(Grade::Good)
+			},
+			AnswerMatch::Wrong => {
+				println!("The answer is {}.", closest);
+				None
+			},
+		};
+		let Some(grade) = read_grade(suggested)? else { break };
+		let accuracy: f64 = session.record_answer(conn, grade, response_ms)?;
+		if let Some(flashcard) = session.current_flashcard() {
+			let interval: i32 = flashcard.schedule.interval;
+			println!(
+				"Graded {}, due again in {} {}. Accuracy on this card: {:.0}%.",
+				grade.name(), interval, if interval == 1 { "day" } else { "days" }, accuracy * 100.0
+			);
+		}
+	}
+
+	session.finish(conn)?;
+	let summary: RevisionSummary = session.summary(conn)?;
+	println!(
+		"\n{} cards practised, {} correct ({:.0}%). Subject accuracy {:.0}% -> {:.0}%.",
+		summary.cards_practised, summary.correct_total, summary.percent_accuracy(), summary.accuracy_before * 100.0, summary.accuracy_after * 100.0
+	);
+	for question in &summary.moved_up {
+		println!("Moved up: {}", question);
+	}
+	for question in &summary.moved_down {
+		println!("Moved down: {}", question);
+	}
+	Ok(())
+}
+
+fn stats(deck: &Deck, subject: Option<&str>) -> CommandResult {
+	let subjects: Vec<Subject> = match subject {
+		Some(name) => vec![subject_named(deck, name)?],
+		None => deck.subjects()?,
+	};
+
+	for subject in subjects {
+		let SubjectStats { cards, tiers, due, accuracy, reviews, next_due } = deck.stats(subject.id)?;
+		println!("{} ({})", subject.name, subject.algorithm.display_name());
+		println!("  Cards: {} ({} weak, {} learning, {} strong)", cards, tiers[0], tiers[1], tiers[2]);
+		println!("  Due today: {}", due);
+		println!("  Accuracy: {:.0}%", accuracy * 100.0);
+		println!("  Reviews: {}", reviews);
+		if let Some(next) = next_due.filter(|_| due == 0) {
+			println!("  Next due: {}", due_date(next));
+		}
+	}
+	Ok(())
+}
@@ -0,0 +1,117 @@
+use std::collections::HashSet;
+
+use rand::seq::SliceRandom; // Handles shuffling the options
+
+use crate::flashcards::Flashcard;
+use crate::matching::{self, AnswerMatch, MatchOptions};
+
+// Options shown for a multiple choice card, including the right one
+pub const OPTION_COUNT: usize = 4;
+
+/* **Explanation of option building**
+	- The card's own distractors are used if it has any, otherwise answers of other cards in the
+		subject are used (The pool)
+	- Anything that would be accepted as the answer (Or is close enough to count) is left out,
+		so picking a wrong option is never marked as right
+	- Options that match each other once normalised are only shown once
+	- Candidates are shuffled, and the first OPTION_COUNT - 1 that pass are mixed with the answer
+*/
+pub fn build_options(flashcard: &Flashcard, pool: &[String], options: &MatchOptions) -> Vec<String> {
+	let accepted: Vec<String> = flashcard.accepted_answers();
+	let source: &[String] = if flashcard.distractors.is_empty() { pool } else { &flashcard.distractors };
+
+	// Shuffled first, so only as many candidates as needed are checked
+	let mut rng: rand::prelude::ThreadRng = rand::thread_rng();
+	let mut candidates: Vec<&String> = source.iter().collect();
+	candidates.shuffle(&mut rng);
+
+	let mut wrong: Vec<String> = Vec::new();
+	let mut seen: HashSet<String> = HashSet::new(); // Normalised wrong options
+	for candidate in candidates {
+		if wrong.len() == OPTION_COUNT - 1 {
+			break;
+		}
+		let normalised: String = matching::normalise(candidate, options);
+		if normalised.is_empty() || seen.contains(&normalised) {
+			continue;
+		}
+		if matching::check_answer(candidate, &accepted, options).0 == AnswerMatch::Wrong {
+			seen.insert(normalised);
+			wrong.push(candidate.clone());
+		}
+	}
+
+	let mut built: Vec<String> = wrong;
+	built.push(flashcard.answer.clone());
+	built.shuffle(&mut rng);
+	built
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::scheduler::Schedule;
+
+	fn card(answer: &str, alternates: &[&str], distractors: &[&str]) -> Flashcard {
+		Flashcard {
+			primary_key: 1,
+			question: "?".to_owned(),
+			answer: answer.to_owned(),
+			alternates: alternates.iter().map(|alternate| alternate.to_string()).collect(),
+			cloze_index: 0,
+			multiple_choice: true,
+			reversed: false,
+			group_id: None,
+			note_id: None,
+			distractors: distractors.iter().map(|distractor| distractor.to_string()).collect(),
+			schedule: Schedule::default(),
+		}
+	}
+
+	fn pool(answers: &[&str]) -> Vec<String> {
+		answers.iter().map(|answer| answer.to_string()).collect()
+	}
+
+	#[test]
+	fn never_offers_accepted_or_close_answers() {
+		let flashcard: Flashcard = card("elephant", &["pachyderm"], &[]);
+		let pool: Vec<String> = pool(&["elephant", "The Elephant!", "elefant", "pachyderm", "mouse", "", "  "]);
+		for _ in 0..20 {
+			let built: Vec<String> = build_options(&flashcard, &pool, &MatchOptions::default());
+			assert_eq!(built.len(), 2, "{:?}", built);
+			assert!(built.contains(&"elephant".to_owned()) && built.contains(&"mouse".to_owned()));
+		}
+	}
+
+	#[test]
+	fn drops_repeated_options() {
+		let flashcard: Flashcard = card("chat", &[], &[]);
+		let pool: Vec<String> = pool(&["chien", "Chien", "chien!", "oiseau", "Oiseau"]);
+		for _ in 0..20 {
+			let mut built: Vec<String> = build_options(&flashcard, &pool, &MatchOptions::default());
+			assert_eq!(built.len(), 3, "{:?}", built);
+			built.sort_by_key(|option| option.to_lowercase());
+			assert_eq!(built[0], "chat");
+			assert_eq!(built[1].to_lowercase().trim_end_matches('!'), "chien");
+			assert_eq!(built[2].to_lowercase(), "oiseau");
+		}
+	}
+
+	#[test]
+	fn keeps_at_most_option_count() {
+		let flashcard: Flashcard = card("un", &[], &[]);
+		let pool: Vec<String> = pool(&["deux", "trois", "quatre", "cinq", "six", "sept"]);
+		let built: Vec<String> = build_options(&flashcard, &pool, &MatchOptions::default());
+		assert_eq!(built.len(), OPTION_COUNT);
+		assert!(built.contains(&"un".to_owned()));
+	}
+
+	#[test]
+	fn explicit_distractors_win_over_the_pool() {
+		let flashcard: Flashcard = card("Paris", &[], &["Lyon", "Marseille"]);
+		let pool: Vec<String> = pool(&["Berlin", "Madrid", "Rome", "Lisbon"]);
+		let mut built: Vec<String> = build_options(&flashcard, &pool, &MatchOptions::default());
+		built.sort();
+		assert_eq!(built, vec!["Lyon".to_owned(), "Marseille".to_owned(), "Paris".to_owned()]);
+	}
+}
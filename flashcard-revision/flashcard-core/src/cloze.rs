@@ -0,0 +1,176 @@
+/* **Explanation of cloze deletions**
+	- A cloze card is a sentence with parts hidden, written as {{c1::hidden text}}
+	- A hint can be shown in place of the blank with {{c1::hidden text::hint}}
+	- Every number (c1, c2...) becomes its own card, so "The {{c1::mitochondria}} is the
+		{{c2::powerhouse}}" makes two cards
+	- Deletions sharing a number are hidden together on the same card
+	- Only the card's own deletions are blanked out, the others are shown as normal text
+	- Deletions can't be nested, so a deletion with "{{" inside is kept as plain text around the
+		inner one
+*/
+
+// Part of a cloze text, either plain text or something that can be hidden
+#[derive(Clone, Debug, PartialEq)]
+pub enum Segment {
+	Text(String),
+	Deletion { index: i32, answer: String, hint: Option<String> },
+}
+
+//* Splits a cloze text into plain text and deletions. Unfinished markup is kept as plain text */
+pub fn parse(text: &str) -> Vec<Segment> {
+	let mut segments: Vec<Segment> = Vec::new();
+	let mut plain: String = String::new();
+	let mut rest: &str = text;
+
+	while let Some(start) = rest.find("{{c") {
+		plain.push_str(&rest[..start]);
+		let after: &str = &rest[start + 3..];
+		let digits: usize = after.chars().take_while(|character| character.is_ascii_digit()).count();
+		let deletion: Option<(i32, &str, usize)> = after[digits..].strip_prefix("::")
+			.and_then(|inside| inside.find("}}").map(|end| (&inside[..end], end)))
+			.filter(|(inside, _)| !inside.contains("{{")) // Nested, the inner deletion is used instead
+			.and_then(|(inside, end)| after[..digits].parse::<i32>().ok().map(|index| (index, inside, end)));
+
+		match deletion {
+			Some((index, inside, end)) if index > 0 => {
+				if !plain.is_empty() {
+					segments.push(Segment::Text(std::mem::take(&mut plain)));
+				}
+				let (answer, hint): (&str, Option<&str>) = match inside.split_once("::") {
+					Some((answer, hint)) => (answer, Some(hint)),
+					None => (inside, None),
+				};
+				segments.push(Segment::Deletion {
+					index,
+					answer: answer.to_owned(),
+					hint: hint.map(|hint| hint.to_owned()),
+				});
+				rest = &after[digits + 2 + end + 2..]; // Past "::", the deletion and "}}"
+			},
+			_ => {
+				plain.push_str("{{c");
+				rest = after;
+			}
+		}
+	}
+	plain.push_str(rest);
+	if !plain.is_empty() {
+		segments.push(Segment::Text(plain));
+	}
+	segments
+}
+
+//* Every deletion number in a text, smallest first. Empty if the text isn't a cloze */
+pub fn indexes(text: &str) -> Vec<i32> {
+	let mut found: Vec<i32> = parse(text).into_iter()
+		.filter_map(|segment| match segment {
+			Segment::Deletion { index, .. } => Some(index),
+			Segment::Text(_) => None,
+		})
+		.collect();
+	found.sort_unstable();
+	found.dedup();
+	found
+}
+
+//* The text with the deletions of one number blanked out (Showing their hint, if any) */
+pub fn question(text: &str, active: i32) -> String {
+	parse(text).into_iter()
+		.map(|segment| match segment {
+			Segment::Text(text) => text,
+			Segment::Deletion { index, hint, .. } if index == active => format!("[{}]", hint.unwrap_or_else(|| "...".to_owned())),
+			Segment::Deletion { answer, .. } => answer,
+		})
+		.collect()
+}
+
+//* What the deletions of one number hide, joined with commas when there is more than one */
+pub fn answer(text: &str, active: i32) -> String {
+	parse(text).into_iter()
+		.filter_map(|segment| match segment {
+			Segment::Deletion { index, answer, .. } if index == active => Some(answer),
+			_ => None,
+		})
+		.collect::<Vec<String>>()
+		.join(", ")
+}
+
+//* The text with the deletions of one number turned back into plain text, keeping the other markup */
+pub fn remove_deletion(text: &str, removed: i32) -> String {
+	parse(text).into_iter()
+		.map(|segment| match segment {
+			Segment::Text(text) => text,
+			Segment::Deletion { index, answer, .. } if index == removed => answer,
+			Segment::Deletion { index, answer, hint: Some(hint) } => format!("{{{{c{}::{}::{}}}}}", index, answer, hint),
+			Segment::Deletion { index, answer, hint: None } => format!("{{{{c{}::{}}}}}", index, answer),
+		})
+		.collect()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn text(text: &str) -> Segment {
+		Segment::Text(text.to_owned())
+	}
+
+	fn deletion(index: i32, answer: &str, hint: Option<&str>) -> Segment {
+		Segment::Deletion { index, answer: answer.to_owned(), hint: hint.map(|hint| hint.to_owned()) }
+	}
+
+	#[test]
+	fn parses_deletions_and_hints() {
+		assert_eq!(parse("The {{c1::mitochondria::organelle}} is the {{c2::powerhouse}}"), vec![
+			text("The "), deletion(1, "mitochondria", Some("organelle")), text(" is the "), deletion(2, "powerhouse", None),
+		]);
+		assert_eq!(parse("No deletions"), vec![text("No deletions")]);
+		assert_eq!(parse(""), Vec::new());
+		// Only the first "::" splits off the hint
+		assert_eq!(parse("{{c1::a::b::c}}"), vec![deletion(1, "a", Some("b::c"))]);
+	}
+
+	#[test]
+	fn hints_replace_the_blank() {
+		let cloze: &str = "{{c1::Paris::city}} is the capital of {{c2::France}}";
+		assert_eq!(question(cloze, 1), "[city] is the capital of France");
+		assert_eq!(question(cloze, 2), "Paris is the capital of [...]");
+		assert_eq!(answer(cloze, 1), "Paris");
+	}
+
+	#[test]
+	fn repeated_indexes_are_one_card() {
+		let cloze: &str = "{{c1::Red}} and {{c2::blue}} make {{c1::purple}}";
+		assert_eq!(indexes(cloze), vec![1, 2]);
+		assert_eq!(question(cloze, 1), "[...] and blue make [...]");
+		assert_eq!(answer(cloze, 1), "Red, purple");
+		assert_eq!(indexes("{{c3::a}} {{c1::b}} {{c3::c}}"), vec![1, 3]);
+	}
+
+	#[test]
+	fn unfinished_markup_is_plain_text() {
+		assert_eq!(parse("Open {{c1::forever"), vec![text("Open {{c1::forever")]);
+		assert_eq!(parse("{{c::x}} {{c0::y}} {{cx::z}}"), vec![text("{{c::x}} {{c0::y}} {{cx::z}}")]);
+		assert_eq!(parse("{{c1:single}} {{c2::ok}}"), vec![text("{{c1:single}} "), deletion(2, "ok", None)]);
+		assert!(indexes("Open {{c1::forever").is_empty());
+		assert_eq!(question("Open {{c1::forever", 1), "Open {{c1::forever");
+	}
+
+	#[test]
+	fn adjacent_and_nested_deletions() {
+		assert_eq!(parse("{{c1::a}}{{c2::b}}"), vec![deletion(1, "a", None), deletion(2, "b", None)]);
+		assert_eq!(question("{{c1::a}}{{c2::b}}", 2), "a[...]");
+
+		// Deletions can't be nested, so only the inner one is a deletion
+		assert_eq!(parse("{{c1::x {{c2::y}} z}}"), vec![text("{{c1::x "), deletion(2, "y", None), text(" z}}")]);
+		assert_eq!(indexes("{{c1::x {{c2::y::hint}} z}}"), vec![2]);
+	}
+
+	#[test]
+	fn removes_one_deletion_keeping_the_others() {
+		let cloze: &str = "{{c1::Paris::city}} is in {{c2::France}}, like {{c1::Lyon}}";
+		assert_eq!(remove_deletion(cloze, 1), "Paris is in {{c2::France}}, like Lyon");
+		assert_eq!(remove_deletion(cloze, 2), "{{c1::Paris::city}} is in France, like {{c1::Lyon}}");
+		assert_eq!(remove_deletion(cloze, 3), cloze);
+	}
+}
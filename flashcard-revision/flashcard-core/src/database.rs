@@ -0,0 +1,537 @@
+use chrono::Utc; // Handles backup names
+
+use log::info; // Handles logging (Shown by whichever frontend sets up a logger)
+
+use rusqlite::{ // Handles SQLite database
+	params,
+	Connection,
+	OptionalExtension,
+	Transaction,
+};
+
+use crate::scheduler::Algorithm;
+
+pub const DATABASE_PATH: &str = "flashcards.db"; // Next to wherever the app is run from
+
+// Every subject follows this structure
+#[derive(Clone, Debug)]
+pub struct Subject {
+	pub id: i64, // Primary key in the subjects table
+	pub name: String,
+	pub algorithm: Algorithm, // Schedules the subject's cards
+}
+
+/* ## Schema ##
+	- subjects holds one row per subject
+	- cards holds every flashcard of every subject, linked by subject_id
+	- notes hold the field values that some cards are made from, linked to their note type
+	- subject_tags holds the tags of each subject, kept in deck files
+	- Deleting a subject deletes its cards, notes and tags (ON DELETE CASCADE)
+Subject names are only ever stored as values, so any name (Spaces, quotes...) is safe.
+
+## Migrations ##
+The schema version is stored in PRAGMA user_version. Each entry of MIGRATIONS upgrades the
+database by one version, so MIGRATIONS[0] takes version 0 to 1 and so on. Never edit a
+migration once released, add a new one to the end instead. Databases at a higher version than
+this build knows were made by a newer one, so they are refused instead of being written to.
+	- Version 0: Empty database, or one made before versioning (deprecated_main.rs or main.rs)
+	- Version 1: subjects has a revision date for each category
+	- Version 2: Every flashcard is in the cards table instead of a table per subject
+	- Version 3: Cards are scheduled by SM-2, with their tier worked out from their interval
+	- Version 4: Subjects pick their algorithm (SM-2 or FSRS), answers are kept in a review log
+	- Version 5: The review log also keeps response times, the scheduler used and the intervals
+	- Version 6: Cards can accept alternate answers
+	- Version 7: Cards can be cloze deletions, with the cards made from one text grouped together
+	- Version 8: Cards can be multiple choice, with their own distractors
+	- Version 9: Cards can have a reverse sibling (Answer to question), grouped with them
+	- Version 10: Notes with user defined types (Fields and card templates) make cards
+	- Version 11: Cards can have an id that stays the same between databases, subjects can have tags
+*/
+type Migration = fn(&Transaction<'_>) -> rusqlite::Result<()>;
+const MIGRATIONS: [Migration; 11] = [
+	migrate_revision_dates,
+	migrate_cards_table,
+	migrate_sm2_schedule,
+	migrate_fsrs,
+	migrate_review_details,
+	migrate_alternate_answers,
+	migrate_cloze,
+	migrate_multiple_choice,
+	migrate_reverse,
+	migrate_notes,
+	migrate_card_ids,
+];
+
+//* Creates/opens the database and upgrades it to the latest schema */
+pub fn open_database(path: &str) -> rusqlite::Result<Connection> {
+	let mut conn: Connection = Connection::open(path)?;
+	migrate(&mut conn, path)?;
+	conn.execute_batch("PRAGMA foreign_keys = ON;")?; // Off by default in SQLite, needed for ON DELETE CASCADE
+	Ok(conn)
+}
+
+//* Runs every migration the database hasn't had yet, backing it up first */
+fn migrate(conn: &mut Connection, path: &str) -> rusqlite::Result<()> {
+	let version: usize = conn.query_row("PRAGMA user_version;", params![], |row: &rusqlite::Row<'_>| row.get(0))?;
+	if version == MIGRATIONS.len() {
+		return Ok(()); // Already up to date
+	}
+	if version > MIGRATIONS.len() {
+		// Writing to a schema this build doesn't know could lose cards, so the database isn't opened
+		return Err(rusqlite::Error::SqliteFailure(
+			rusqlite::ffi::Error::new(rusqlite::ffi::SQLITE_CANTOPEN),
+			Some(format!("{} was made by a newer version (Schema {}, this version knows up to {}), update to open it", path, version, MIGRATIONS.len())),
+		));
+	}
+
+	// Empty databases have nothing to lose, so only back up ones with tables in
+	let table_count: i64 = conn.query_row("SELECT COUNT(*) FROM sqlite_master WHERE type = 'table';", params![], |row: &rusqlite::Row<'_>| row.get(0))?;
+	if table_count > 0 {
+		let backup_path: String = format!("{}.v{}-{}.bak", path, version, Utc::now().timestamp());
+		conn.execute("VACUUM INTO ?1;", params![backup_path])?; // Can't be run inside a transaction
+		info!("Backed up database to {} before upgrading from version {}", backup_path, version);
+	}
+
+	// All or nothing - if any migration fails, the database is left exactly as it was
+	let transaction: Transaction<'_> = conn.transaction()?;
+	for (index, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+		migration(&transaction)?;
+		info!("Database upgraded to version {}", index + 1);
+	}
+	transaction.pragma_update(None, "user_version", MIGRATIONS.len())?;
+	transaction.commit()
+}
+
+//* Returns true if the table exists */
+fn table_exists(conn: &Connection, table: &str) -> rusqlite::Result<bool> {
+	conn.query_row(
+		"SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?1);",
+		params![table],
+		|row: &rusqlite::Row<'_>| row.get(0),
+	)
+}
+
+//* Returns true if the table has a column of this name */
+fn column_exists(conn: &Connection, table: &str, column: &str) -> rusqlite::Result<bool> {
+	conn.query_row(
+		"SELECT EXISTS(SELECT 1 FROM pragma_table_info(?1) WHERE name = ?2);",
+		params![table, column],
+		|row: &rusqlite::Row<'_>| row.get(0),
+	)
+}
+
+//* Version 0 to 1: Replaces date_last_revised with a revision date per category */
+fn migrate_revision_dates(conn: &Transaction<'_>) -> rusqlite::Result<()> {
+	if !table_exists(conn, "subjects")? {
+		conn.execute_batch("
+			CREATE TABLE subjects (
+				id INTEGER PRIMARY KEY,
+				name TEXT NOT NULL,
+				date_weak_revised INTEGER NOT NULL,
+				date_learning_revised INTEGER NOT NULL,
+				date_strong_revised INTEGER NOT NULL
+			); -- Stores dates as seconds since epoch
+		")?;
+		return Ok(());
+	}
+
+	if column_exists(conn, "subjects", "date_last_revised")? {
+		// SQLite can't drop NOT NULL columns in place, so the table is rebuilt
+		conn.execute_batch("
+			CREATE TABLE subjects_new (
+				id INTEGER PRIMARY KEY,
+				name TEXT NOT NULL,
+				date_weak_revised INTEGER NOT NULL,
+				date_learning_revised INTEGER NOT NULL,
+				date_strong_revised INTEGER NOT NULL
+			);
+			INSERT INTO subjects_new (id, name, date_weak_revised, date_learning_revised, date_strong_revised)
+				SELECT id, name, date_last_revised, date_last_revised, date_last_revised FROM subjects;
+			DROP TABLE subjects;
+			ALTER TABLE subjects_new RENAME TO subjects;
+		")?;
+	}
+	Ok(())
+}
+
+//* Version 1 to 2: Moves every per-subject table into the shared cards table */
+fn migrate_cards_table(conn: &Transaction<'_>) -> rusqlite::Result<()> {
+	conn.execute_batch("
+		CREATE TABLE IF NOT EXISTS cards (
+			id INTEGER PRIMARY KEY,
+			subject_id INTEGER NOT NULL REFERENCES subjects(id) ON DELETE CASCADE,
+			category INTEGER NOT NULL DEFAULT 0, -- 0 = weak, 1 = learning, 2 = strong
+			question TEXT NOT NULL,
+			answer TEXT NOT NULL,
+			correct INTEGER NOT NULL DEFAULT 0,
+			incorrect INTEGER NOT NULL DEFAULT 0
+		);
+
+		CREATE INDEX IF NOT EXISTS idx_subjects_name ON subjects(name);
+		CREATE INDEX IF NOT EXISTS idx_cards_subject_category ON cards(subject_id, category);
+	")?;
+
+	for subject in get_subjects(conn)? {
+		if subject.name == "subjects" || subject.name == "cards" || !table_exists(conn, &subject.name)? {
+			continue; // Subject never had any flashcards made
+		}
+
+		// Old tables were named after their subject, so the name has to be quoted as an identifier
+		let table: String = format!("\"{}\"", subject.name.replace('"', "\"\""));
+		let moved: usize = conn.execute(
+			&format!("INSERT INTO cards (subject_id, category, question, answer, correct, incorrect)
+				SELECT ?1, category, question, answer, correct, incorrect FROM {} ORDER BY id;", table),
+			params![subject.id],
+		)?;
+		conn.execute_batch(&format!("DROP TABLE {};", table))?;
+		info!("Moved {} flashcards of {} into the cards table", moved, subject.name);
+	}
+	Ok(())
+}
+
+//* Lists all created subjects stored in the database */
+pub fn get_subjects(conn: &Connection) -> rusqlite::Result<Vec<Subject>> {
+	// Older databases don't have an algorithm yet (Read while migrating)
+	let sql: &str = if column_exists(conn, "subjects", "scheduler")? {
+		"SELECT id, name, scheduler FROM subjects ORDER BY id;"
+	} else {
+		"SELECT id, name, 'sm2' FROM subjects ORDER BY id;"
+	};
+	let mut stmt: rusqlite::Statement<'_> = conn.prepare(sql)?;
+	let subjects = stmt.query_map(params![], |row: &rusqlite::Row<'_>| {
+		let algorithm: String = row.get(2)?;
+		Ok(Subject {
+			id: row.get(0)?,
+			name: row.get(1)?,
+			algorithm: Algorithm::from_name(&algorithm).unwrap_or(Algorithm::Sm2),
+		})
+	})?;
+
+	subjects.collect()
+}
+
+//* Returns the primary key of the subject with this name, adding it (With nothing revised yet) if there isn't one */
+pub fn get_or_add_subject(conn: &Connection, name: &str) -> rusqlite::Result<i64> {
+	let existing: Option<i64> = conn.query_row(
+		"SELECT id FROM subjects WHERE name = ?1 ORDER BY id LIMIT 1;",
+		params![name],
+		|row: &rusqlite::Row<'_>| row.get(0),
+	).optional()?;
+	if let Some(subject_id) = existing {
+		return Ok(subject_id);
+	}
+
+	let now: i64 = Utc::now().timestamp();
+	conn.execute(
+		"INSERT INTO subjects (name, date_weak_revised, date_learning_revised, date_strong_revised) VALUES (?1, ?2, ?2, ?2);",
+		params![name, now],
+	)?;
+	info!("Subject {} added", name);
+	Ok(conn.last_insert_rowid())
+}
+
+//* Reads the tags of a subject, in order */
+pub fn get_tags(conn: &Connection, subject_id: i64) -> rusqlite::Result<Vec<String>> {
+	let mut stmt: rusqlite::Statement<'_> = conn.prepare("SELECT tag FROM subject_tags WHERE subject_id = ?1 ORDER BY position;")?;
+	let tags = stmt.query_map(params![subject_id], |row: &rusqlite::Row<'_>| row.get(0))?;
+
+	tags.collect()
+}
+
+//* Replaces the tags of a subject */
+pub fn set_tags(conn: &Connection, subject_id: i64, tags: &[String]) -> rusqlite::Result<()> {
+	conn.execute("DELETE FROM subject_tags WHERE subject_id = ?1;", params![subject_id])?;
+	for (position, tag) in tags.iter().enumerate() {
+		conn.execute(
+			"INSERT INTO subject_tags (subject_id, position, tag) VALUES (?1, ?2, ?3);",
+			params![subject_id, position as i64, tag],
+		)?;
+	}
+	Ok(())
+}
+
+//* Removes a subject along with its cards, notes, tags and their review log (ON DELETE CASCADE) */
+pub fn remove_subject(conn: &Connection, subject_id: i64) -> rusqlite::Result<()> {
+	conn.execute("DELETE FROM subjects WHERE id = ?1;", params![subject_id])?;
+	Ok(())
+}
+
+//* Changes the algorithm that schedules a subject's cards */
+pub fn set_algorithm(conn: &Connection, subject_id: i64, algorithm: Algorithm) -> rusqlite::Result<()> {
+	conn.execute(
+		"UPDATE subjects SET scheduler = ?1 WHERE id = ?2;",
+		params![algorithm.name(), subject_id],
+	)?;
+	Ok(())
+}
+
+//* Version 2 to 3: Gives every card an SM-2 schedule in place of its stored category */
+fn migrate_sm2_schedule(conn: &Transaction<'_>) -> rusqlite::Result<()> {
+	conn.execute_batch("
+		ALTER TABLE cards ADD COLUMN ease_factor REAL NOT NULL DEFAULT 2.5;
+		ALTER TABLE cards ADD COLUMN interval INTEGER NOT NULL DEFAULT 0; -- Days
+		ALTER TABLE cards ADD COLUMN repetitions INTEGER NOT NULL DEFAULT 0;
+		ALTER TABLE cards ADD COLUMN due INTEGER NOT NULL DEFAULT 0; -- Seconds since epoch
+
+		-- Weak cards stay new (Due straight away). Learning and strong cards get the shortest
+		-- interval of their tier, due that long after their tier of the subject was last revised
+		UPDATE cards SET interval = 6, repetitions = 2,
+			due = (SELECT date_learning_revised FROM subjects WHERE subjects.id = cards.subject_id) + 6 * 86400
+			WHERE category = 1;
+		UPDATE cards SET interval = 21, repetitions = 3,
+			due = (SELECT date_strong_revised FROM subjects WHERE subjects.id = cards.subject_id) + 21 * 86400
+			WHERE category = 2;
+
+		DROP INDEX IF EXISTS idx_cards_subject_category;
+		ALTER TABLE cards DROP COLUMN category;
+		CREATE INDEX idx_cards_subject_due ON cards(subject_id, due);
+	")
+}
+
+//* Version 3 to 4: Adds FSRS memory state, each subject's algorithm and the review log */
+fn migrate_fsrs(conn: &Transaction<'_>) -> rusqlite::Result<()> {
+	conn.execute_batch("
+		ALTER TABLE subjects ADD COLUMN scheduler TEXT NOT NULL DEFAULT 'sm2'; -- sm2 or fsrs
+
+		ALTER TABLE cards ADD COLUMN stability REAL NOT NULL DEFAULT 0; -- Days
+		ALTER TABLE cards ADD COLUMN difficulty REAL NOT NULL DEFAULT 0;
+		ALTER TABLE cards ADD COLUMN last_review INTEGER NOT NULL DEFAULT 0; -- Seconds since epoch
+		UPDATE cards SET last_review = due - interval * 86400 WHERE interval > 0;
+
+		CREATE TABLE reviews (
+			id INTEGER PRIMARY KEY,
+			card_id INTEGER NOT NULL REFERENCES cards(id) ON DELETE CASCADE,
+			reviewed_at INTEGER NOT NULL, -- Seconds since epoch
+			grade INTEGER NOT NULL -- 1 = again, 2 = hard, 3 = good, 4 = easy
+		);
+		CREATE INDEX idx_reviews_card ON reviews(card_id, reviewed_at);
+
+		CREATE TABLE fsrs_weights (
+			id INTEGER PRIMARY KEY CHECK (id = 1), -- Only ever one row
+			weights TEXT NOT NULL, -- Comma separated
+			reviews INTEGER NOT NULL, -- Reviews the weights were fitted to
+			optimised_at INTEGER NOT NULL
+		);
+	")
+}
+
+//* Version 4 to 5: Stores everything about each review, not just its grade */
+fn migrate_review_details(conn: &Transaction<'_>) -> rusqlite::Result<()> {
+	// Reviews logged before this have no timings, so they get 0 (Unknown)
+	conn.execute_batch("
+		ALTER TABLE reviews ADD COLUMN response_ms INTEGER NOT NULL DEFAULT 0;
+		ALTER TABLE reviews ADD COLUMN scheduler TEXT NOT NULL DEFAULT 'sm2'; -- sm2 or fsrs
+		ALTER TABLE reviews ADD COLUMN interval_before INTEGER NOT NULL DEFAULT 0; -- Days
+		ALTER TABLE reviews ADD COLUMN interval_after INTEGER NOT NULL DEFAULT 0; -- Days
+	")
+}
+
+//* Version 5 to 6: Adds alternate answers, kept in order for the editor */
+fn migrate_alternate_answers(conn: &Transaction<'_>) -> rusqlite::Result<()> {
+	conn.execute_batch("
+		CREATE TABLE alternate_answers (
+			id INTEGER PRIMARY KEY,
+			card_id INTEGER NOT NULL REFERENCES cards(id) ON DELETE CASCADE,
+			position INTEGER NOT NULL,
+			answer TEXT NOT NULL
+		);
+		CREATE INDEX idx_alternate_answers_card ON alternate_answers(card_id, position);
+	")
+}
+
+//* Version 6 to 7: Adds cloze deletions. Existing cards are all question/answer cards */
+fn migrate_cloze(conn: &Transaction<'_>) -> rusqlite::Result<()> {
+	conn.execute_batch("
+		ALTER TABLE cards ADD COLUMN cloze_index INTEGER NOT NULL DEFAULT 0; -- 0 = question/answer card, otherwise the cN it hides
+		ALTER TABLE cards ADD COLUMN group_id INTEGER; -- Primary key of the first card made from the same text (NULL = on its own)
+		CREATE INDEX idx_cards_group ON cards(group_id);
+	")
+}
+
+//* Version 7 to 8: Adds multiple choice cards. Existing cards are all typed */
+fn migrate_multiple_choice(conn: &Transaction<'_>) -> rusqlite::Result<()> {
+	conn.execute_batch("
+		ALTER TABLE cards ADD COLUMN multiple_choice INTEGER NOT NULL DEFAULT 0; -- 0 = typed answer, 1 = pick from options
+		CREATE TABLE distractors (
+			id INTEGER PRIMARY KEY,
+			card_id INTEGER NOT NULL REFERENCES cards(id) ON DELETE CASCADE,
+			position INTEGER NOT NULL,
+			distractor TEXT NOT NULL -- A wrong option
+		);
+		CREATE INDEX idx_distractors_card ON distractors(card_id, position);
+	")
+}
+
+//* Version 8 to 9: Adds reverse cards, which use the groups added in version 7 */
+fn migrate_reverse(conn: &Transaction<'_>) -> rusqlite::Result<()> {
+	conn.execute_batch("
+		ALTER TABLE cards ADD COLUMN reversed INTEGER NOT NULL DEFAULT 0; -- 1 = answer to question sibling of another card in its group
+	")
+}
+
+//* Version 9 to 10: Adds notes and note types. Existing cards aren't made from a note, so they are edited as they are */
+fn migrate_notes(conn: &Transaction<'_>) -> rusqlite::Result<()> {
+	conn.execute_batch("
+		CREATE TABLE note_types (
+			id INTEGER PRIMARY KEY,
+			name TEXT NOT NULL
+		);
+		CREATE TABLE note_fields (
+			id INTEGER PRIMARY KEY,
+			note_type_id INTEGER NOT NULL REFERENCES note_types(id) ON DELETE CASCADE,
+			position INTEGER NOT NULL,
+			name TEXT NOT NULL
+		);
+		CREATE TABLE card_templates (
+			id INTEGER PRIMARY KEY,
+			note_type_id INTEGER NOT NULL REFERENCES note_types(id) ON DELETE CASCADE,
+			position INTEGER NOT NULL,
+			name TEXT NOT NULL,
+			front TEXT NOT NULL, -- {{Field}} is replaced by the note's value
+			back TEXT NOT NULL
+		);
+		CREATE TABLE notes (
+			id INTEGER PRIMARY KEY,
+			subject_id INTEGER NOT NULL REFERENCES subjects(id) ON DELETE CASCADE,
+			note_type_id INTEGER NOT NULL REFERENCES note_types(id) -- Note types can't be deleted while in use
+		);
+		CREATE TABLE note_values (
+			id INTEGER PRIMARY KEY,
+			note_id INTEGER NOT NULL REFERENCES notes(id) ON DELETE CASCADE,
+			position INTEGER NOT NULL, -- Matches the position of the field
+			value TEXT NOT NULL
+		);
+		CREATE INDEX idx_note_values_note ON note_values(note_id, position);
+
+		ALTER TABLE cards ADD COLUMN note_id INTEGER REFERENCES notes(id) ON DELETE CASCADE; -- NULL = not made from a note
+		ALTER TABLE cards ADD COLUMN template_position INTEGER NOT NULL DEFAULT 0; -- Card template that made the card
+		CREATE INDEX idx_cards_note ON cards(note_id);
+
+		-- A note type to start from, making cards both ways from a word and its meaning
+		INSERT INTO note_types (id, name) VALUES (1, 'Vocabulary');
+		INSERT INTO note_fields (note_type_id, position, name) VALUES (1, 0, 'Word'), (1, 1, 'Meaning'), (1, 2, 'Example');
+		INSERT INTO card_templates (note_type_id, position, name, front, back) VALUES
+			(1, 0, 'Recognition', '{{Word}}', '{{Meaning}}'),
+			(1, 1, 'Recall', '{{Meaning}}', '{{Word}}');
+	")
+}
+
+//* Version 10 to 11: Adds card ids kept in deck files, so importing an edited file updates its cards, and subject tags */
+fn migrate_card_ids(conn: &Transaction<'_>) -> rusqlite::Result<()> {
+	conn.execute_batch("
+		ALTER TABLE cards ADD COLUMN uid TEXT; -- NULL until the card is first written to a deck file
+		CREATE UNIQUE INDEX idx_cards_uid ON cards(uid);
+
+		CREATE TABLE subject_tags (
+			id INTEGER PRIMARY KEY,
+			subject_id INTEGER NOT NULL REFERENCES subjects(id) ON DELETE CASCADE,
+			position INTEGER NOT NULL,
+			tag TEXT NOT NULL
+		);
+		CREATE INDEX idx_subject_tags_subject ON subject_tags(subject_id, position);
+	")
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	//* A database path in the temp folder, with any left over from an earlier run removed */
+	fn temp_path(name: &str) -> String {
+		let path: String = std::env::temp_dir().join(format!("flashcard-core-{}-{}.db", name, std::process::id())).to_string_lossy().into_owned();
+		remove_with_backups(&path);
+		path
+	}
+
+	fn backups(path: &str) -> Vec<std::path::PathBuf> {
+		let name: String = format!("{}.v", std::path::Path::new(path).file_name().unwrap().to_string_lossy());
+		std::fs::read_dir(std::env::temp_dir()).unwrap()
+			.filter_map(|entry| entry.ok().map(|entry| entry.path()))
+			.filter(|entry| entry.file_name().is_some_and(|file| file.to_string_lossy().starts_with(&name)))
+			.collect()
+	}
+
+	fn remove_with_backups(path: &str) {
+		let _ = std::fs::remove_file(path);
+		for backup in backups(path) {
+			let _ = std::fs::remove_file(backup);
+		}
+	}
+
+	fn version(conn: &Connection) -> usize {
+		conn.query_row("PRAGMA user_version;", params![], |row: &rusqlite::Row<'_>| row.get(0)).unwrap()
+	}
+
+	#[test]
+	fn creates_new_databases_at_the_latest_version() {
+		let conn: Connection = open_database(":memory:").unwrap();
+		assert_eq!(version(&conn), MIGRATIONS.len());
+		assert!(table_exists(&conn, "cards").unwrap());
+		assert!(get_subjects(&conn).unwrap().is_empty());
+	}
+
+	#[test]
+	fn migrates_the_baseline_schema() {
+		let path: String = temp_path("baseline");
+		{
+			// As made by deprecated_main.rs, with a table per subject
+			let conn: Connection = Connection::open(&path).unwrap();
+			conn.execute_batch("
+				CREATE TABLE subjects (id INTEGER PRIMARY KEY, name TEXT NOT NULL, date_last_revised INTEGER NOT NULL);
+				INSERT INTO subjects (id, name, date_last_revised) VALUES (1, 'French', 1700000000), (2, 'Empty \"quoted\"', 1700000000);
+				CREATE TABLE French (
+					id INTEGER PRIMARY KEY, category INTEGER NOT NULL, question TEXT NOT NULL,
+					answer TEXT NOT NULL, correct INTEGER NOT NULL, incorrect INTEGER NOT NULL
+				);
+				INSERT INTO French (category, question, answer, correct, incorrect) VALUES
+					(0, 'chat', 'cat', 1, 2), (1, 'chien', 'dog', 3, 0), (2, 'oiseau', 'bird', 5, 1);
+			").unwrap();
+		}
+
+		let conn: Connection = open_database(&path).unwrap();
+		assert_eq!(version(&conn), MIGRATIONS.len());
+		assert!(!table_exists(&conn, "French").unwrap());
+		assert!(!column_exists(&conn, "subjects", "date_last_revised").unwrap());
+		let names: Vec<String> = get_subjects(&conn).unwrap().into_iter().map(|subject| subject.name).collect();
+		assert_eq!(names, vec!["French".to_owned(), "Empty \"quoted\"".to_owned()]);
+
+		let cards: Vec<(String, i32, i32, i64)> = conn.prepare("SELECT question, correct, interval, due FROM cards WHERE subject_id = 1 ORDER BY id;").unwrap()
+			.query_map(params![], |row: &rusqlite::Row<'_>| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))).unwrap()
+			.collect::<rusqlite::Result<_>>().unwrap();
+		assert_eq!(cards, vec![
+			("chat".to_owned(), 1, 0, 0),
+			("chien".to_owned(), 3, 6, 1700000000 + 6 * 86400),
+			("oiseau".to_owned(), 5, 21, 1700000000 + 21 * 86400),
+		]);
+
+		// The backup is the database as it was before upgrading
+		let backups: Vec<std::path::PathBuf> = backups(&path);
+		assert_eq!(backups.len(), 1);
+		assert!(backups[0].to_string_lossy().contains(".v0-"));
+		let backup: Connection = Connection::open(&backups[0]).unwrap();
+		assert_eq!(version(&backup), 0);
+		assert!(table_exists(&backup, "French").unwrap());
+		drop(backup);
+
+		// Opening it again has nothing left to do, so doesn't back it up again
+		drop(conn);
+		open_database(&path).unwrap();
+		assert_eq!(self::backups(&path).len(), 1);
+		remove_with_backups(&path);
+	}
+
+	#[test]
+	fn refuses_databases_from_newer_versions() {
+		let path: String = temp_path("newer");
+		{
+			let conn: Connection = open_database(&path).unwrap();
+			conn.pragma_update(None, "user_version", MIGRATIONS.len() + 1).unwrap();
+		}
+
+		let error: rusqlite::Error = open_database(&path).err().unwrap();
+		assert!(error.to_string().contains("made by a newer version"));
+		let conn: Connection = Connection::open(&path).unwrap();
+		assert_eq!(version(&conn), MIGRATIONS.len() + 1); // Left alone
+		remove_with_backups(&path);
+	}
+}
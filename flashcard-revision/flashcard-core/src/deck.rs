@@ -0,0 +1,306 @@
+use rusqlite::Connection; // Handles SQLite database
+
+use crate::database::{self, Subject};
+use crate::flashcards::{self, Flashcard, FlashcardEdit, FlashcardSnapshot};
+use crate::formats::ImportCard;
+use crate::reviews;
+use crate::scheduler::{self, Algorithm};
+
+/* **Explanation of the deck repository**
+A Deck owns the connection to one database, opened and upgraded by `Deck::open`. Frontends go
+through it for subjects and their flashcards instead of each writing their own queries:
+	- Subjects are looked up by name, since that is what users type
+	- Flashcards are added from an `ImportCard`, so a missing answer makes a cloze text's cards
+	- Anything it doesn't wrap (Notes, import/export, revision sessions, note types...) takes
+		`Deck::conn`, like every module's free functions
+*/
+pub struct Deck {
+	conn: Connection,
+}
+
+// Numbers shown about a subject
+#[derive(Clone, Debug, PartialEq)]
+pub struct SubjectStats {
+	pub cards: usize,
+	pub tiers: [usize; 3], // Cards in each tier (Weak, learning, strong)
+	pub due: usize, // Due by the end of today, counting siblings that would be buried
+	pub accuracy: f64, // 0.0 to 1.0
+	pub reviews: usize,
+	pub next_due: Option<i64>, // Seconds since epoch, None if the subject has no cards
+}
+
+impl Deck {
+	//* Creates/opens the database at `path` and upgrades it to the latest schema */
+	pub fn open(path: &str) -> rusqlite::Result<Deck> {
+		Ok(Deck { conn: database::open_database(path)? })
+	}
+
+	//* The connection, for the module functions the deck doesn't wrap */
+	pub fn conn(&self) -> &Connection {
+		&self.conn
+	}
+
+	pub fn subjects(&self) -> rusqlite::Result<Vec<Subject>> {
+		database::get_subjects(&self.conn)
+	}
+
+	//* The subject with this name (Ignoring spaces around it), if there is one */
+	pub fn find_subject(&self, name: &str) -> rusqlite::Result<Option<Subject>> {
+		Ok(self.subjects()?.into_iter().find(|subject| subject.name == name.trim()))
+	}
+
+	//* Adds a subject, returning None if there is already one with this name */
+	pub fn add_subject(&self, name: &str, algorithm: Algorithm, tags: &[String]) -> rusqlite::Result<Option<Subject>> {
+		if self.find_subject(name)?.is_some() {
+			return Ok(None);
+		}
+		let transaction: rusqlite::Transaction<'_> = self.conn.unchecked_transaction()?;
+		let subject_id: i64 = database::get_or_add_subject(&transaction, name.trim())?;
+		database::set_algorithm(&transaction, subject_id, algorithm)?;
+		database::set_tags(&transaction, subject_id, tags)?;
+		transaction.commit()?;
+		Ok(Some(Subject { id: subject_id, name: name.trim().to_owned(), algorithm }))
+	}
+
+	//* Removes a subject with its cards, notes, tags and review log */
+	pub fn remove_subject(&self, subject_id: i64) -> rusqlite::Result<()> {
+		database::remove_subject(&self.conn, subject_id)
+	}
+
+	//* Every flashcard of a subject, oldest first */
+	pub fn flashcards(&self, subject_id: i64) -> rusqlite::Result<Vec<Flashcard>> {
+		flashcards::load_all_flashcards(&self.conn, subject_id)
+	}
+
+	//* How many flashcards a subject has, and how many are due by the end of today */
+	pub fn count_flashcards(&self, subject_id: i64) -> rusqlite::Result<(usize, usize)> {
+		Ok((
+			flashcards::count_flashcards(&self.conn, subject_id, i64::MAX)?,
+			flashcards::count_flashcards(&self.conn, subject_id, scheduler::end_of_today())?,
+		))
+	}
+
+	//* Flashcards of a subject due by the end of today, most overdue first */
+	pub fn due_flashcards(&self, subject_id: i64) -> rusqlite::Result<Vec<Flashcard>> {
+		flashcards::load_due_flashcards(&self.conn, subject_id, scheduler::end_of_today())
+	}
+
+	/* Adds a card to a subject (Due straight away), with its answer to question sibling if `reverse`,
+	returning the primary keys made. Cloze texts (No answer) get one card per deletion and no reverse. */
+	pub fn add_flashcard(&self, subject_id: i64, card: &ImportCard, reverse: bool) -> rusqlite::Result<Vec<i64>> {
+		let transaction: rusqlite::Transaction<'_> = self.conn.unchecked_transaction()?;
+		let primary_keys: Vec<i64> = if card.is_cloze() {
+			flashcards::add_cloze_flashcards(&transaction, subject_id, &card.question)?
+		} else {
+			let primary_key: i64 = flashcards::add_new_flashcard(&transaction, subject_id, &card.question, &card.answer, &card.alternates)?;
+			flashcards::set_multiple_choice(&transaction, primary_key as i32, card.multiple_choice, &card.distractors)?;
+			if reverse {
+				vec![primary_key, flashcards::add_reverse_flashcard(&transaction, primary_key as i32)?]
+			} else {
+				vec![primary_key]
+			}
+		};
+		transaction.commit()?;
+		Ok(primary_keys)
+	}
+
+	//* Every stored field of a flashcard, None if there is no card with this primary key */
+	pub fn flashcard(&self, primary_key: i32) -> rusqlite::Result<Option<FlashcardSnapshot>> {
+		match flashcards::load_snapshot(&self.conn, primary_key) {
+			Ok(snapshot) => Ok(Some(snapshot)),
+			Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+			Err(e) => Err(e),
+		}
+	}
+
+	/* Edits a flashcard, returning it as stored or what is wrong with the edit.
+	Editing a cloze card edits its whole group */
+	pub fn edit_flashcard(&self, before: &FlashcardSnapshot, edit: &FlashcardEdit) -> Result<FlashcardSnapshot, String> {
+		let after: FlashcardSnapshot = edit.apply(before)?;
+		flashcards::save_edit(&self.conn, &after).map_err(|e| format!("Couldn't save the flashcard: {}", e))?;
+		Ok(after)
+	}
+
+	//* Removes a flashcard, or the whole note of a card made from one */
+	pub fn remove_flashcard(&self, primary_key: i32) -> rusqlite::Result<()> {
+		flashcards::remove_flashcard(&self.conn, primary_key)
+	}
+
+	pub fn stats(&self, subject_id: i64) -> rusqlite::Result<SubjectStats> {
+		let cards: Vec<Flashcard> = self.flashcards(subject_id)?;
+		let mut tiers: [usize; 3] = [0; 3];
+		for flashcard in &cards {
+			tiers[flashcard.category() as usize] += 1;
+		}
+
+		Ok(SubjectStats {
+			cards: cards.len(),
+			tiers,
+			due: self.due_flashcards(subject_id)?.len(),
+			accuracy: flashcards::get_subject_accuracy(&self.conn, subject_id)?,
+			reviews: reviews::count_subject_reviews(&self.conn, subject_id)?,
+			next_due: flashcards::next_due(&self.conn, subject_id)?,
+		})
+	}
+}
+
+//* The subject shown in a row (From 0) of a page (From 0) of the subject list, if the list reaches it */
+pub fn subject_at(subjects: &[Subject], page: usize, per_page: usize, row: usize) -> Option<&Subject> {
+	if row >= per_page {
+		return None;
+	}
+	subjects.get(page * per_page + row)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::reviews::Review;
+	use crate::scheduler::{Grade, Schedule};
+
+	fn subjects(names: &[&str]) -> Vec<Subject> {
+		names.iter().enumerate().map(|(index, name)| Subject { id: index as i64 + 1, name: name.to_string(), algorithm: Algorithm::Sm2 }).collect()
+	}
+
+	#[test]
+	fn opens_an_empty_deck() {
+		let deck: Deck = Deck::open(":memory:").unwrap();
+		assert!(deck.subjects().unwrap().is_empty());
+	}
+
+	#[test]
+	fn adds_subjects_once() {
+		let deck: Deck = Deck::open(":memory:").unwrap();
+		let subject: Subject = deck.add_subject(" French ", Algorithm::Fsrs, &["languages".to_owned()]).unwrap().unwrap();
+		assert_eq!(subject.name, "French");
+		assert_eq!(subject.algorithm, Algorithm::Fsrs);
+		assert!(deck.add_subject("French", Algorithm::Sm2, &[]).unwrap().is_none());
+
+		assert_eq!(deck.subjects().unwrap().len(), 1);
+		assert_eq!(deck.find_subject("French ").unwrap().unwrap().id, subject.id);
+		assert_eq!(database::get_tags(deck.conn(), subject.id).unwrap(), vec!["languages".to_owned()]);
+	}
+
+	#[test]
+	fn adds_cards_with_reverses_and_cloze_deletions() {
+		let deck: Deck = Deck::open(":memory:").unwrap();
+		let subject: Subject = deck.add_subject("French", Algorithm::Sm2, &[]).unwrap().unwrap();
+
+		let mut card: ImportCard = ImportCard::new("chat", "cat");
+		card.alternates = vec!["kitten".to_owned()];
+		let primary_keys: Vec<i64> = deck.add_flashcard(subject.id, &card, true).unwrap();
+		assert_eq!(primary_keys.len(), 2);
+		let cloze_keys: Vec<i64> = deck.add_flashcard(subject.id, &ImportCard::new("Le {{c1::chat}} est {{c2::noir}}", ""), true).unwrap();
+		assert_eq!(cloze_keys.len(), 2); // No reverse for cloze texts
+
+		let cards: Vec<Flashcard> = deck.flashcards(subject.id).unwrap();
+		assert_eq!(cards.len(), 4);
+		assert_eq!(cards[0].alternates, vec!["kitten".to_owned()]);
+		assert!(cards[1].reversed);
+		assert_eq!((cards[1].question.as_str(), cards[1].answer.as_str()), ("cat", "chat"));
+		assert_eq!(cards[3].cloze_index, 2);
+		assert_eq!(deck.due_flashcards(subject.id).unwrap().len(), 4);
+	}
+
+	#[test]
+	fn removes_cards_and_subjects() {
+		let deck: Deck = Deck::open(":memory:").unwrap();
+		let subject: Subject = deck.add_subject("French", Algorithm::Sm2, &[]).unwrap().unwrap();
+		let primary_keys: Vec<i64> = deck.add_flashcard(subject.id, &ImportCard::new("chat", "cat"), false).unwrap();
+		deck.add_flashcard(subject.id, &ImportCard::new("chien", "dog"), false).unwrap();
+
+		deck.remove_flashcard(primary_keys[0] as i32).unwrap();
+		let cards: Vec<Flashcard> = deck.flashcards(subject.id).unwrap();
+		assert_eq!(cards.len(), 1);
+		assert_eq!(cards[0].question, "chien");
+
+		deck.remove_subject(subject.id).unwrap();
+		assert!(deck.subjects().unwrap().is_empty());
+		assert!(deck.flashcards(subject.id).unwrap().is_empty());
+	}
+
+	#[test]
+	fn stats_count_cards_and_reviews() {
+		let deck: Deck = Deck::open(":memory:").unwrap();
+		let subject: Subject = deck.add_subject("French", Algorithm::Sm2, &[]).unwrap().unwrap();
+		let empty: SubjectStats = deck.stats(subject.id).unwrap();
+		assert_eq!((empty.cards, empty.due, empty.reviews, empty.next_due), (0, 0, 0, None));
+
+		let primary_keys: Vec<i64> = deck.add_flashcard(subject.id, &ImportCard::new("chat", "cat"), true).unwrap();
+		flashcards::record_answer(deck.conn(), primary_keys[0] as i32, true).unwrap();
+		flashcards::record_answer(deck.conn(), primary_keys[1] as i32, false).unwrap();
+		let review: Review = Review {
+			card_id: primary_keys[0] as i32, reviewed_at: 0, grade: Grade::Good, response_ms: 1500,
+			algorithm: Algorithm::Sm2, interval_before: 0, interval_after: 1,
+		};
+		reviews::log_review(deck.conn(), &review).unwrap();
+		// Reviews of other subjects aren't counted
+		let other: Subject = deck.add_subject("German", Algorithm::Sm2, &[]).unwrap().unwrap();
+		let other_keys: Vec<i64> = deck.add_flashcard(other.id, &ImportCard::new("Katze", "cat"), false).unwrap();
+		reviews::log_review(deck.conn(), &Review { card_id: other_keys[0] as i32, ..review }).unwrap();
+
+		let stats: SubjectStats = deck.stats(subject.id).unwrap();
+		assert_eq!(stats.cards, 2);
+		assert_eq!(stats.tiers, [2, 0, 0]);
+		assert_eq!(stats.due, 2);
+		assert_eq!(stats.reviews, 1);
+		assert!((stats.accuracy - 0.5).abs() < 1e-9);
+		assert!(stats.next_due.is_some());
+	}
+
+	#[test]
+	fn finds_subjects_by_page_and_row() {
+		let subjects: Vec<Subject> = subjects(&["a", "b", "c", "d", "e", "f", "g"]);
+		assert_eq!(subject_at(&subjects, 0, 3, 0).unwrap().name, "a");
+		assert_eq!(subject_at(&subjects, 0, 3, 2).unwrap().name, "c");
+		assert_eq!(subject_at(&subjects, 1, 3, 0).unwrap().name, "d");
+		assert_eq!(subject_at(&subjects, 2, 3, 0).unwrap().name, "g");
+		assert!(subject_at(&subjects, 2, 3, 1).is_none()); // Past the end of the list
+		assert!(subject_at(&subjects, 0, 3, 3).is_none()); // Below the last row of the page
+		assert!(subject_at(&[], 0, 6, 0).is_none());
+	}
+
+	#[test]
+	fn edits_cards_and_refuses_bad_edits() {
+		let deck: Deck = Deck::open(":memory:").unwrap();
+		let subject: Subject = deck.add_subject("French", Algorithm::Sm2, &[]).unwrap().unwrap();
+		let plain: i32 = deck.add_flashcard(subject.id, &ImportCard::new("chat", "cat"), false).unwrap()[0] as i32;
+		let clozes: Vec<i64> = deck.add_flashcard(subject.id, &ImportCard::new("Le {{c1::chat}} est {{c2::noir}}", ""), false).unwrap();
+		assert!(deck.flashcard(999).unwrap().is_none());
+
+		let before: FlashcardSnapshot = deck.flashcard(plain).unwrap().unwrap();
+		let edit: FlashcardEdit = FlashcardEdit { answer: Some(" cat ".to_owned()), alternates: Some(vec!["kitty".to_owned()]), multiple_choice: Some(true), ..FlashcardEdit::default() };
+		let after: FlashcardSnapshot = deck.edit_flashcard(&before, &edit).unwrap();
+		assert_eq!(deck.flashcard(plain).unwrap().unwrap(), after);
+		assert_eq!((after.question.as_str(), after.answer.as_str(), after.multiple_choice), ("chat", "cat", true));
+		assert_eq!(after.schedule, before.schedule);
+
+		let cloze: FlashcardSnapshot = deck.flashcard(clozes[1] as i32).unwrap().unwrap();
+		let retext: FlashcardEdit = FlashcardEdit { question: Some("Le {{c1::chien}} est {{c2::blanc}}".to_owned()), ..FlashcardEdit::default() };
+		assert_eq!(deck.edit_flashcard(&cloze, &retext).unwrap().answer, "blanc");
+		assert_eq!(deck.flashcard(clozes[0] as i32).unwrap().unwrap().answer, "chien"); // The whole group follows
+
+		let cases: [(&FlashcardSnapshot, FlashcardEdit, &str); 3] = [
+			(&before, FlashcardEdit { answer: Some(" ".to_owned()), ..FlashcardEdit::default() }, "Flashcards need a question and an answer."),
+			(&cloze, FlashcardEdit { answer: Some("noir".to_owned()), ..FlashcardEdit::default() }, "Cloze cards have no answer of their own, change the {{c1::...}} deletions in the question instead."),
+			(&cloze, FlashcardEdit { question: Some("Le {{c1::chat}} est noir".to_owned()), ..FlashcardEdit::default() }, "This card is for {{c2::...}}, so the text has to keep it."),
+		];
+		for (card, edit, problem) in cases {
+			assert_eq!(deck.edit_flashcard(card, &edit), Err(problem.to_owned()));
+		}
+		let from_note: FlashcardSnapshot = FlashcardSnapshot { note_id: Some(1), ..before };
+		assert!(deck.edit_flashcard(&from_note, &FlashcardEdit::default()).is_err());
+	}
+
+	#[test]
+	fn counts_cards_and_due_cards() {
+		let deck: Deck = Deck::open(":memory:").unwrap();
+		let subject: Subject = deck.add_subject("French", Algorithm::Sm2, &[]).unwrap().unwrap();
+		assert_eq!(deck.count_flashcards(subject.id).unwrap(), (0, 0));
+		let primary_keys: Vec<i64> = deck.add_flashcard(subject.id, &ImportCard::new("chat", "cat"), true).unwrap();
+		let later: Schedule = Schedule { interval: 6, due: scheduler::end_of_today() + 6 * scheduler::SECONDS_PER_DAY, ..Schedule::default() };
+		flashcards::set_schedule(deck.conn(), primary_keys[0] as i32, &later).unwrap();
+		assert_eq!(deck.count_flashcards(subject.id).unwrap(), (2, 1));
+		assert_eq!(deck.count_flashcards(subject.id).unwrap().1, deck.due_flashcards(subject.id).unwrap().len());
+	}
+}
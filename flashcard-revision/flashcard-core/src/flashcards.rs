@@ -0,0 +1,574 @@
+use chrono::Utc; // Handles revision dates
+
+use rand::Rng; // Handles card ids
+
+use rusqlite::{ // Handles SQLite database
+	params,
+	Connection,
+	OptionalExtension,
+};
+
+use crate::cloze;
+use crate::notes;
+use crate::scheduler::Schedule;
+
+// All flashcards follow this structure
+#[derive(Clone, Debug)]
+pub struct Flashcard {
+	pub primary_key: i32, // Primary key of the flashcard in the cards table
+	pub question: String, // Question or front text of the card
+	pub answer: String, // Answer or back text of the card
+	pub alternates: Vec<String>, // Other accepted answers (e.g. synonyms, UK/US spellings)
+	pub cloze_index: i32, // 0 = question/answer card, otherwise the cloze deletion it hides (Question is the cloze text)
+	pub multiple_choice: bool, // Answered by picking from options instead of typing
+	pub reversed: bool, // Answer to question sibling of another card
+	pub group_id: Option<i64>, // Cards made from the same note (Cloze text or both directions) share a group
+	pub note_id: Option<i64>, // Note the card was made from, which is edited instead of the card
+	pub distractors: Vec<String>, // Wrong options of a multiple choice card (Empty = taken from other cards)
+	pub schedule: Schedule, // When the card is next due (Tier is worked out from this)
+}
+
+impl Flashcard {
+	//* 0 = weak, 1 = learning, 2 = strong */
+	pub fn category(&self) -> i32 {
+		self.schedule.tier()
+	}
+
+	//* Question as shown when revising, with cloze deletions blanked out */
+	pub fn display_question(&self) -> String {
+		if self.cloze_index > 0 {
+			cloze::question(&self.question, self.cloze_index)
+		} else {
+			self.question.clone()
+		}
+	}
+
+	//* The answer followed by every alternate */
+	pub fn accepted_answers(&self) -> Vec<String> {
+		let mut answers: Vec<String> = vec![self.answer.clone()];
+		answers.extend(self.alternates.iter().cloned());
+		answers
+	}
+}
+
+fn schedule_from_row(row: &rusqlite::Row<'_>, first: usize) -> rusqlite::Result<Schedule> {
+	Ok(Schedule {
+		ease_factor: row.get(first)?,
+		interval: row.get(first + 1)?,
+		repetitions: row.get(first + 2)?,
+		due: row.get(first + 3)?,
+		stability: row.get(first + 4)?,
+		difficulty: row.get(first + 5)?,
+		last_review: row.get(first + 6)?,
+	})
+}
+
+fn flashcard_from_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<Flashcard> {
+	Ok(Flashcard {
+		primary_key: row.get(0)?,
+		question: row.get(1)?,
+		answer: row.get(2)?,
+		alternates: Vec::new(), // Filled in by `attach_lists`
+		cloze_index: row.get(3)?,
+		multiple_choice: row.get(4)?,
+		reversed: row.get(5)?,
+		group_id: row.get(6)?,
+		note_id: row.get(7)?,
+		distractors: Vec::new(), // Filled in by `attach_lists`
+		schedule: schedule_from_row(row, 8)?,
+	})
+}
+
+//* Loads the alternate answers and distractors of every flashcard of a subject into the flashcards given */
+fn attach_lists(conn: &Connection, subject_id: i64, flashcards: &mut [Flashcard]) -> rusqlite::Result<()> {
+	let mut stmt: rusqlite::Statement<'_> = conn.prepare(
+		"SELECT alternate_answers.card_id, alternate_answers.answer, 0, alternate_answers.position FROM alternate_answers
+			JOIN cards ON cards.id = alternate_answers.card_id WHERE cards.subject_id = ?1
+		UNION ALL
+		SELECT distractors.card_id, distractors.distractor, 1, distractors.position FROM distractors
+			JOIN cards ON cards.id = distractors.card_id WHERE cards.subject_id = ?1
+		ORDER BY 1, 3, 4;" // Card, then list, then position
+	)?;
+	let entries = stmt.query_map(params![subject_id], |row: &rusqlite::Row<'_>| {
+		Ok((row.get::<_, i32>(0)?, row.get::<_, String>(1)?, row.get::<_, bool>(2)?))
+	})?;
+
+	for entry in entries {
+		let (card_id, text, is_distractor): (i32, String, bool) = entry?;
+		if let Some(flashcard) = flashcards.iter_mut().find(|flashcard| flashcard.primary_key == card_id) {
+			if is_distractor {
+				flashcard.distractors.push(text);
+			} else {
+				flashcard.alternates.push(text);
+			}
+		}
+	}
+	Ok(())
+}
+
+//* Reads the alternate answers of one flashcard, in order */
+pub fn load_alternates(conn: &Connection, primary_key: i32) -> rusqlite::Result<Vec<String>> {
+	let mut stmt: rusqlite::Statement<'_> = conn.prepare(
+		"SELECT answer FROM alternate_answers WHERE card_id = ?1 ORDER BY position;"
+	)?;
+	let alternates = stmt.query_map(params![primary_key], |row: &rusqlite::Row<'_>| row.get(0))?;
+
+	alternates.collect()
+}
+
+//* Reads the distractors of one flashcard, in order */
+pub fn load_distractors(conn: &Connection, primary_key: i32) -> rusqlite::Result<Vec<String>> {
+	let mut stmt: rusqlite::Statement<'_> = conn.prepare(
+		"SELECT distractor FROM distractors WHERE card_id = ?1 ORDER BY position;"
+	)?;
+	let distractors = stmt.query_map(params![primary_key], |row: &rusqlite::Row<'_>| row.get(0))?;
+
+	distractors.collect()
+}
+
+//* Splits text with one entry per line (Accepted answers or distractors) into a list, leaving out blank lines */
+pub fn parse_alternates(text: &str) -> Vec<String> {
+	text.lines()
+		.map(|line| line.trim())
+		.filter(|line| !line.is_empty())
+		.map(|line| line.to_owned())
+		.collect()
+}
+
+//* Replaces the alternate answers of a flashcard */
+pub fn set_alternates(conn: &Connection, primary_key: i32, alternates: &[String]) -> rusqlite::Result<()> {
+	conn.execute("DELETE FROM alternate_answers WHERE card_id = ?1;", params![primary_key])?;
+	for (position, answer) in alternates.iter().enumerate() {
+		conn.execute(
+			"INSERT INTO alternate_answers (card_id, position, answer) VALUES (?1, ?2, ?3);",
+			params![primary_key, position as i64, answer],
+		)?;
+	}
+	Ok(())
+}
+
+//* Makes a flashcard typed or multiple choice and replaces its distractors */
+pub fn set_multiple_choice(conn: &Connection, primary_key: i32, multiple_choice: bool, distractors: &[String]) -> rusqlite::Result<()> {
+	conn.execute("UPDATE cards SET multiple_choice = ?1 WHERE id = ?2;", params![multiple_choice, primary_key])?;
+	conn.execute("DELETE FROM distractors WHERE card_id = ?1;", params![primary_key])?;
+	for (position, distractor) in distractors.iter().enumerate() {
+		conn.execute(
+			"INSERT INTO distractors (card_id, position, distractor) VALUES (?1, ?2, ?3);",
+			params![primary_key, position as i64, distractor],
+		)?;
+	}
+	Ok(())
+}
+
+//* Returns every answer of the subject's question/answer cards, for multiple choice cards without their own distractors */
+pub fn load_answer_pool(conn: &Connection, subject_id: i64) -> rusqlite::Result<Vec<String>> {
+	let mut stmt: rusqlite::Statement<'_> = conn.prepare(
+		"SELECT DISTINCT answer FROM cards WHERE subject_id = ?1 AND cloze_index = 0 AND reversed = 0 ORDER BY answer;"
+	)?;
+	let answers = stmt.query_map(params![subject_id], |row: &rusqlite::Row<'_>| row.get(0))?;
+
+	answers.collect()
+}
+
+//* Loads every flashcard of a subject due before the given time, most overdue first */
+pub fn load_due_flashcards(conn: &Connection, subject_id: i64, before: i64) -> rusqlite::Result<Vec<Flashcard>> {
+	let mut stmt: rusqlite::Statement<'_> = conn.prepare(
+		"SELECT id, question, answer, cloze_index, multiple_choice, reversed, group_id, note_id, ease_factor, interval, repetitions, due, stability, difficulty, last_review FROM cards
+			WHERE subject_id = ?1 AND due < ?2 ORDER BY due, id;"
+	)?;
+	let mut flashcards: Vec<Flashcard> = stmt.query_map(params![subject_id, before], flashcard_from_row)?.collect::<rusqlite::Result<_>>()?;
+
+	attach_lists(conn, subject_id, &mut flashcards)?;
+	Ok(flashcards)
+}
+
+//* Loads every flashcard of a subject, oldest first */
+pub fn load_all_flashcards(conn: &Connection, subject_id: i64) -> rusqlite::Result<Vec<Flashcard>> {
+	let mut stmt: rusqlite::Statement<'_> = conn.prepare(
+		"SELECT id, question, answer, cloze_index, multiple_choice, reversed, group_id, note_id, ease_factor, interval, repetitions, due, stability, difficulty, last_review FROM cards WHERE subject_id = ?1 ORDER BY id;"
+	)?;
+	let mut flashcards: Vec<Flashcard> = stmt.query_map(params![subject_id], flashcard_from_row)?.collect::<rusqlite::Result<_>>()?;
+
+	attach_lists(conn, subject_id, &mut flashcards)?;
+	Ok(flashcards)
+}
+
+//* Returns when the next flashcard of a subject is due, if it has any */
+pub fn next_due(conn: &Connection, subject_id: i64) -> rusqlite::Result<Option<i64>> {
+	conn.query_row(
+		"SELECT MIN(due) FROM cards WHERE subject_id = ?1;",
+		params![subject_id],
+		|row: &rusqlite::Row<'_>| row.get(0),
+	)
+}
+
+//* Counts the flashcards of a subject due before the given time (i64::MAX counts them all) */
+pub fn count_flashcards(conn: &Connection, subject_id: i64, before: i64) -> rusqlite::Result<usize> {
+	conn.query_row(
+		"SELECT COUNT(*) FROM cards WHERE subject_id = ?1 AND due < ?2;",
+		params![subject_id, before],
+		|row: &rusqlite::Row<'_>| row.get::<_, i64>(0),
+	).map(|count: i64| count as usize)
+}
+
+//* Creates a new flashcard (Due straight away) and returns its primary key */
+pub fn add_new_flashcard(conn: &Connection, subject_id: i64, question: &str, answer: &str, alternates: &[String]) -> rusqlite::Result<i64> {
+	// Scheduling columns default to a new card
+	conn.execute(
+		"INSERT INTO cards (subject_id, question, answer, correct, incorrect) VALUES (?1, ?2, ?3, ?4, ?5);",
+		params![subject_id, question, answer, 0, 0],
+	)?;
+	let primary_key: i64 = conn.last_insert_rowid();
+	set_alternates(conn, primary_key as i32, alternates)?;
+	Ok(primary_key)
+}
+
+//* Creates one flashcard for each deletion number of a cloze text, grouped together, and returns their primary keys */
+pub fn add_cloze_flashcards(conn: &Connection, subject_id: i64, text: &str) -> rusqlite::Result<Vec<i64>> {
+	let mut primary_keys: Vec<i64> = Vec::new();
+	for index in cloze::indexes(text) {
+		conn.execute(
+			"INSERT INTO cards (subject_id, question, answer, correct, incorrect, cloze_index, group_id) VALUES (?1, ?2, ?3, 0, 0, ?4, ?5);",
+			params![subject_id, text, cloze::answer(text, index), index, primary_keys.first()],
+		)?;
+		primary_keys.push(conn.last_insert_rowid());
+	}
+	// The first card is its own group
+	conn.execute("UPDATE cards SET group_id = id WHERE id = ?1;", params![primary_keys.first()])?;
+	Ok(primary_keys)
+}
+
+//* Creates the answer to question sibling of a card (Due straight away) and returns its primary key */
+pub fn add_reverse_flashcard(conn: &Connection, primary_key: i32) -> rusqlite::Result<i64> {
+	let (subject_id, question, answer, group_id): (i64, String, String, Option<i64>) = conn.query_row(
+		"SELECT subject_id, question, answer, group_id FROM cards WHERE id = ?1;",
+		params![primary_key],
+		|row: &rusqlite::Row<'_>| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+	)?;
+	let group_id: i64 = group_id.unwrap_or(primary_key as i64);
+	conn.execute("UPDATE cards SET group_id = ?1 WHERE id = ?2;", params![group_id, primary_key])?;
+
+	conn.execute(
+		"INSERT INTO cards (subject_id, question, answer, correct, incorrect, reversed, group_id) VALUES (?1, ?2, ?3, 0, 0, 1, ?4);",
+		params![subject_id, answer, question, group_id],
+	)?;
+	Ok(conn.last_insert_rowid())
+}
+
+//* Returns the primary key of the answer to question sibling of a card, if it has one */
+pub fn find_reverse(conn: &Connection, primary_key: i32) -> rusqlite::Result<Option<i32>> {
+	conn.query_row(
+		"SELECT id FROM cards WHERE reversed = 1 AND id != ?1 AND group_id = (SELECT group_id FROM cards WHERE id = ?1) ORDER BY id;",
+		params![primary_key],
+		|row: &rusqlite::Row<'_>| row.get(0),
+	).optional()
+}
+
+/* **Explanation of group syncing**
+Cards made from one note are copies of it, so after one of them is edited the rest are updated.
+Cloze cards all store the whole cloze text:
+	- Cards of the group whose deletion is still in the text get the new text and answer
+	- Cards whose deletion was taken out of the text are removed
+	- New deletion numbers get new cards, due straight away
+Question/answer cards and their reverse siblings store the same text swapped round, so the
+other direction gets the edited answer as its question and the edited question as its answer.
+*/
+fn sync_group(conn: &Connection, primary_key: i32) -> rusqlite::Result<()> {
+	let (subject_id, text, answer, cloze_index, reversed, group_id): (i64, String, String, i32, bool, Option<i64>) = conn.query_row(
+		"SELECT subject_id, question, answer, cloze_index, reversed, group_id FROM cards WHERE id = ?1;",
+		params![primary_key],
+		|row: &rusqlite::Row<'_>| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?, row.get(5)?)),
+	)?;
+	let group_id: i64 = match group_id {
+		Some(group_id) => group_id,
+		None if cloze_index > 0 => primary_key as i64, // Cloze cards are always grouped
+		None => return Ok(()), // On its own
+	};
+	conn.execute("UPDATE cards SET group_id = ?1 WHERE id = ?2;", params![group_id, primary_key])?;
+
+	if cloze_index == 0 {
+		conn.execute(
+			"UPDATE cards SET question = ?1, answer = ?2 WHERE group_id = ?3 AND id != ?4 AND cloze_index = 0 AND reversed != ?5;",
+			params![answer, text, group_id, primary_key, reversed],
+		)?;
+		return Ok(());
+	}
+
+	let indexes: Vec<i32> = cloze::indexes(&text);
+	let mut stmt: rusqlite::Statement<'_> = conn.prepare("SELECT id, cloze_index FROM cards WHERE group_id = ?1 AND cloze_index > 0;")?;
+	let siblings: Vec<(i32, i32)> = stmt.query_map(params![group_id], |row: &rusqlite::Row<'_>| Ok((row.get(0)?, row.get(1)?)))?
+		.collect::<rusqlite::Result<_>>()?;
+
+	for (sibling, index) in &siblings {
+		if indexes.contains(index) {
+			conn.execute(
+				"UPDATE cards SET question = ?1, answer = ?2 WHERE id = ?3;",
+				params![text, cloze::answer(&text, *index), sibling],
+			)?;
+		} else {
+			conn.execute("DELETE FROM cards WHERE id = ?1;", params![sibling])?; // Not `remove_flashcard`, the text is already right
+		}
+	}
+	for index in indexes.iter().filter(|index| !siblings.iter().any(|(_, sibling_index)| sibling_index == *index)) {
+		conn.execute(
+			"INSERT INTO cards (subject_id, question, answer, correct, incorrect, cloze_index, group_id) VALUES (?1, ?2, ?3, 0, 0, ?4, ?5);",
+			params![subject_id, text, cloze::answer(&text, *index), index, group_id],
+		)?;
+	}
+	Ok(())
+}
+
+/* Remove specified flashcard. Removing a cloze card turns its deletion back into plain text for the rest of its group.
+Cards made from a note would be made again the next time the note changed, so the whole note is removed. */
+pub fn remove_flashcard(conn: &Connection, primary_key: i32) -> rusqlite::Result<()> {
+	if let Some(note_id) = notes::note_of_card(conn, primary_key)? {
+		conn.execute("DELETE FROM notes WHERE id = ?1;", params![note_id])?; // Its cards go with it (ON DELETE CASCADE)
+		return Ok(());
+	}
+
+	let cloze: Option<(String, i32, Option<i64>)> = conn.query_row(
+		"SELECT question, cloze_index, group_id FROM cards WHERE id = ?1 AND cloze_index > 0;",
+		params![primary_key],
+		|row: &rusqlite::Row<'_>| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+	).optional()?;
+
+	conn.execute(
+		"DELETE FROM cards WHERE id = ?1;",
+		params![primary_key],
+	)?;
+
+	if let Some((text, index, Some(group_id))) = cloze {
+		let text: String = cloze::remove_deletion(&text, index);
+		conn.execute("UPDATE cards SET question = ?1 WHERE group_id = ?2 AND cloze_index > 0;", params![text, group_id])?;
+	}
+	Ok(())
+}
+
+//* Returns the id a card keeps in deck files, giving it a new random one if it doesn't have one yet */
+pub fn card_uid(conn: &Connection, primary_key: i32) -> rusqlite::Result<String> {
+	let existing: Option<String> = conn.query_row("SELECT uid FROM cards WHERE id = ?1;", params![primary_key], |row: &rusqlite::Row<'_>| row.get(0))?;
+	if let Some(uid) = existing {
+		return Ok(uid);
+	}
+	let mut rng: rand::prelude::ThreadRng = rand::thread_rng();
+	loop {
+		let uid: String = format!("{:012x}", rng.gen::<u64>() & 0xffff_ffff_ffff);
+		if find_by_uid(conn, &uid)?.is_none() {
+			set_uid(conn, primary_key, &uid)?;
+			return Ok(uid);
+		}
+	}
+}
+
+//* Gives a card the id of a card in a deck file */
+pub fn set_uid(conn: &Connection, primary_key: i32, uid: &str) -> rusqlite::Result<()> {
+	conn.execute("UPDATE cards SET uid = ?1 WHERE id = ?2;", params![uid, primary_key])?;
+	Ok(())
+}
+
+//* Returns the primary key of the card with an id from a deck file, if there is one */
+pub fn find_by_uid(conn: &Connection, uid: &str) -> rusqlite::Result<Option<i32>> {
+	conn.query_row("SELECT id FROM cards WHERE uid = ?1;", params![uid], |row: &rusqlite::Row<'_>| row.get(0)).optional()
+}
+
+// Every stored field of a flashcard, used to undo/redo edits
+#[derive(Clone, Debug, PartialEq)]
+pub struct FlashcardSnapshot {
+	pub primary_key: i32,
+	pub question: String,
+	pub answer: String,
+	pub alternates: Vec<String>,
+	pub cloze_index: i32, // Read only, the text of a cloze card decides which deletions exist
+	pub multiple_choice: bool,
+	pub reversed: bool, // Read only, reverse cards are made with `add_reverse_flashcard`
+	pub note_id: Option<i64>, // Read only, cards made from a note are changed by editing the note
+	pub distractors: Vec<String>,
+	pub correct: i32,
+	pub incorrect: i32,
+	pub schedule: Schedule,
+}
+
+//* Reads every stored field of a flashcard */
+pub fn load_snapshot(conn: &Connection, primary_key: i32) -> rusqlite::Result<FlashcardSnapshot> {
+	let alternates: Vec<String> = load_alternates(conn, primary_key)?;
+	let distractors: Vec<String> = load_distractors(conn, primary_key)?;
+	conn.query_row(
+		"SELECT id, question, answer, correct, incorrect, cloze_index, multiple_choice, reversed, note_id, ease_factor, interval, repetitions, due, stability, difficulty, last_review FROM cards WHERE id = ?1;",
+		params![primary_key],
+		|row: &rusqlite::Row<'_>| {
+			Ok(FlashcardSnapshot {
+				primary_key: row.get(0)?,
+				question: row.get(1)?,
+				answer: row.get(2)?,
+				alternates,
+				cloze_index: row.get(5)?,
+				multiple_choice: row.get(6)?,
+				reversed: row.get(7)?,
+				note_id: row.get(8)?,
+				distractors,
+				correct: row.get(3)?,
+				incorrect: row.get(4)?,
+				schedule: schedule_from_row(row, 9)?,
+			})
+		},
+	)
+}
+
+//* Overwrites every stored field of a flashcard, all or nothing so a failed undo/redo leaves it as it was */
+pub fn write_snapshot(conn: &Connection, snapshot: &FlashcardSnapshot) -> rusqlite::Result<()> {
+	let transaction: rusqlite::Transaction<'_> = conn.unchecked_transaction()?;
+	transaction.execute(
+		"UPDATE cards SET question = ?1, answer = ?2, correct = ?3, incorrect = ?4 WHERE id = ?5;",
+		params![snapshot.question, snapshot.answer, snapshot.correct, snapshot.incorrect, snapshot.primary_key],
+	)?;
+	set_alternates(&transaction, snapshot.primary_key, &snapshot.alternates)?;
+	set_multiple_choice(&transaction, snapshot.primary_key, snapshot.multiple_choice, &snapshot.distractors)?;
+	set_schedule(&transaction, snapshot.primary_key, &snapshot.schedule)?;
+	sync_group(&transaction, snapshot.primary_key)?;
+	transaction.commit()
+}
+
+//* Edit the question and accepted answers of a specified flashcard. Editing a cloze card edits its whole group */
+pub fn edit_flashcard(conn: &Connection, primary_key: i32, question: &str, answer: &str, alternates: &[String]) -> rusqlite::Result<()> {
+	conn.execute(
+		"UPDATE cards SET question = ?1, answer = ?2 WHERE id = ?3;",
+		params![question, answer, primary_key],
+	)?;
+	set_alternates(conn, primary_key, alternates)?;
+	sync_group(conn, primary_key)
+}
+
+// Changes to the text and options of one flashcard. None leaves that field as it is
+#[derive(Clone, Debug, Default)]
+pub struct FlashcardEdit {
+	pub question: Option<String>,
+	pub answer: Option<String>, // Must be None for cloze cards, their deletion is their answer
+	pub alternates: Option<Vec<String>>,
+	pub multiple_choice: Option<bool>,
+	pub distractors: Option<Vec<String>>,
+}
+
+impl FlashcardEdit {
+	/* Checks the edit against the card it changes, returning the card as it will be stored.
+	Cards made from a note can't be edited on their own, and cloze cards have to keep their deletion */
+	pub fn apply(&self, before: &FlashcardSnapshot) -> Result<FlashcardSnapshot, String> {
+		if before.note_id.is_some() {
+			return Err("This card is made from a note, so the note has to be edited instead.".to_owned());
+		}
+		let mut after: FlashcardSnapshot = before.clone();
+		if let Some(question) = &self.question {
+			after.question = question.trim().to_owned();
+		}
+		if before.cloze_index > 0 {
+			if self.answer.is_some() {
+				return Err("Cloze cards have no answer of their own, change the {{c1::...}} deletions in the question instead.".to_owned());
+			}
+			if !cloze::indexes(&after.question).contains(&before.cloze_index) {
+				return Err(format!("This card is for {{{{c{}::...}}}}, so the text has to keep it.", before.cloze_index));
+			}
+			after.answer = cloze::answer(&after.question, before.cloze_index);
+		} else if let Some(answer) = &self.answer {
+			after.answer = answer.trim().to_owned();
+		}
+		if after.question.is_empty() || after.answer.is_empty() {
+			return Err("Flashcards need a question and an answer.".to_owned());
+		}
+
+		if let Some(alternates) = &self.alternates {
+			after.alternates = alternates.clone();
+		}
+		if let Some(multiple_choice) = self.multiple_choice {
+			after.multiple_choice = multiple_choice;
+		}
+		if let Some(distractors) = &self.distractors {
+			after.distractors = distractors.clone();
+		}
+		Ok(after)
+	}
+}
+
+//* Stores a card made by `FlashcardEdit::apply`, leaving its counters and schedule alone */
+pub fn save_edit(conn: &Connection, edited: &FlashcardSnapshot) -> rusqlite::Result<()> {
+	let transaction: rusqlite::Transaction<'_> = conn.unchecked_transaction()?;
+	edit_flashcard(&transaction, edited.primary_key, &edited.question, &edited.answer, &edited.alternates)?;
+	set_multiple_choice(&transaction, edited.primary_key, edited.multiple_choice, &edited.distractors)?;
+	transaction.commit()
+}
+
+//* Schedules a flashcard as if it were new and clears its correct/incorrect counters */
+pub fn reset_flashcard(conn: &Connection, primary_key: i32) -> rusqlite::Result<()> {
+	conn.execute(
+		"UPDATE cards SET correct = 0, incorrect = 0 WHERE id = ?1;",
+		params![primary_key],
+	)?;
+	set_schedule(conn, primary_key, &Schedule::default())
+}
+
+//* Bumps the correct or incorrect counter of a flashcard */
+pub fn record_answer(conn: &Connection, primary_key: i32, correct: bool) -> rusqlite::Result<()> {
+	let sql: &str = if correct {
+		"UPDATE cards SET correct = correct + 1 WHERE id = ?1;"
+	} else {
+		"UPDATE cards SET incorrect = incorrect + 1 WHERE id = ?1;"
+	};
+	conn.execute(sql, params![primary_key])?;
+	Ok(())
+}
+
+//* Returns the accuracy (0.0 to 1.0) of a flashcard across all attempts */
+pub fn get_accuracy(conn: &Connection, primary_key: i32) -> rusqlite::Result<f64> {
+	let (correct, incorrect): (i32, i32) = conn.query_row(
+		"SELECT correct, incorrect FROM cards WHERE id = ?1;",
+		params![primary_key],
+		|row: &rusqlite::Row<'_>| Ok((row.get(0)?, row.get(1)?)),
+	)?;
+
+	let total_attempts: f64 = (correct + incorrect) as f64;
+	if total_attempts == 0.0 {
+		Ok(0.0)
+	} else {
+		Ok(correct as f64 / total_attempts)
+	}
+}
+
+//* Stores when a flashcard is next due */
+pub fn set_schedule(conn: &Connection, primary_key: i32, schedule: &Schedule) -> rusqlite::Result<()> {
+	conn.execute(
+		"UPDATE cards SET ease_factor = ?1, interval = ?2, repetitions = ?3, due = ?4, stability = ?5, difficulty = ?6, last_review = ?7
+			WHERE id = ?8;",
+		params![
+			schedule.ease_factor, schedule.interval, schedule.repetitions, schedule.due,
+			schedule.stability, schedule.difficulty, schedule.last_review, primary_key
+		],
+	)?;
+	Ok(())
+}
+
+//* Stores the time at which a tier of the subject was last revised */
+pub fn update_revision_date(conn: &Connection, subject_id: i64, category: i32) -> rusqlite::Result<()> {
+	// Column names can't be parameters, so each one gets its own fixed query
+	let sql: &str = match category {
+		0 => "UPDATE subjects SET date_weak_revised = ?1 WHERE id = ?2;",
+		1 => "UPDATE subjects SET date_learning_revised = ?1 WHERE id = ?2;",
+		_ => "UPDATE subjects SET date_strong_revised = ?1 WHERE id = ?2;",
+	};
+	let date: i64 = Utc::now().timestamp(); // Seconds since epoch
+	conn.execute(sql, params![date, subject_id])?;
+	Ok(())
+}
+
+//* Returns the accuracy (0.0 to 1.0) of every flashcard in a subject combined */
+pub fn get_subject_accuracy(conn: &Connection, subject_id: i64) -> rusqlite::Result<f64> {
+	let (correct, incorrect): (i64, i64) = conn.query_row(
+		"SELECT COALESCE(SUM(correct), 0), COALESCE(SUM(incorrect), 0) FROM cards WHERE subject_id = ?1;",
+		params![subject_id],
+		|row: &rusqlite::Row<'_>| Ok((row.get(0)?, row.get(1)?)),
+	)?;
+
+	let total_attempts: f64 = (correct + incorrect) as f64;
+	if total_attempts == 0.0 {
+		Ok(0.0)
+	} else {
+		Ok(correct as f64 / total_attempts)
+	}
+}
@@ -0,0 +1,1171 @@
+use std::collections::{hash_map::Entry, HashMap, HashSet};
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use chrono::Utc; // Handles export times
+
+use rusqlite::{ // Handles SQLite database
+	params,
+	Connection,
+	OpenFlags,
+};
+
+use crate::database::{self, Subject};
+use crate::flashcards::{self, Flashcard};
+use crate::formats::{duplicate_key, MEDIA_DIR};
+use crate::notes::{self, CardTemplate, Note, NoteType};
+use crate::reviews::{self, Review};
+use crate::scheduler::{start_of_today, Algorithm, Grade, Schedule};
+
+const SECONDS_PER_DAY: i64 = 86400;
+
+// Numbers the temporary collections made by this process, so imports and exports running at once don't share one
+static TEMP_COLLECTIONS: AtomicUsize = AtomicUsize::new(0);
+
+// Collection files of a package, newest format first. Newer packages also hold an old collection saying to update Anki
+const COLLECTIONS: [&str; 3] = ["collection.anki21b", "collection.anki21", "collection.anki2"];
+
+// A note type of an Anki collection, with its templates still in Anki's HTML form
+#[derive(Clone, Debug)]
+struct AnkiNoteType {
+	id: i64,
+	name: String,
+	cloze: bool,
+	fields: Vec<String>,
+	templates: Vec<(String, String, String)>, // Name, front (qfmt) and back (afmt), in card ordinal order
+}
+
+#[derive(Clone, Debug)]
+struct AnkiNote {
+	id: i64,
+	note_type_id: i64,
+	values: Vec<String>, // HTML
+}
+
+#[derive(Clone, Debug)]
+struct AnkiCard {
+	id: i64,
+	note_id: i64,
+	deck_id: i64,
+	ord: i32, // Template of the card, or cloze number - 1
+	kind: i32, // 0 = new, 1 = learning, 2 = review, 3 = relearning
+	queue: i32, // 1 = learning (Due in seconds), 3 = learning a day at a time (Due in days)
+	due: i64, // Days since the collection was made for review cards
+	interval: i64, // Days (Negative = seconds while learning)
+	factor: i64, // Ease factor in thousandths
+	stability: f64, // FSRS memory state, 0 if the collection doesn't use FSRS
+	difficulty: f64,
+}
+
+#[derive(Clone, Debug)]
+struct AnkiReview {
+	card_id: i64,
+	reviewed_at: i64, // Milliseconds since epoch (The revlog id)
+	ease: i32, // 1 to 4, 0 = rescheduled by hand
+	interval: i64,
+	last_interval: i64,
+	taken_ms: i64,
+}
+
+// Everything read from an .apkg/.colpkg file, ready to be previewed and imported
+pub struct AnkiPackage {
+	path: PathBuf, // Opened again to copy the media
+	created: i64, // Seconds since epoch that the collection's day numbers count from
+	compressed_media: bool, // Newer packages compress their media files with zstd
+	decks: HashMap<i64, String>,
+	note_types: Vec<AnkiNoteType>,
+	notes: Vec<AnkiNote>,
+	cards: Vec<AnkiCard>,
+	reviews: Vec<AnkiReview>,
+	media: Vec<(String, String)>, // Name of the entry in the zip, name of the file
+}
+
+// What an import did
+#[derive(Default)]
+pub struct AnkiReport {
+	pub cards: usize,
+	pub duplicates: usize, // Notes skipped because the subject already has their cards
+	pub skipped: usize, // Notes that make no cards here (e.g. an unknown note type)
+	pub media: usize, // Files copied into MEDIA_DIR
+	pub subjects: Vec<String>, // Subjects cards were added to
+}
+
+// A collection file in the temp folder, deleted when dropped so that no error leaves it behind
+struct TempCollection {
+	path: PathBuf,
+}
+
+impl TempCollection {
+	//* A file no other import or export is using, in this process (Counter) or another (Process id) */
+	fn new(purpose: &str) -> TempCollection {
+		let number: usize = TEMP_COLLECTIONS.fetch_add(1, Ordering::Relaxed);
+		let path: PathBuf = std::env::temp_dir().join(format!("flashcard-revision-{}-{}-{}.anki2", purpose, std::process::id(), number));
+		let _ = std::fs::remove_file(&path); // Left behind by an earlier process with the same id that crashed
+		TempCollection { path }
+	}
+}
+
+impl Drop for TempCollection {
+	fn drop(&mut self) {
+		let _ = std::fs::remove_file(&self.path);
+		let _ = std::fs::remove_file(self.path.with_extension("anki2-journal"));
+	}
+}
+
+//* Reads one entry of a zip, returning None if it isn't there */
+fn read_entry(archive: &mut zip::ZipArchive<File>, name: &str) -> Result<Option<Vec<u8>>, String> {
+	let mut entry = match archive.by_name(name) {
+		Ok(entry) => entry,
+		Err(zip::result::ZipError::FileNotFound) => return Ok(None),
+		Err(e) => return Err(format!("Couldn't read {} from the package: {}", name, e)),
+	};
+	let mut bytes: Vec<u8> = Vec::new();
+	entry.read_to_end(&mut bytes).map_err(|e| format!("Couldn't read {} from the package: {}", name, e))?;
+	Ok(Some(bytes))
+}
+
+fn open_package(path: &Path) -> Result<zip::ZipArchive<File>, String> {
+	let file: File = File::open(path).map_err(|e| format!("Couldn't open {}: {}", path.display(), e))?;
+	zip::ZipArchive::new(file).map_err(|e| format!("{} isn't an Anki package: {}", path.display(), e))
+}
+
+/* **Explanation of protobuf**
+Newer collections store note type options and media lists as protobuf messages. Each field is a key
+(Field number * 8 + wire type) followed by a varint (Type 0), 8 bytes (Type 1), a length and that
+many bytes (Type 2, strings and nested messages) or 4 bytes (Type 5). Only the few fields needed are read.
+*/
+fn read_varint(bytes: &mut &[u8]) -> Option<u64> {
+	let mut value: u64 = 0;
+	for shift in (0..64).step_by(7) {
+		let (byte, rest) = bytes.split_first()?;
+		*bytes = rest;
+		value |= ((byte & 0x7f) as u64) << shift;
+		if byte & 0x80 == 0 {
+			return Some(value);
+		}
+	}
+	None
+}
+
+//* Reads the fields of a protobuf message as (Field number, varint value, bytes). Stops at anything malformed */
+fn protobuf_fields(mut bytes: &[u8]) -> Vec<(u64, u64, &[u8])> {
+	let mut fields: Vec<(u64, u64, &[u8])> = Vec::new();
+	while let Some(key) = read_varint(&mut bytes) {
+		let (number, wire_type): (u64, u64) = (key >> 3, key & 7);
+		let field = match wire_type {
+			0 => read_varint(&mut bytes).map(|value| (number, value, &[][..])),
+			1 | 5 => {
+				let size: usize = if wire_type == 1 { 8 } else { 4 };
+				(bytes.len() >= size).then(|| {
+					let (value, rest) = bytes.split_at(size);
+					bytes = rest;
+					(number, 0, value)
+				})
+			},
+			2 => read_varint(&mut bytes).and_then(|length| {
+				let length: usize = length as usize;
+				(bytes.len() >= length).then(|| {
+					let (value, rest) = bytes.split_at(length);
+					bytes = rest;
+					(number, 0, value)
+				})
+			}),
+			_ => None,
+		};
+		match field {
+			Some(field) => fields.push(field),
+			None => break,
+		}
+	}
+	fields
+}
+
+//* Text of a string field of a protobuf message, empty if it isn't set */
+fn protobuf_string(bytes: &[u8], number: u64) -> String {
+	protobuf_fields(bytes).into_iter()
+		.find(|(field, _, _)| *field == number)
+		.map(|(_, _, value)| String::from_utf8_lossy(value).into_owned())
+		.unwrap_or_default()
+}
+
+fn table_exists(conn: &Connection, table: &str) -> rusqlite::Result<bool> {
+	conn.query_row(
+		"SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?1);",
+		params![table],
+		|row: &rusqlite::Row<'_>| row.get(0),
+	)
+}
+
+//* Note types and decks of a collection from before Anki 2.1.28, stored as JSON in the col table */
+fn read_json_models(conn: &Connection) -> Result<(Vec<AnkiNoteType>, HashMap<i64, String>), String> {
+	let (models, decks): (String, String) = conn.query_row("SELECT models, decks FROM col;", params![], |row: &rusqlite::Row<'_>| {
+		Ok((row.get(0)?, row.get(1)?))
+	}).map_err(|e| format!("Couldn't read the collection: {}", e))?;
+	let models: serde_json::Value = serde_json::from_str(&models).map_err(|e| format!("Couldn't read the note types: {}", e))?;
+	let decks: serde_json::Value = serde_json::from_str(&decks).map_err(|e| format!("Couldn't read the decks: {}", e))?;
+
+	let mut note_types: Vec<AnkiNoteType> = Vec::new();
+	for (id, model) in models.as_object().into_iter().flatten() {
+		let text = |value: &serde_json::Value, key: &str| value[key].as_str().unwrap_or("").to_owned();
+		let mut fields: Vec<(i64, String)> = model["flds"].as_array().into_iter().flatten()
+			.map(|field| (field["ord"].as_i64().unwrap_or(0), text(field, "name")))
+			.collect();
+		fields.sort();
+		let mut templates: Vec<(i64, (String, String, String))> = model["tmpls"].as_array().into_iter().flatten()
+			.map(|template| (template["ord"].as_i64().unwrap_or(0), (text(template, "name"), text(template, "qfmt"), text(template, "afmt"))))
+			.collect();
+		templates.sort_by_key(|(ord, _)| *ord);
+		note_types.push(AnkiNoteType {
+			id: id.parse().unwrap_or(0),
+			name: text(model, "name"),
+			cloze: model["type"].as_i64() == Some(1),
+			fields: fields.into_iter().map(|(_, name)| name).collect(),
+			templates: templates.into_iter().map(|(_, template)| template).collect(),
+		});
+	}
+
+	let decks: HashMap<i64, String> = decks.as_object().into_iter().flatten()
+		.map(|(id, deck)| (id.parse().unwrap_or(0), deck["name"].as_str().unwrap_or("").to_owned()))
+		.collect();
+	Ok((note_types, decks))
+}
+
+//* Note types and decks of a newer collection, which has a table for each */
+fn read_table_models(conn: &Connection) -> rusqlite::Result<(Vec<AnkiNoteType>, HashMap<i64, String>)> {
+	let mut stmt: rusqlite::Statement<'_> = conn.prepare("SELECT id, name, config FROM notetypes ORDER BY id;")?;
+	let mut note_types: Vec<AnkiNoteType> = stmt.query_map(params![], |row: &rusqlite::Row<'_>| {
+		let config: Vec<u8> = row.get(2)?;
+		let kind: u64 = protobuf_fields(&config).into_iter().find(|(field, _, _)| *field == 1).map(|(_, value, _)| value).unwrap_or(0);
+		Ok(AnkiNoteType { id: row.get(0)?, name: row.get(1)?, cloze: kind == 1, fields: Vec::new(), templates: Vec::new() })
+	})?.collect::<rusqlite::Result<_>>()?;
+
+	for note_type in note_types.iter_mut() {
+		let mut stmt: rusqlite::Statement<'_> = conn.prepare("SELECT name FROM fields WHERE ntid = ?1 ORDER BY ord;")?;
+		note_type.fields = stmt.query_map(params![note_type.id], |row: &rusqlite::Row<'_>| row.get(0))?
+			.collect::<rusqlite::Result<_>>()?;
+		let mut stmt: rusqlite::Statement<'_> = conn.prepare("SELECT name, config FROM templates WHERE ntid = ?1 ORDER BY ord;")?;
+		note_type.templates = stmt.query_map(params![note_type.id], |row: &rusqlite::Row<'_>| {
+			let config: Vec<u8> = row.get(1)?;
+			Ok((row.get(0)?, protobuf_string(&config, 1), protobuf_string(&config, 2))) // Front is field 1, back is field 2
+		})?.collect::<rusqlite::Result<_>>()?;
+	}
+
+	let mut stmt: rusqlite::Statement<'_> = conn.prepare("SELECT id, name FROM decks;")?;
+	let decks: HashMap<i64, String> = stmt.query_map(params![], |row: &rusqlite::Row<'_>| {
+		let name: String = row.get(1)?;
+		Ok((row.get(0)?, name.replace('\u{1f}', "::"))) // Parent and child decks are split by a unit separator
+	})?.collect::<rusqlite::Result<_>>()?;
+	Ok((note_types, decks))
+}
+
+impl AnkiPackage {
+	//* Unpacks an .apkg/.colpkg file and reads its collection */
+	pub fn read(path: &str) -> Result<AnkiPackage, String> {
+		let path: PathBuf = PathBuf::from(path);
+		let mut archive: zip::ZipArchive<File> = open_package(&path)?;
+
+		let mut collection: Option<(&str, Vec<u8>)> = None;
+		for name in COLLECTIONS {
+			if let Some(bytes) = read_entry(&mut archive, name)? {
+				collection = Some((name, bytes));
+				break;
+			}
+		}
+		let (name, mut bytes): (&str, Vec<u8>) = collection.ok_or("The package has no Anki collection in it.".to_owned())?;
+		let compressed: bool = name == COLLECTIONS[0];
+		if compressed {
+			bytes = decompress(&bytes).map_err(|e| format!("Couldn't decompress the collection: {}", e))?;
+		}
+
+		// SQLite needs a file to open
+		let temp: TempCollection = TempCollection::new("import");
+		std::fs::write(&temp.path, &bytes).map_err(|e| format!("Couldn't unpack the collection: {}", e))?;
+		let mut package: AnkiPackage = Connection::open_with_flags(&temp.path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+			.map_err(|e| format!("Couldn't open the collection: {}", e))
+			.and_then(|conn| AnkiPackage::read_collection(&conn, path.clone(), compressed))?;
+		drop(temp);
+
+		package.media = read_media_list(&mut archive, compressed)?;
+		Ok(package)
+	}
+
+	fn read_collection(conn: &Connection, path: PathBuf, compressed_media: bool) -> Result<AnkiPackage, String> {
+		let read_error = |e: rusqlite::Error| format!("Couldn't read the collection: {}", e);
+		let created: i64 = conn.query_row("SELECT crt FROM col;", params![], |row: &rusqlite::Row<'_>| row.get(0)).map_err(read_error)?;
+		let (note_types, decks) = if table_exists(conn, "notetypes").map_err(read_error)? {
+			read_table_models(conn).map_err(read_error)?
+		} else {
+			read_json_models(conn)?
+		};
+
+		let mut stmt: rusqlite::Statement<'_> = conn.prepare("SELECT id, mid, flds FROM notes ORDER BY id;").map_err(read_error)?;
+		let notes: Vec<AnkiNote> = stmt.query_map(params![], |row: &rusqlite::Row<'_>| {
+			let fields: String = row.get(2)?;
+			Ok(AnkiNote { id: row.get(0)?, note_type_id: row.get(1)?, values: fields.split('\u{1f}').map(str::to_owned).collect() })
+		}).and_then(|notes| notes.collect()).map_err(read_error)?;
+
+		let mut stmt: rusqlite::Statement<'_> = conn.prepare(
+			"SELECT id, nid, did, ord, type, queue, due, ivl, factor, data FROM cards ORDER BY nid, ord;"
+		).map_err(read_error)?;
+		let cards: Vec<AnkiCard> = stmt.query_map(params![], |row: &rusqlite::Row<'_>| {
+			// FSRS memory state is kept as JSON, e.g. {"s":12.5,"d":5.1}
+			let data: String = row.get::<_, Option<String>>(9)?.unwrap_or_default();
+			let data: serde_json::Value = serde_json::from_str(&data).unwrap_or_default();
+			Ok(AnkiCard {
+				id: row.get(0)?,
+				note_id: row.get(1)?,
+				deck_id: row.get(2)?,
+				ord: row.get(3)?,
+				kind: row.get(4)?,
+				queue: row.get(5)?,
+				due: row.get(6)?,
+				interval: row.get(7)?,
+				factor: row.get(8)?,
+				stability: data["s"].as_f64().unwrap_or(0.0),
+				difficulty: data["d"].as_f64().unwrap_or(0.0),
+			})
+		}).and_then(|cards| cards.collect()).map_err(read_error)?;
+
+		let mut stmt: rusqlite::Statement<'_> = conn.prepare("SELECT id, cid, ease, ivl, lastIvl, time FROM revlog ORDER BY id;").map_err(read_error)?;
+		let reviews: Vec<AnkiReview> = stmt.query_map(params![], |row: &rusqlite::Row<'_>| {
+			Ok(AnkiReview {
+				reviewed_at: row.get(0)?,
+				card_id: row.get(1)?,
+				ease: row.get(2)?,
+				interval: row.get(3)?,
+				last_interval: row.get(4)?,
+				taken_ms: row.get(5)?,
+			})
+		}).and_then(|reviews| reviews.collect()).map_err(read_error)?;
+
+		Ok(AnkiPackage { path, created, compressed_media, decks, note_types, notes, cards, reviews, media: Vec::new() })
+	}
+
+	/* The cards of each note, in template order. A note's cards are kept together (Siblings and cloze
+	groups need each other), so they all go into the subject of the first card's deck */
+	fn cards_by_note(&self) -> HashMap<i64, Vec<&AnkiCard>> {
+		let mut cards_of_note: HashMap<i64, Vec<&AnkiCard>> = HashMap::new();
+		for card in &self.cards {
+			cards_of_note.entry(card.note_id).or_default().push(card); // Cards are sorted by note, then template
+		}
+		cards_of_note
+	}
+
+	//* Lines shown in the preview list: each deck as it will be imported, then each note type */
+	pub fn summary(&self) -> Vec<String> {
+		let mut lines: Vec<String> = Vec::new();
+		let cards_of_note: HashMap<i64, Vec<&AnkiCard>> = self.cards_by_note();
+		let mut deck_ids: Vec<&i64> = self.decks.keys().collect();
+		deck_ids.sort_by_key(|id| &self.decks[id]);
+		for deck_id in deck_ids {
+			let notes: Vec<&Vec<&AnkiCard>> = cards_of_note.values().filter(|cards| cards[0].deck_id == *deck_id).collect();
+			if notes.is_empty() {
+				continue; // e.g. the Default deck, or one only holding cards of notes from other decks
+			}
+			let cards: usize = notes.iter().map(|cards| cards.len()).sum();
+			let moved: usize = notes.iter().flat_map(|cards| cards.iter()).filter(|card| card.deck_id != *deck_id).count();
+			let mut line: String = format!("Deck {}: {} notes, {} cards", self.decks[deck_id], notes.len(), cards);
+			if moved > 0 {
+				line.push_str(&format!(" ({} from other decks, kept with their note)", moved));
+			}
+			lines.push(line);
+		}
+		for note_type in &self.note_types {
+			let note_count: usize = self.notes.iter().filter(|note| note.note_type_id == note_type.id).count();
+			if note_count > 0 {
+				let kind: &str = if note_type.cloze { "cloze" } else { "note type" };
+				lines.push(format!("{} ({}): {} notes", note_type.name, kind, note_count));
+			}
+		}
+		if !self.media.is_empty() {
+			lines.push(format!("{} media files", self.media.len()));
+		}
+		lines
+	}
+
+	pub fn note_count(&self) -> usize {
+		self.notes.len()
+	}
+
+	pub fn card_count(&self) -> usize {
+		self.cards.len()
+	}
+
+	//* Copies the package's media into MEDIA_DIR, returning how many files were copied */
+	fn extract_media(&self) -> Result<usize, String> {
+		if self.media.is_empty() {
+			return Ok(0);
+		}
+		let mut archive: zip::ZipArchive<File> = open_package(&self.path)?;
+		std::fs::create_dir_all(MEDIA_DIR).map_err(|e| format!("Couldn't make the {} folder: {}", MEDIA_DIR, e))?;
+
+		let mut copied: usize = 0;
+		for (entry, name) in &self.media {
+			// Names come from the package, so anything that could write outside the media folder is skipped
+			if name.is_empty() || name.contains(['/', '\\']) || name.starts_with('.') {
+				continue;
+			}
+			let Some(mut bytes) = read_entry(&mut archive, entry)? else { continue };
+			if self.compressed_media {
+				bytes = decompress(&bytes).map_err(|e| format!("Couldn't decompress {}: {}", name, e))?;
+			}
+			std::fs::write(Path::new(MEDIA_DIR).join(name), bytes).map_err(|e| format!("Couldn't copy {}: {}", name, e))?;
+			copied += 1;
+		}
+		Ok(copied)
+	}
+}
+
+//* Undoes the zstd compression of newer (anki21b) packages */
+#[cfg(feature = "anki21b")]
+fn decompress(bytes: &[u8]) -> Result<Vec<u8>, String> {
+	zstd::decode_all(bytes).map_err(|e| e.to_string())
+}
+
+//* Without the anki21b feature only older packages can be read */
+#[cfg(not(feature = "anki21b"))]
+fn decompress(_bytes: &[u8]) -> Result<Vec<u8>, String> {
+	Err("it uses the newer Anki format, which this build can't read (enable the anki21b feature)".to_owned())
+}
+
+/* Reads the list of media files. Older packages use JSON ({"0": "cat.jpg"}), newer ones a zstd
+compressed protobuf list whose entry numbers are the positions in the list */
+fn read_media_list(archive: &mut zip::ZipArchive<File>, compressed: bool) -> Result<Vec<(String, String)>, String> {
+	let Some(bytes) = read_entry(archive, "media")? else { return Ok(Vec::new()) };
+	if !compressed {
+		let media: HashMap<String, String> = serde_json::from_slice(&bytes).map_err(|e| format!("Couldn't read the media list: {}", e))?;
+		return Ok(media.into_iter().collect());
+	}
+
+	let bytes: Vec<u8> = decompress(&bytes).map_err(|e| format!("Couldn't read the media list: {}", e))?;
+	Ok(protobuf_fields(&bytes).into_iter()
+		.filter(|(field, _, _)| *field == 1)
+		.enumerate()
+		.map(|(position, (_, _, entry))| (position.to_string(), protobuf_string(entry, 1)))
+		.collect())
+}
+
+/* **Explanation of HTML stripping**
+	- Line breaks, divs and paragraphs become new lines
+	- Images become "[image: cat.jpg]" so the file can be found in MEDIA_DIR. Sounds stay as "[sound:cat.mp3]"
+	- Every other tag is dropped and the common entities (&amp; &nbsp; ...) are decoded
+*/
+pub fn strip_html(html: &str) -> String {
+	let mut text: String = String::new();
+	let mut rest: &str = html;
+	while let Some(start) = rest.find('<') {
+		text.push_str(&rest[..start]);
+		let Some(end) = rest[start..].find('>') else {
+			text.push_str(&rest[start..]); // A lone "<" isn't a tag
+			rest = "";
+			break;
+		};
+		let tag: &str = &rest[start + 1..start + end];
+		let name: String = tag.trim_start_matches('/').split(|character: char| character.is_whitespace() || character == '/')
+			.next()
+			.unwrap_or("")
+			.to_lowercase();
+		match name.as_str() {
+			"br" | "div" | "p" => text.push('\n'), // Runs of new lines are collapsed below
+			"img" => {
+				if let Some(source) = tag.split("src=").nth(1) {
+					let source: &str = source.trim_start_matches(['"', '\'']);
+					let source: &str = source.split(['"', '\'', ' ']).next().unwrap_or("");
+					text.push_str(&format!("[image: {}]", source));
+				}
+			},
+			_ => {},
+		}
+		rest = &rest[start + end + 1..];
+	}
+	text.push_str(rest);
+
+	let text: String = text.replace("&nbsp;", " ")
+		.replace("&lt;", "<")
+		.replace("&gt;", ">")
+		.replace("&quot;", "\"")
+		.replace("&#39;", "'")
+		.replace("&amp;", "&"); // Last, so "&amp;lt;" stays "&lt;"
+	text.lines().map(str::trim).filter(|line| !line.is_empty()).collect::<Vec<&str>>().join("\n")
+}
+
+/* Turns an Anki card template into one of ours. {{FrontSide}}, conditionals and special fields
+(Tags, Deck...) are dropped and filters such as {{type:Back}} become {{Back}} */
+fn convert_template(format: &str, fields: &[String]) -> String {
+	let text: String = strip_html(&format.replace("{{FrontSide}}", ""));
+	let mut converted: String = String::new();
+	let mut rest: &str = &text;
+	while let Some(start) = rest.find("{{") {
+		converted.push_str(&rest[..start]);
+		let Some(end) = rest[start..].find("}}") else { break };
+		let inner: &str = rest[start + 2..start + end].trim();
+		let name: &str = inner.rsplit(':').next().unwrap_or("").trim();
+		if !inner.starts_with(['#', '^', '/']) && fields.iter().any(|field| field == name) {
+			converted.push_str(&format!("{{{{{}}}}}", name));
+		}
+		rest = &rest[start + end + 2..];
+	}
+	converted.push_str(rest);
+	converted.lines().map(str::trim).filter(|line| !line.is_empty()).collect::<Vec<&str>>().join(" ")
+}
+
+//* How the cards of one Anki note are made here */
+enum NotePlan {
+	Cloze(String), // Cloze text, one card per deletion
+	Note(NoteType, Vec<String>), // A note of one of our note types, with its values
+	Plain(Vec<(i32, String, String)>), // Cards on their own (Ordinal, question, answer) when the note type can't be one of ours
+}
+
+impl NotePlan {
+	//* Question and answer of each card, for finding duplicates (Cloze texts have no answer) */
+	fn keys(&self) -> Vec<(String, String)> {
+		match self {
+			NotePlan::Cloze(text) => vec![duplicate_key(text, "")],
+			NotePlan::Note(note_type, values) => notes::generate(note_type, values).iter()
+				.map(|(_, question, answer)| duplicate_key(question, answer))
+				.collect(),
+			NotePlan::Plain(cards) => cards.iter().map(|(_, question, answer)| duplicate_key(question, answer)).collect(),
+		}
+	}
+}
+
+//* Finds (Or saves) the note type an Anki note type becomes, or None if it can't be one (e.g. too many fields) */
+fn note_type_for(conn: &Connection, anki_type: &AnkiNoteType, existing: &mut Vec<NoteType>) -> rusqlite::Result<Option<NoteType>> {
+	let mut note_type: NoteType = NoteType {
+		id: 0,
+		name: anki_type.name.clone(),
+		fields: anki_type.fields.clone(),
+		templates: anki_type.templates.iter()
+			.map(|(name, front, back)| CardTemplate {
+				name: name.clone(),
+				front: convert_template(front, &anki_type.fields),
+				back: convert_template(back, &anki_type.fields),
+			})
+			.collect(),
+	};
+	if notes::validate(&note_type).is_err() {
+		return Ok(None);
+	}
+
+	// Same name and contents are reused, otherwise "(Anki)" is added until the name is free
+	let base_name: String = note_type.name.clone();
+	for attempt in 0.. {
+		note_type.name = match attempt {
+			0 => base_name.clone(),
+			1 => format!("{} (Anki)", base_name),
+			_ => format!("{} (Anki {})", base_name, attempt),
+		};
+		match existing.iter().find(|other| other.name == note_type.name) {
+			Some(other) if other.fields == note_type.fields && other.templates == note_type.templates => return Ok(Some(other.clone())),
+			Some(_) => continue,
+			None => break,
+		}
+	}
+	note_type.id = notes::save_note_type(conn, &note_type)?;
+	existing.push(note_type.clone());
+	Ok(Some(note_type))
+}
+
+//* Schedule of a card here from its Anki scheduling state */
+fn schedule_for(card: &AnkiCard, created: i64, last_review: Option<i64>) -> Schedule {
+	let mut schedule: Schedule = Schedule::default();
+	if card.kind == 0 {
+		return schedule; // New
+	}
+	schedule.interval = card.interval.max(0) as i32; // Negative intervals are seconds while learning
+	schedule.repetitions = if schedule.interval > 0 { 2 } else { 0 }; // So SM-2 carries on from the interval instead of restarting at 1 day
+	if card.factor > 0 {
+		schedule.ease_factor = card.factor as f64 / 1000.0;
+	}
+	schedule.due = if card.queue == 1 { card.due } else { created + card.due * SECONDS_PER_DAY };
+	schedule.stability = card.stability;
+	schedule.difficulty = card.difficulty;
+	schedule.last_review = last_review.unwrap_or(if schedule.interval > 0 { schedule.due - schedule.interval as i64 * SECONDS_PER_DAY } else { 0 });
+	schedule
+}
+
+//* Gives a card its Anki schedule, counters and review history */
+fn apply_card(conn: &Connection, primary_key: i32, card: &AnkiCard, history: &[&AnkiReview], created: i64) -> rusqlite::Result<()> {
+	let correct: usize = history.iter().filter(|review| review.ease > 1).count();
+	let incorrect: usize = history.iter().filter(|review| review.ease == 1).count();
+	conn.execute("UPDATE cards SET correct = ?1, incorrect = ?2 WHERE id = ?3;", params![correct, incorrect, primary_key])?;
+
+	let last_review: Option<i64> = history.iter().map(|review| review.reviewed_at / 1000).max();
+	flashcards::set_schedule(conn, primary_key, &schedule_for(card, created, last_review))?;
+
+	for review in history {
+		if let Some(grade) = Grade::from_number(review.ease) {
+			reviews::log_review(conn, &Review {
+				card_id: primary_key,
+				reviewed_at: review.reviewed_at / 1000,
+				grade,
+				response_ms: review.taken_ms,
+				algorithm: Algorithm::Sm2, // Anki's scheduler is based on SM-2
+				interval_before: review.last_interval.max(0) as i32,
+				interval_after: review.interval.max(0) as i32,
+			})?;
+		}
+	}
+	Ok(())
+}
+
+//* Adds every note of a package to the subject named after its deck */
+fn add_package(conn: &Connection, package: &AnkiPackage) -> rusqlite::Result<AnkiReport> {
+	let mut report: AnkiReport = AnkiReport::default();
+	let mut existing_types: Vec<NoteType> = notes::load_note_types(conn)?;
+	let mut converted: HashMap<i64, Option<NoteType>> = HashMap::new();
+	let mut subjects: HashMap<i64, i64> = HashMap::new(); // Deck to subject
+	let mut seen: HashMap<i64, Vec<(String, String)>> = HashMap::new(); // Duplicate keys of each subject
+
+	let cards_of_note: HashMap<i64, Vec<&AnkiCard>> = package.cards_by_note();
+	let mut history: HashMap<i64, Vec<&AnkiReview>> = HashMap::new();
+	for review in &package.reviews {
+		history.entry(review.card_id).or_default().push(review);
+	}
+
+	for note in &package.notes {
+		let anki_type: Option<&AnkiNoteType> = package.note_types.iter().find(|note_type| note_type.id == note.note_type_id);
+		let (Some(anki_type), Some(cards)) = (anki_type, cards_of_note.get(&note.id)) else {
+			report.skipped += 1;
+			continue;
+		};
+
+		// Every card of the note goes into its first card's deck, as shown by the summary
+		let deck_name: String = package.decks.get(&cards[0].deck_id).cloned().unwrap_or_else(|| "Anki".to_owned());
+		let subject_id: i64 = match subjects.get(&cards[0].deck_id) {
+			Some(subject_id) => *subject_id,
+			None => {
+				let subject_id: i64 = database::get_or_add_subject(conn, &deck_name)?;
+				subjects.insert(cards[0].deck_id, subject_id);
+				subject_id
+			}
+		};
+		let values: Vec<String> = note.values.iter().map(|value| strip_html(value)).collect();
+
+		let plan: NotePlan = if anki_type.cloze {
+			// The field holding the cloze text is the one the front uses as {{cloze:Field}}
+			let front: &str = anki_type.templates.first().map(|(_, front, _)| front.as_str()).unwrap_or("");
+			let field: usize = front.split("{{cloze:").nth(1)
+				.and_then(|rest| rest.split("}}").next())
+				.and_then(|name| anki_type.fields.iter().position(|field| field == name.trim()))
+				.unwrap_or(0);
+			NotePlan::Cloze(values.get(field).cloned().unwrap_or_default())
+		} else {
+			if let Entry::Vacant(entry) = converted.entry(anki_type.id) {
+				entry.insert(note_type_for(conn, anki_type, &mut existing_types)?);
+			}
+			match &converted[&anki_type.id] {
+				Some(note_type) => NotePlan::Note(note_type.clone(), values.clone()),
+				None => NotePlan::Plain(cards.iter()
+					.filter_map(|card| anki_type.templates.get(card.ord as usize).map(|template| (card.ord, template)))
+					.map(|(ord, (_, front, back))| {
+						let (question, _) = notes::render(&convert_template(front, &anki_type.fields), &anki_type.fields, &values);
+						let (answer, _) = notes::render(&convert_template(back, &anki_type.fields), &anki_type.fields, &values);
+						(ord, question.trim().to_owned(), answer.trim().to_owned())
+					})
+					.filter(|(_, question, answer)| !question.is_empty() && !answer.is_empty())
+					.collect()),
+			}
+		};
+
+		let keys: Vec<(String, String)> = plan.keys();
+		if keys.is_empty() || keys.iter().all(|(question, _)| question.is_empty()) {
+			report.skipped += 1;
+			continue;
+		}
+		let subject_keys: &mut Vec<(String, String)> = match seen.entry(subject_id) {
+			Entry::Occupied(entry) => entry.into_mut(),
+			Entry::Vacant(entry) => entry.insert(flashcards::load_all_flashcards(conn, subject_id)?.iter()
+				.map(|flashcard| duplicate_key(&flashcard.question, if flashcard.cloze_index > 0 { "" } else { &flashcard.answer }))
+				.collect()),
+		};
+		if keys.iter().any(|key| subject_keys.contains(key)) {
+			report.duplicates += 1;
+			continue;
+		}
+		subject_keys.extend(keys);
+
+		// Primary key here of each Anki card, found by its ordinal
+		let made: Vec<(i32, i32)> = match &plan {
+			NotePlan::Cloze(text) => {
+				let primary_keys: Vec<i64> = flashcards::add_cloze_flashcards(conn, subject_id, text)?;
+				let mut stmt: rusqlite::Statement<'_> = conn.prepare("SELECT cloze_index - 1, id FROM cards WHERE group_id = ?1;")?;
+				let made = stmt.query_map(params![primary_keys.first()], |row: &rusqlite::Row<'_>| Ok((row.get(0)?, row.get(1)?)))?;
+				made.collect::<rusqlite::Result<_>>()?
+			},
+			NotePlan::Note(note_type, values) => {
+				let (note_id, _) = notes::add_note(conn, subject_id, note_type.id, values)?;
+				let mut stmt: rusqlite::Statement<'_> = conn.prepare("SELECT template_position, id FROM cards WHERE note_id = ?1;")?;
+				let made = stmt.query_map(params![note_id], |row: &rusqlite::Row<'_>| Ok((row.get(0)?, row.get(1)?)))?;
+				made.collect::<rusqlite::Result<_>>()?
+			},
+			NotePlan::Plain(plain) => {
+				let mut made: Vec<(i32, i32)> = Vec::new();
+				for (ord, question, answer) in plain {
+					made.push((*ord, flashcards::add_new_flashcard(conn, subject_id, question, answer, &[])? as i32));
+				}
+				made
+			},
+		};
+
+		for (ord, primary_key) in &made {
+			if let Some(card) = cards.iter().find(|card| card.ord == *ord) {
+				let card_history: &[&AnkiReview] = history.get(&card.id).map(|history| history.as_slice()).unwrap_or(&[]);
+				apply_card(conn, *primary_key, card, card_history, package.created)?;
+			}
+		}
+		report.cards += made.len();
+		if !report.subjects.contains(&deck_name) {
+			report.subjects.push(deck_name);
+		}
+	}
+	Ok(report)
+}
+
+/* Adds an Anki package to the database, one subject per deck, then copies its media.
+The cards are added in one transaction, so a failure part way through adds nothing. */
+pub fn import_package(conn: &Connection, package: &AnkiPackage) -> Result<AnkiReport, String> {
+	let database_error = |e: rusqlite::Error| format!("Couldn't add the cards: {}", e);
+	let transaction: rusqlite::Transaction<'_> = conn.unchecked_transaction().map_err(database_error)?;
+	let mut report: AnkiReport = add_package(&transaction, package).map_err(database_error)?;
+	transaction.commit().map_err(database_error)?;
+
+	report.media = package.extract_media()?;
+	Ok(report)
+}
+
+/* **Explanation of Anki export**
+Exports are packages with an older (Schema 11) collection, which every version of Anki can read:
+	- Cards made from one of our notes become a note of the same note type, with the same templates
+	- Cloze cards become a Cloze note, cards made both ways a "Basic (and reversed card)" note and
+		the rest Basic notes. Alternate answers and distractors have nowhere to go and are left out
+	- Cards with an interval are review cards due on the same day as here, reviewed cards without
+		one are learning cards and the rest are new
+	- Images and sounds the cards use are copied from MEDIA_DIR
+*/
+const COLLECTION_SCHEMA: &str = "
+	CREATE TABLE col (id integer primary key, crt integer not null, mod integer not null, scm integer not null, ver integer not null,
+		dty integer not null, usn integer not null, ls integer not null, conf text not null, models text not null, decks text not null,
+		dconf text not null, tags text not null);
+	CREATE TABLE notes (id integer primary key, guid text not null, mid integer not null, mod integer not null, usn integer not null,
+		tags text not null, flds text not null, sfld integer not null, csum integer not null, flags integer not null, data text not null);
+	CREATE TABLE cards (id integer primary key, nid integer not null, did integer not null, ord integer not null, mod integer not null,
+		usn integer not null, type integer not null, queue integer not null, due integer not null, ivl integer not null, factor integer not null,
+		reps integer not null, lapses integer not null, left integer not null, odue integer not null, odid integer not null,
+		flags integer not null, data text not null);
+	CREATE TABLE revlog (id integer primary key, cid integer not null, usn integer not null, ease integer not null, ivl integer not null,
+		lastIvl integer not null, factor integer not null, time integer not null, type integer not null);
+	CREATE TABLE graves (usn integer not null, oid integer not null, type integer not null);
+	CREATE INDEX ix_notes_usn ON notes (usn);
+	CREATE INDEX ix_cards_usn ON cards (usn);
+	CREATE INDEX ix_revlog_usn ON revlog (usn);
+	CREATE INDEX ix_cards_nid ON cards (nid);
+	CREATE INDEX ix_cards_sched ON cards (did, queue, due);
+	CREATE INDEX ix_revlog_cid ON revlog (cid);
+	CREATE INDEX ix_notes_csum ON notes (csum);
+";
+
+const ANSWER_SEPARATOR: &str = "{{FrontSide}}\n\n<hr id=answer>\n\n"; // Start of Anki's answer side
+
+// A note being exported, with the cards made from it
+struct ExportNote<'a> {
+	guid: String, // Lets Anki update the note instead of adding it again when a subject is exported twice
+	note_type: usize, // Position in the exported note types
+	values: Vec<String>, // Plain text, turned into HTML when written
+	cards: Vec<(i32, &'a Flashcard)>, // Ordinal (Template, or cloze number - 1) and card
+}
+
+// What an export wrote
+pub struct AnkiExport {
+	pub cards: usize,
+	pub media: usize,
+}
+
+//* The reverse of `strip_html`: escapes the text, keeps new lines and turns "[image: cat.jpg]" back into an image */
+fn to_html(text: &str) -> String {
+	let escaped: String = text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('\n', "<br>");
+	let mut html: String = String::new();
+	let mut rest: &str = &escaped;
+	while let Some(start) = rest.find("[image: ") {
+		html.push_str(&rest[..start]);
+		let Some(end) = rest[start..].find(']') else { break };
+		let name: &str = &rest[start + "[image: ".len()..start + end];
+		html.push_str(&format!("<img src=\"{}\">", name.replace('"', "&quot;")));
+		rest = &rest[start + end + 1..];
+	}
+	html.push_str(rest);
+	html
+}
+
+//* Names of the files in MEDIA_DIR that a text shows ("[image: cat.jpg]") or plays ("[sound:cat.mp3]") */
+fn media_names(text: &str) -> Vec<String> {
+	let mut names: Vec<String> = Vec::new();
+	for marker in ["[image: ", "[sound:"] {
+		for part in text.split(marker).skip(1) {
+			if let Some((name, _)) = part.split_once(']') {
+				let name: &str = name.trim();
+				let safe: bool = !name.is_empty() && !name.contains(['/', '\\']) && !name.starts_with('.');
+				if safe && Path::new(MEDIA_DIR).join(name).is_file() {
+					names.push(name.to_owned());
+				}
+			}
+		}
+	}
+	names
+}
+
+//* Anki's own note types, made as they are needed */
+fn stock_note_type(id: i64, kind: &str) -> AnkiNoteType {
+	let template = |name: &str, front: &str, back: &str| (name.to_owned(), front.to_owned(), back.to_owned());
+	let mut note_type: AnkiNoteType = AnkiNoteType {
+		id,
+		name: "Basic".to_owned(),
+		cloze: false,
+		fields: vec!["Front".to_owned(), "Back".to_owned()],
+		templates: vec![template("Card 1", "{{Front}}", &format!("{}{{{{Back}}}}", ANSWER_SEPARATOR))],
+	};
+	match kind {
+		"Cloze" => {
+			note_type.name = "Cloze".to_owned();
+			note_type.cloze = true;
+			note_type.fields = vec!["Text".to_owned(), "Back Extra".to_owned()];
+			note_type.templates = vec![template("Cloze", "{{cloze:Text}}", "{{cloze:Text}}<br>\n{{Back Extra}}")];
+		},
+		"Reversed" => {
+			note_type.name = "Basic (and reversed card)".to_owned();
+			note_type.templates.push(template("Card 2", "{{Back}}", &format!("{}{{{{Front}}}}", ANSWER_SEPARATOR)));
+		},
+		_ => {},
+	}
+	note_type
+}
+
+//* One of our note types as an Anki note type, with its templates escaped and the answer below the question */
+fn export_note_type(id: i64, note_type: &NoteType) -> AnkiNoteType {
+	AnkiNoteType {
+		id,
+		name: note_type.name.clone(),
+		cloze: false,
+		fields: note_type.fields.clone(),
+		templates: note_type.templates.iter()
+			.map(|template| (template.name.clone(), to_html(&template.front), format!("{}{}", ANSWER_SEPARATOR, to_html(&template.back))))
+			.collect(),
+	}
+}
+
+//* A note type in the JSON form of Schema 11 collections */
+fn note_type_json(note_type: &AnkiNoteType, deck_id: i64, modified: i64) -> serde_json::Value {
+	serde_json::json!({
+		"id": note_type.id,
+		"name": note_type.name,
+		"type": if note_type.cloze { 1 } else { 0 },
+		"mod": modified,
+		"usn": 0,
+		"sortf": 0,
+		"did": deck_id,
+		"tmpls": note_type.templates.iter().enumerate().map(|(ord, (name, front, back))| serde_json::json!({
+			"name": name, "ord": ord, "qfmt": front, "afmt": back, "did": null, "bqfmt": "", "bafmt": "",
+		})).collect::<Vec<serde_json::Value>>(),
+		"flds": note_type.fields.iter().enumerate().map(|(ord, name)| serde_json::json!({
+			"name": name, "ord": ord, "sticky": false, "rtl": false, "font": "Arial", "size": 20, "media": [],
+		})).collect::<Vec<serde_json::Value>>(),
+		"css": ".card {\n font-family: arial;\n font-size: 20px;\n text-align: center;\n color: black;\n background-color: white;\n}\n",
+		"latexPre": "\\documentclass[12pt]{article}\n\\special{papersize=3in,5in}\n\\usepackage[utf8]{inputenc}\n\\usepackage{amssymb,amsmath}\n\\pagestyle{empty}\n\\setlength{\\parindent}{0in}\n\\begin{document}\n",
+		"latexPost": "\\end{document}",
+		"latexsvg": false,
+		"req": note_type.templates.iter().enumerate().map(|(ord, _)| serde_json::json!([ord, "any", [0]])).collect::<Vec<serde_json::Value>>(),
+		"tags": [],
+		"vers": [],
+	})
+}
+
+//* A deck in the JSON form of Schema 11 collections */
+fn deck_json(id: i64, name: &str, modified: i64) -> serde_json::Value {
+	serde_json::json!({
+		"id": id, "name": name, "mod": modified, "usn": 0, "desc": "", "dyn": 0, "conf": 1, "collapsed": false, "browserCollapsed": false,
+		"newToday": [0, 0], "revToday": [0, 0], "lrnToday": [0, 0], "timeToday": [0, 0], "extendNew": 0, "extendRev": 0,
+	})
+}
+
+//* Anki's type, queue, due, interval, ease factor and left (Learning steps) of a card */
+fn anki_scheduling(schedule: &Schedule, created: i64, position: i64) -> (i32, i32, i64, i64, i64, i64) {
+	let factor: i64 = (schedule.ease_factor * 1000.0).round() as i64;
+	if schedule.interval > 0 {
+		(2, 2, (schedule.due - created).div_euclid(SECONDS_PER_DAY), schedule.interval as i64, factor, 0)
+	} else if schedule.last_review > 0 {
+		(1, 1, schedule.due, 0, factor, 1001) // One step left, due in seconds
+	} else {
+		(0, 0, position, 0, 0, 0) // New cards are due in the order they were added
+	}
+}
+
+//* Groups the cards of a subject into the notes they are exported as, adding the note types they need */
+fn export_notes<'a>(conn: &Connection, flashcards: &'a [Flashcard], note_types: &mut Vec<AnkiNoteType>, base_id: i64) -> rusqlite::Result<Vec<ExportNote<'a>>> {
+	let mut stmt: rusqlite::Statement<'_> = conn.prepare("SELECT template_position FROM cards WHERE id = ?1;")?;
+	let mut exported: Vec<ExportNote<'a>> = Vec::new();
+	let mut kinds: Vec<String> = Vec::new(); // Key of each exported note type, e.g. "Basic" or "Note 3"
+	let mut type_for = |kind: String, note_types: &mut Vec<AnkiNoteType>, make: &dyn Fn(i64) -> rusqlite::Result<AnkiNoteType>| -> rusqlite::Result<usize> {
+		if let Some(position) = kinds.iter().position(|existing| *existing == kind) {
+			return Ok(position);
+		}
+		note_types.push(make(base_id + kinds.len() as i64)?);
+		kinds.push(kind);
+		Ok(kinds.len() - 1)
+	};
+
+	for flashcard in flashcards {
+		let (guid, ord): (String, i32) = if let Some(note_id) = flashcard.note_id {
+			(format!("flashcard-revision:note:{}", note_id), stmt.query_row(params![flashcard.primary_key], |row: &rusqlite::Row<'_>| row.get(0))?)
+		} else if flashcard.cloze_index > 0 {
+			(format!("flashcard-revision:cloze:{}", flashcard.group_id.unwrap_or(flashcard.primary_key as i64)), flashcard.cloze_index - 1)
+		} else if let Some(group_id) = flashcard.group_id {
+			(format!("flashcard-revision:reversed:{}", group_id), if flashcard.reversed { 1 } else { 0 })
+		} else {
+			(format!("flashcard-revision:card:{}", flashcard.primary_key), 0)
+		};
+		if let Some(note) = exported.iter_mut().find(|note| note.guid == guid) {
+			note.cards.push((ord, flashcard));
+			continue;
+		}
+
+		let (note_type, values): (usize, Vec<String>) = if let Some(note_id) = flashcard.note_id {
+			let note: Note = notes::load_note(conn, note_id)?;
+			let position: usize = type_for(format!("Note {}", note.note_type_id), note_types, &|id| {
+				Ok(export_note_type(id, &notes::load_note_type(conn, note.note_type_id)?))
+			})?;
+			(position, note.values)
+		} else if flashcard.cloze_index > 0 {
+			(type_for("Cloze".to_owned(), note_types, &|id| Ok(stock_note_type(id, "Cloze")))?, vec![flashcard.question.clone(), String::new()])
+		} else if flashcard.group_id.is_some() {
+			// The sibling's question is this card's answer, so either card gives the note's front and back
+			let values: Vec<String> = if flashcard.reversed {
+				vec![flashcard.answer.clone(), flashcard.question.clone()]
+			} else {
+				vec![flashcard.question.clone(), flashcard.answer.clone()]
+			};
+			(type_for("Reversed".to_owned(), note_types, &|id| Ok(stock_note_type(id, "Reversed")))?, values)
+		} else {
+			(type_for("Basic".to_owned(), note_types, &|id| Ok(stock_note_type(id, "Basic")))?, vec![flashcard.question.clone(), flashcard.answer.clone()])
+		};
+		exported.push(ExportNote { guid, note_type, values, cards: vec![(ord, flashcard)] });
+	}
+	Ok(exported)
+}
+
+//* Writes a subject into an empty Schema 11 collection, returning how many cards were written */
+fn write_collection(conn: &Connection, collection: &Connection, subject: &Subject, flashcards: &[Flashcard]) -> rusqlite::Result<usize> {
+	let now: i64 = Utc::now().timestamp();
+	let base_id: i64 = Utc::now().timestamp_millis(); // Anki ids are creation times in milliseconds
+	let deck_id: i64 = base_id;
+
+	// Day numbers count from the day of the most overdue card, so none of them are negative
+	let earliest: i64 = flashcards.iter()
+		.filter(|flashcard| flashcard.schedule.interval > 0)
+		.map(|flashcard| flashcard.schedule.due)
+		.min()
+		.unwrap_or(now)
+		.min(now);
+	let today: i64 = start_of_today();
+	let created: i64 = today - (today - earliest + SECONDS_PER_DAY - 1).div_euclid(SECONDS_PER_DAY).max(0) * SECONDS_PER_DAY;
+
+	let mut note_types: Vec<AnkiNoteType> = Vec::new();
+	let exported: Vec<ExportNote<'_>> = export_notes(conn, flashcards, &mut note_types, base_id + 1)?;
+
+	let mut stmt: rusqlite::Statement<'_> = conn.prepare("SELECT correct, incorrect FROM cards WHERE id = ?1;")?;
+	let history: HashMap<i32, Vec<Review>> = reviews::load_subject_reviews(conn, subject.id)?;
+	let mut review_ids: HashSet<i64> = HashSet::new();
+	let mut card_id: i64 = base_id;
+	for (position, note) in exported.iter().enumerate() {
+		let note_id: i64 = base_id + position as i64;
+		let fields: Vec<String> = note.values.iter().map(|value| to_html(value)).collect();
+		collection.execute(
+			"INSERT INTO notes (id, guid, mid, mod, usn, tags, flds, sfld, csum, flags, data) VALUES (?1, ?2, ?3, ?4, 0, '', ?5, ?6, 0, 0, '');",
+			params![note_id, note.guid, note_types[note.note_type].id, now, fields.join("\x1f"), note.values.first()],
+		)?; // Anki works out the checksum used to find duplicates when the notes are checked
+
+		for (ord, flashcard) in &note.cards {
+			card_id += 1;
+			let (correct, incorrect): (i64, i64) = stmt.query_row(params![flashcard.primary_key], |row: &rusqlite::Row<'_>| Ok((row.get(0)?, row.get(1)?)))?;
+			let (kind, queue, due, interval, factor, left) = anki_scheduling(&flashcard.schedule, created, position as i64 + 1);
+			let data: String = if flashcard.schedule.stability > 0.0 {
+				serde_json::json!({ "s": flashcard.schedule.stability, "d": flashcard.schedule.difficulty }).to_string()
+			} else {
+				String::new()
+			};
+			collection.execute(
+				"INSERT INTO cards (id, nid, did, ord, mod, usn, type, queue, due, ivl, factor, reps, lapses, left, odue, odid, flags, data)
+					VALUES (?1, ?2, ?3, ?4, ?5, 0, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, 0, 0, 0, ?14);",
+				params![card_id, note_id, deck_id, ord, now, kind, queue, due, interval, factor, correct + incorrect, incorrect, left, data],
+			)?;
+
+			for review in history.get(&flashcard.primary_key).into_iter().flatten() {
+				// Review ids are times in milliseconds, so answers in the same second are moved apart
+				let mut review_id: i64 = review.reviewed_at * 1000;
+				while !review_ids.insert(review_id) {
+					review_id += 1;
+				}
+				collection.execute(
+					"INSERT INTO revlog (id, cid, usn, ease, ivl, lastIvl, factor, time, type) VALUES (?1, ?2, 0, ?3, ?4, ?5, ?6, ?7, ?8);",
+					params![
+						review_id, card_id, review.grade as i32, review.interval_after, review.interval_before, factor,
+						review.response_ms.min(60_000), if review.interval_before > 0 { 1 } else { 0 } // 0 = learning, 1 = review
+					],
+				)?;
+			}
+		}
+	}
+
+	let models: serde_json::Map<String, serde_json::Value> = note_types.iter()
+		.map(|note_type| (note_type.id.to_string(), note_type_json(note_type, deck_id, now)))
+		.collect();
+	let decks: serde_json::Value = serde_json::json!({ "1": deck_json(1, "Default", now), deck_id.to_string(): deck_json(deck_id, &subject.name, now) });
+	let deck_options: serde_json::Value = serde_json::json!({ "1": {
+		"id": 1, "name": "Default", "mod": now, "usn": 0, "maxTaken": 60, "autoplay": true, "timer": 0, "replayq": true, "dyn": false,
+		"new": { "delays": [1.0, 10.0], "ints": [1, 4, 0], "initialFactor": 2500, "order": 1, "perDay": 20, "bury": false },
+		"rev": { "perDay": 200, "ease4": 1.3, "ivlFct": 1.0, "maxIvl": 36500, "hardFactor": 1.2, "bury": false },
+		"lapse": { "delays": [10.0], "mult": 0.0, "minInt": 1, "leechFails": 8, "leechAction": 1 },
+	}});
+	let config: serde_json::Value = serde_json::json!({
+		"nextPos": exported.len() + 1, "estTimes": true, "activeDecks": [1], "sortType": "noteFld", "timeLim": 0, "sortBackwards": false,
+		"addToCur": true, "curDeck": 1, "newSpread": 0, "dueCounts": true, "curModel": note_types.first().map(|note_type| note_type.id), "collapseTime": 1200,
+	});
+	collection.execute(
+		"INSERT INTO col (id, crt, mod, scm, ver, dty, usn, ls, conf, models, decks, dconf, tags) VALUES (1, ?1, ?2, ?2, 11, 0, 0, 0, ?3, ?4, ?5, ?6, '{}');",
+		params![created, base_id, config.to_string(), serde_json::Value::Object(models).to_string(), decks.to_string(), deck_options.to_string()],
+	)?;
+	Ok(card_id as usize - base_id as usize)
+}
+
+//* Writes a subject as an .apkg file with its notes, scheduling, review history and media */
+pub fn export_package(conn: &Connection, subject: &Subject, path: &str) -> Result<AnkiExport, String> {
+	let flashcards: Vec<Flashcard> = flashcards::load_all_flashcards(conn, subject.id).map_err(|e| format!("Couldn't read the flashcards: {}", e))?;
+
+	// The collection is made in a temporary file, then copied into the package
+	let temp: TempCollection = TempCollection::new("export");
+	let cards: usize = Connection::open(&temp.path).and_then(|collection| {
+		collection.execute_batch(COLLECTION_SCHEMA)?;
+		let transaction: rusqlite::Transaction<'_> = collection.unchecked_transaction()?;
+		let written: usize = write_collection(conn, &transaction, subject, &flashcards)?;
+		transaction.commit()?;
+		Ok(written)
+	}).map_err(|e| format!("Couldn't write the collection: {}", e))?;
+	let bytes: Vec<u8> = std::fs::read(&temp.path).map_err(|e| format!("Couldn't read the collection back: {}", e))?;
+	drop(temp);
+
+	let mut media: Vec<String> = Vec::new();
+	for flashcard in &flashcards {
+		for name in media_names(&flashcard.question).into_iter().chain(media_names(&flashcard.answer)) {
+			if !media.contains(&name) {
+				media.push(name);
+			}
+		}
+	}
+
+	let write_error = |e: &dyn std::fmt::Display| format!("Couldn't write {}: {}", path, e);
+	let file: File = File::create(path).map_err(|e| write_error(&e))?;
+	let mut archive: zip::ZipWriter<File> = zip::ZipWriter::new(file);
+	let options: zip::write::SimpleFileOptions = zip::write::SimpleFileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+	archive.start_file("collection.anki2", options).map_err(|e| write_error(&e))?;
+	archive.write_all(&bytes).map_err(|e| write_error(&e))?;
+
+	// Media files are stored as "0", "1"... with a JSON list of their names
+	let names: serde_json::Map<String, serde_json::Value> = media.iter().enumerate()
+		.map(|(position, name)| (position.to_string(), serde_json::Value::from(name.as_str())))
+		.collect();
+	archive.start_file("media", options).map_err(|e| write_error(&e))?;
+	archive.write_all(serde_json::Value::Object(names).to_string().as_bytes()).map_err(|e| write_error(&e))?;
+	for (position, name) in media.iter().enumerate() {
+		let contents: Vec<u8> = std::fs::read(Path::new(MEDIA_DIR).join(name)).map_err(|e| format!("Couldn't read {}: {}", name, e))?;
+		archive.start_file(position.to_string(), options).map_err(|e| write_error(&e))?;
+		archive.write_all(&contents).map_err(|e| write_error(&e))?;
+	}
+	archive.finish().map_err(|e| write_error(&e))?;
+
+	Ok(AnkiExport { cards, media: media.len() })
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn temp_collections_are_unique_and_removed() {
+		let first: TempCollection = TempCollection::new("test");
+		let second: TempCollection = TempCollection::new("test");
+		assert_ne!(first.path, second.path);
+		std::fs::write(&first.path, b"collection").unwrap();
+		let path: PathBuf = first.path.clone();
+		drop(first);
+		assert!(!path.exists());
+	}
+
+	#[test]
+	fn exported_packages_read_back() {
+		let conn: Connection = database::open_database(":memory:").unwrap();
+		let subject_id: i64 = database::get_or_add_subject(&conn, "French").unwrap();
+		let forward: i64 = flashcards::add_new_flashcard(&conn, subject_id, "dog", "chien", &[]).unwrap();
+		flashcards::add_reverse_flashcard(&conn, forward as i32).unwrap();
+		flashcards::add_cloze_flashcards(&conn, subject_id, "{{c1::Paris}} is in {{c2::France}}").unwrap();
+		let subject: Subject = Subject { id: subject_id, name: "French".to_owned(), algorithm: Algorithm::Sm2 };
+
+		let path: PathBuf = std::env::temp_dir().join(format!("flashcard-core-export-{}.apkg", std::process::id()));
+		let export: AnkiExport = export_package(&conn, &subject, path.to_str().unwrap()).unwrap();
+		assert_eq!((export.cards, export.media), (4, 0));
+		let package: AnkiPackage = AnkiPackage::read(path.to_str().unwrap()).unwrap();
+		let _ = std::fs::remove_file(&path);
+		assert_eq!((package.note_count(), package.card_count()), (2, 4));
+		assert_eq!(package.summary()[0], "Deck French: 2 notes, 4 cards");
+
+		// Into a new database, where it makes the same cards
+		let other: Connection = database::open_database(":memory:").unwrap();
+		let report: AnkiReport = import_package(&other, &package).unwrap();
+		assert_eq!((report.cards, report.duplicates, report.subjects), (4, 0, vec!["French".to_owned()]));
+		let cards: Vec<Flashcard> = flashcards::load_all_flashcards(&other, database::get_subjects(&other).unwrap()[0].id).unwrap();
+		assert_eq!(cards.iter().filter(|card| card.cloze_index > 0).count(), 2);
+		assert_eq!(cards.iter().filter(|card| card.question == "chien").count(), 1);
+	}
+
+	fn card(id: i64, note_id: i64, deck_id: i64, ord: i32) -> AnkiCard {
+		AnkiCard { id, note_id, deck_id, ord, kind: 0, queue: 0, due: 0, interval: 0, factor: 0, stability: 0.0, difficulty: 0.0 }
+	}
+
+	#[test]
+	fn notes_split_across_decks_stay_together() {
+		let basic: AnkiNoteType = AnkiNoteType {
+			id: 1,
+			name: "Basic (and reversed card)".to_owned(),
+			cloze: false,
+			fields: vec!["Front".to_owned(), "Back".to_owned()],
+			templates: vec![
+				("Card 1".to_owned(), "{{Front}}".to_owned(), "{{FrontSide}}<hr id=answer>{{Back}}".to_owned()),
+				("Card 2".to_owned(), "{{Back}}".to_owned(), "{{FrontSide}}<hr id=answer>{{Front}}".to_owned()),
+			],
+		};
+		let package: AnkiPackage = AnkiPackage {
+			path: PathBuf::new(),
+			created: 0,
+			compressed_media: false,
+			decks: HashMap::from([(10, "French".to_owned()), (20, "French::Reverse".to_owned())]),
+			note_types: vec![basic],
+			notes: vec![
+				AnkiNote { id: 100, note_type_id: 1, values: vec!["dog".to_owned(), "chien".to_owned()] },
+				AnkiNote { id: 200, note_type_id: 1, values: vec!["cat".to_owned(), "chat".to_owned()] },
+			],
+			// The reverse of dog is in the other deck
+			cards: vec![card(1, 100, 10, 0), card(2, 100, 20, 1), card(3, 200, 10, 0), card(4, 200, 10, 1)],
+			reviews: Vec::new(),
+			media: Vec::new(),
+		};
+		assert_eq!(package.summary()[0], "Deck French: 2 notes, 4 cards (1 from other decks, kept with their note)");
+		assert!(!package.summary().iter().any(|line| line.starts_with("Deck French::Reverse")));
+
+		let conn: Connection = database::open_database(":memory:").unwrap();
+		let report: AnkiReport = import_package(&conn, &package).unwrap();
+		assert_eq!((report.cards, report.subjects), (4, vec!["French".to_owned()]));
+		let subjects: Vec<Subject> = database::get_subjects(&conn).unwrap();
+		assert_eq!(subjects.len(), 1);
+		assert_eq!(flashcards::load_all_flashcards(&conn, subjects[0].id).unwrap().len(), 4);
+	}
+}
@@ -0,0 +1,392 @@
+use chrono::{Local, NaiveDate, NaiveTime, TimeZone}; // Handles due dates
+
+use rusqlite::Connection; // Handles SQLite database
+
+use crate::cloze;
+use crate::flashcards::{self, FlashcardSnapshot};
+use crate::formats::{ImportCard, PreviewRow, RowStatus};
+use crate::scheduler::tier_name;
+
+// Delimiters tried when sniffing, in order of preference when they tie
+const DELIMITERS: [u8; 4] = [b'\t', b',', b';', b'|'];
+const SNIFF_ROWS: usize = 20; // Rows read when sniffing the delimiter
+const DATE_FORMAT: &str = "%Y-%m-%d";
+
+// What a column of the file is read as
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Column {
+	Ignore,
+	Question,
+	Answer,
+	Alternates, // One per line within the cell
+	Distractors, // One per line within the cell
+	MultipleChoice, // "yes" or "no"
+	Interval, // Days
+	Due, // YYYY-MM-DD, empty for a new card
+	Correct,
+	Incorrect,
+}
+
+impl Column {
+	// Order the mapping buttons cycle through
+	const ALL: [Column; 10] = [
+		Column::Ignore, Column::Question, Column::Answer, Column::Alternates, Column::Distractors,
+		Column::MultipleChoice, Column::Interval, Column::Due, Column::Correct, Column::Incorrect,
+	];
+
+	pub fn name(&self) -> &'static str {
+		match self {
+			Column::Ignore => "Ignore",
+			Column::Question => "Question",
+			Column::Answer => "Answer",
+			Column::Alternates => "Alternates",
+			Column::Distractors => "Distractors",
+			Column::MultipleChoice => "Multiple choice",
+			Column::Interval => "Interval",
+			Column::Due => "Due",
+			Column::Correct => "Correct",
+			Column::Incorrect => "Incorrect",
+		}
+	}
+
+	//* Works out a column from its header, including names other apps use. Headers that aren't known are ignored */
+	fn from_header(header: &str) -> Option<Column> {
+		match header.trim().to_lowercase().as_str() {
+			"question" | "front" | "term" | "prompt" => Some(Column::Question),
+			"answer" | "back" | "definition" => Some(Column::Answer),
+			"alternates" | "also accepted" | "alternate answers" => Some(Column::Alternates),
+			"distractors" | "wrong options" => Some(Column::Distractors),
+			"multiple choice" => Some(Column::MultipleChoice),
+			"interval" => Some(Column::Interval),
+			"due" => Some(Column::Due),
+			"correct" => Some(Column::Correct),
+			"incorrect" => Some(Column::Incorrect),
+			"tier" => Some(Column::Ignore), // Worked out from the interval, so only exported
+			_ => None,
+		}
+	}
+
+	//* The next column kind, used by the mapping button */
+	pub fn next(&self) -> Column {
+		let index: usize = Column::ALL.iter().position(|column| column == self).unwrap_or(0);
+		Column::ALL[(index + 1) % Column::ALL.len()]
+	}
+}
+
+//* Display name of a delimiter */
+pub fn delimiter_name(delimiter: u8) -> &'static str {
+	match delimiter {
+		b'\t' => "Tab",
+		b',' => "Comma",
+		b';' => "Semicolon",
+		b'|' => "Bar",
+		_ => "Other",
+	}
+}
+
+//* The next delimiter, used by the delimiter button */
+pub fn next_delimiter(delimiter: u8) -> u8 {
+	let index: usize = DELIMITERS.iter().position(|candidate| *candidate == delimiter).unwrap_or(0);
+	DELIMITERS[(index + 1) % DELIMITERS.len()]
+}
+
+//* Reads every row of the text, rows may have different lengths */
+fn read_rows(text: &str, delimiter: u8) -> Result<Vec<Vec<String>>, String> {
+	let mut reader = csv::ReaderBuilder::new()
+		.delimiter(delimiter)
+		.has_headers(false)
+		.flexible(true)
+		.from_reader(text.as_bytes());
+	let mut rows: Vec<Vec<String>> = Vec::new();
+	for record in reader.records() {
+		let record: csv::StringRecord = record.map_err(|e| format!("Couldn't read the file: {}", e))?;
+		if record.iter().all(|cell| cell.trim().is_empty()) {
+			continue; // Blank line
+		}
+		rows.push(record.iter().map(|cell| cell.trim().to_owned()).collect());
+	}
+	Ok(rows)
+}
+
+/* **Explanation of delimiter sniffing**
+	- The first few rows are read with each delimiter
+	- A delimiter scores by how many rows split into the most common number of cells, as long as that is at least 2
+	- Ties go to the delimiter earliest in DELIMITERS, so tabs win over commas inside text
+	- Comma is used if nothing splits the rows
+*/
+pub fn sniff_delimiter(text: &str) -> u8 {
+	let mut best: (u8, usize) = (b',', 0);
+	for delimiter in DELIMITERS {
+		let rows: Vec<Vec<String>> = match read_rows(text, delimiter) {
+			Ok(rows) => rows.into_iter().take(SNIFF_ROWS).collect(),
+			Err(_) => continue,
+		};
+		let mut counts: Vec<usize> = rows.iter().map(|row| row.len()).collect();
+		counts.sort_unstable();
+		let common: usize = counts.iter()
+			.max_by_key(|count| (counts.iter().filter(|other| other == count).count(), **count))
+			.copied()
+			.unwrap_or(0);
+		if common < 2 {
+			continue;
+		}
+		let score: usize = counts.iter().filter(|count| **count == common).count();
+		if score > best.1 {
+			best = (delimiter, score);
+		}
+	}
+	best.0
+}
+
+// A CSV/TSV file read for importing, with how its columns are mapped to cards
+pub struct DelimitedFile {
+	pub delimiter: u8,
+	pub has_header: bool, // First row names the columns instead of being a card
+	pub columns: Vec<Column>, // One per column of the widest row
+	rows: Vec<Vec<String>>, // Including the header row
+}
+
+impl DelimitedFile {
+	//* Reads a file with the delimiter sniffed and the header and columns guessed */
+	pub fn parse(text: &str) -> Result<DelimitedFile, String> {
+		DelimitedFile::parse_with(text, sniff_delimiter(text))
+	}
+
+	//* Reads a file with a chosen delimiter, guessing the header and columns */
+	pub fn parse_with(text: &str, delimiter: u8) -> Result<DelimitedFile, String> {
+		let rows: Vec<Vec<String>> = read_rows(text, delimiter)?;
+		if rows.is_empty() {
+			return Err("The file has no rows.".to_owned());
+		}
+		// A header is a first row that names at least one known column
+		let has_header: bool = rows[0].iter().any(|cell| Column::from_header(cell).is_some_and(|column| column != Column::Ignore));
+		let mut file: DelimitedFile = DelimitedFile { delimiter, has_header, columns: Vec::new(), rows };
+		file.guess_columns();
+		Ok(file)
+	}
+
+	//* Maps the columns from the header, or as question then answer if there isn't one */
+	pub fn guess_columns(&mut self) {
+		let width: usize = self.rows.iter().map(|row| row.len()).max().unwrap_or(0);
+		self.columns = (0..width)
+			.map(|index| {
+				if self.has_header {
+					self.rows[0].get(index).and_then(|header| Column::from_header(header)).unwrap_or(Column::Ignore)
+				} else {
+					match index {
+						0 => Column::Question,
+						1 => Column::Answer,
+						_ => Column::Ignore,
+					}
+				}
+			})
+			.collect();
+	}
+
+	//* Name shown for a column, its header if there is one */
+	pub fn column_name(&self, index: usize) -> String {
+		match self.rows[0].get(index) {
+			Some(header) if self.has_header && !header.is_empty() => header.clone(),
+			_ => format!("Column {}", index + 1),
+		}
+	}
+
+	//* Turns each row after the header into a card, marking rows that can't be one as invalid */
+	pub fn preview(&self) -> Vec<PreviewRow> {
+		let skip: usize = if self.has_header { 1 } else { 0 };
+		self.rows.iter()
+			.enumerate()
+			.skip(skip)
+			.map(|(index, row)| {
+				let (card, problem): (ImportCard, Option<String>) = self.read_card(row);
+				let status: RowStatus = match problem.or_else(|| card.problem()) {
+					Some(problem) => RowStatus::Invalid(problem),
+					None => RowStatus::Ready,
+				};
+				PreviewRow { line: index + 1, card, status }
+			})
+			.collect()
+	}
+
+	//* Reads one row using the column mapping. Returns the first problem found as well */
+	fn read_card(&self, row: &[String]) -> (ImportCard, Option<String>) {
+		let mut card: ImportCard = ImportCard::new("", "");
+		let mut problem: Option<String> = None;
+		let mut interval: Option<i32> = None;
+		let mut due: Option<i64> = None;
+
+		for (column, cell) in self.columns.iter().zip(row.iter()) {
+			let result: Result<(), String> = match column {
+				Column::Ignore => Ok(()),
+				Column::Question => { card.question = cell.clone(); Ok(()) },
+				Column::Answer => { card.answer = cell.clone(); Ok(()) },
+				Column::Alternates => { card.alternates = flashcards::parse_alternates(cell); Ok(()) },
+				Column::Distractors => { card.distractors = flashcards::parse_alternates(cell); Ok(()) },
+				Column::MultipleChoice => match cell.to_lowercase().as_str() {
+					"yes" | "true" | "1" => { card.multiple_choice = true; Ok(()) },
+					"no" | "false" | "0" | "" => Ok(()),
+					_ => Err(format!("Multiple choice '{}' isn't yes or no", cell)),
+				},
+				Column::Interval => parse_count(cell, "Interval").map(|days| interval = Some(days)),
+				Column::Due => parse_due(cell).map(|seconds| due = Some(seconds)),
+				Column::Correct => parse_count(cell, "Correct").map(|count| card.correct = count),
+				Column::Incorrect => parse_count(cell, "Incorrect").map(|count| card.incorrect = count),
+			};
+			if let Err(e) = result {
+				problem = problem.or(Some(e));
+			}
+		}
+
+		if let Some(days) = interval {
+			card.schedule.interval = days;
+			card.schedule.repetitions = if days > 0 { 2 } else { 0 }; // So SM-2 carries on from the interval instead of restarting at 1 day
+		}
+		if let Some(seconds) = due {
+			card.schedule.due = seconds;
+		}
+		(card, problem)
+	}
+}
+
+fn parse_count(cell: &str, name: &str) -> Result<i32, String> {
+	if cell.is_empty() {
+		return Ok(0);
+	}
+	match cell.parse::<i32>() {
+		Ok(count) if count >= 0 => Ok(count),
+		_ => Err(format!("{} '{}' isn't a whole number", name, cell)),
+	}
+}
+
+//* Reads a due date as local midnight, empty means due straight away */
+fn parse_due(cell: &str) -> Result<i64, String> {
+	if cell.is_empty() {
+		return Ok(0);
+	}
+	NaiveDate::parse_from_str(cell, DATE_FORMAT)
+		.ok()
+		.and_then(|date| Local.from_local_datetime(&date.and_time(NaiveTime::MIN)).earliest())
+		.map(|midnight| midnight.timestamp())
+		.ok_or(format!("Due '{}' isn't a YYYY-MM-DD date", cell))
+}
+
+//* Delimiter of an export file, from its extension */
+pub fn delimiter_for_path(path: &str) -> u8 {
+	if path.to_lowercase().ends_with(".tsv") { b'\t' } else { b',' }
+}
+
+/* Writes every card of a subject as CSV/TSV, with a header row that `DelimitedFile::parse` maps back.
+Cloze, reverse and note cards are written as the plain question and answer they are revised as. */
+pub fn export(conn: &Connection, subject_id: i64, delimiter: u8) -> Result<(String, usize), String> {
+	let snapshots: Vec<FlashcardSnapshot> = flashcards::load_all_flashcards(conn, subject_id)
+		.and_then(|all| all.iter().map(|flashcard| flashcards::load_snapshot(conn, flashcard.primary_key)).collect())
+		.map_err(|e| format!("Couldn't read the flashcards: {}", e))?;
+
+	let mut writer = csv::WriterBuilder::new().delimiter(delimiter).from_writer(Vec::new());
+	let header: [&str; 10] = [
+		"Question", "Answer", "Alternates", "Distractors", "Multiple choice", "Tier", "Interval", "Due", "Correct", "Incorrect",
+	];
+	writer.write_record(header).map_err(|e| e.to_string())?;
+	for snapshot in &snapshots {
+		let (question, answer): (String, String) = if snapshot.cloze_index > 0 {
+			(cloze::question(&snapshot.question, snapshot.cloze_index), snapshot.answer.clone())
+		} else {
+			(snapshot.question.clone(), snapshot.answer.clone())
+		};
+		let due: String = match Local.timestamp_opt(snapshot.schedule.due, 0).single() {
+			Some(date) if snapshot.schedule.due > 0 => date.format(DATE_FORMAT).to_string(),
+			_ => String::new(), // New card
+		};
+		writer.write_record([
+			question,
+			answer,
+			snapshot.alternates.join("\n"),
+			snapshot.distractors.join("\n"),
+			if snapshot.multiple_choice { "yes".to_owned() } else { "no".to_owned() },
+			tier_name(snapshot.schedule.tier()).to_owned(),
+			snapshot.schedule.interval.to_string(),
+			due,
+			snapshot.correct.to_string(),
+			snapshot.incorrect.to_string(),
+		]).map_err(|e| e.to_string())?;
+	}
+
+	let bytes: Vec<u8> = writer.into_inner().map_err(|e| e.to_string())?;
+	let text: String = String::from_utf8(bytes).map_err(|e| e.to_string())?;
+	Ok((text, snapshots.len()))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn cards(file: &DelimitedFile) -> Vec<(String, String)> {
+		file.preview().into_iter().map(|row| (row.card.question, row.card.answer)).collect()
+	}
+
+	#[test]
+	fn sniffs_the_delimiter() {
+		assert_eq!(sniff_delimiter("dog,chien\ncat,chat\n"), b',');
+		assert_eq!(sniff_delimiter("dog\tchien\ncat\tchat\n"), b'\t');
+		assert_eq!(sniff_delimiter("dog;chien\ncat;chat\n"), b';');
+		assert_eq!(sniff_delimiter("dog|chien\ncat|chat\n"), b'|');
+		// Commas inside tab separated text don't make it CSV
+		assert_eq!(sniff_delimiter("red, green\trouge, vert\nblue, black\tbleu, noir\n"), b'\t');
+		assert_eq!(sniff_delimiter("a,b\tc\nd,e\tf\n"), b'\t'); // Both split every row, so tabs win the tie
+		assert_eq!(sniff_delimiter("a\tb,c\nd,e\n"), b','); // Only commas split every row
+		assert_eq!(sniff_delimiter("just one column\nanother\n"), b','); // Nothing splits the rows
+	}
+
+	#[test]
+	fn quoted_fields_keep_their_delimiters() {
+		let text: &str = "\"Paris, France\",capital\n\"He said \"\"hi\"\"\",quote\n\"one\ntwo\",lines\n";
+		assert_eq!(sniff_delimiter(text), b',');
+		let file: DelimitedFile = DelimitedFile::parse(text).unwrap();
+		assert!(!file.has_header);
+		assert_eq!(cards(&file), vec![
+			("Paris, France".to_owned(), "capital".to_owned()),
+			("He said \"hi\"".to_owned(), "quote".to_owned()),
+			("one\ntwo".to_owned(), "lines".to_owned()),
+		]);
+	}
+
+	#[test]
+	fn maps_columns_from_the_header() {
+		let text: &str = "Notes,Term,Definition,Also accepted,Tier,Interval,Multiple choice\nx,dog,chien,\"le chien\ntoutou\",Strong,30,yes\n";
+		let file: DelimitedFile = DelimitedFile::parse(text).unwrap();
+		assert!(file.has_header);
+		assert_eq!(file.columns, vec![
+			Column::Ignore, Column::Question, Column::Answer, Column::Alternates, Column::Ignore, Column::Interval, Column::MultipleChoice,
+		]);
+		assert_eq!(file.column_name(1), "Term");
+
+		let rows: Vec<PreviewRow> = file.preview();
+		assert_eq!(rows.len(), 1);
+		assert_eq!(rows[0].line, 2);
+		assert_eq!(rows[0].status, RowStatus::Ready);
+		assert_eq!(rows[0].card.alternates, vec!["le chien".to_owned(), "toutou".to_owned()]);
+		assert_eq!((rows[0].card.schedule.interval, rows[0].card.schedule.repetitions), (30, 2));
+		assert!(rows[0].card.multiple_choice);
+	}
+
+	#[test]
+	fn files_without_a_header_are_question_then_answer() {
+		let file: DelimitedFile = DelimitedFile::parse("dog\tchien\textra\n\ncat\tchat\n").unwrap();
+		assert!(!file.has_header);
+		assert_eq!(file.columns, vec![Column::Question, Column::Answer, Column::Ignore]);
+		assert_eq!(file.column_name(0), "Column 1");
+		assert_eq!(cards(&file), vec![("dog".to_owned(), "chien".to_owned()), ("cat".to_owned(), "chat".to_owned())]);
+		assert_eq!(DelimitedFile::parse("\n\n").err(), Some("The file has no rows.".to_owned()));
+	}
+
+	#[test]
+	fn marks_rows_that_cant_be_cards() {
+		let text: &str = "question,answer,interval,due,multiple choice\ndog,,,,\ncat,chat,-2,,\nbird,oiseau,,31/12/2024,\nfish,poisson,,,maybe\n";
+		let rows: Vec<PreviewRow> = DelimitedFile::parse(text).unwrap().preview();
+		let statuses: Vec<RowStatus> = rows.into_iter().map(|row| row.status).collect();
+		assert!(matches!(&statuses[0], RowStatus::Invalid(_)));
+		assert_eq!(statuses[1], RowStatus::Invalid("Interval '-2' isn't a whole number".to_owned()));
+		assert_eq!(statuses[2], RowStatus::Invalid("Due '31/12/2024' isn't a YYYY-MM-DD date".to_owned()));
+		assert_eq!(statuses[3], RowStatus::Invalid("Multiple choice 'maybe' isn't yes or no".to_owned()));
+	}
+}
@@ -0,0 +1,709 @@
+use std::collections::HashMap;
+
+use rusqlite::Connection; // Handles SQLite database
+
+use serde::{Deserialize, Serialize}; // Handles JSON decks
+
+use crate::cloze;
+use crate::database::{self, Subject};
+use crate::flashcards::{self, Flashcard, FlashcardSnapshot};
+use crate::formats::{self, ImportCard, PreviewRow, RowStatus};
+use crate::reviews::{self, Review};
+use crate::scheduler::{self, Algorithm, Grade, Schedule};
+
+/* **Explanation of JSON decks**
+A JSON deck is a whole subject: its cards, tiers, counters, scheduling and review log. The layout is
+described by schemas/deck.v1.schema.json, and files say which version of it they follow:
+
+	{ "format": "flashcard-revision-deck", "version": 1, "subject": { "name": "French", ... }, "cards": [...] }
+
+	- Files are checked strictly before anything is imported. Unknown fields, values out of range and
+		cards that don't fit together are all reported (e.g. "cards[2].schedule.ease_factor must be
+		at least 1.3"), and a file with any problem is not imported at all
+	- Files from a newer version are refused, since they may hold things this version would lose
+	- Cards made together share a "group" number (Only meaningful within the file): every deletion of a
+		cloze text, or a card and its reverse. Cards made from notes are written as plain cards
+	- "tier" is worked out from the interval, so it is only checked against it
+	- Cards keep their ids, so a card whose id is already in the database is skipped. A new subject
+		gets the algorithm and tags of the file, an existing one keeps its own
+*/
+pub const FORMAT: &str = "flashcard-revision-deck";
+pub const VERSION: u64 = 1;
+pub const SCHEMA_ID: &str = "deck.v1.schema.json";
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct JsonDeck {
+	#[serde(rename = "$schema", default, skip_serializing_if = "Option::is_none")]
+	pub schema: Option<String>,
+	pub format: String,
+	pub version: u64,
+	pub subject: JsonSubject,
+	pub cards: Vec<JsonCard>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct JsonSubject {
+	pub name: String,
+	#[serde(default = "default_algorithm")]
+	pub algorithm: String,
+	#[serde(default)]
+	pub tags: Vec<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct JsonCard {
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub id: Option<String>,
+	pub question: String, // Whole cloze text for cloze cards
+	#[serde(default)]
+	pub answer: String,
+	#[serde(default)]
+	pub alternates: Vec<String>,
+	#[serde(default)]
+	pub distractors: Vec<String>,
+	#[serde(default)]
+	pub multiple_choice: bool,
+	#[serde(default)]
+	pub cloze_index: i32,
+	#[serde(default)]
+	pub reversed: bool,
+	#[serde(default)]
+	pub group: Option<i64>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub tier: Option<String>,
+	#[serde(default)]
+	pub correct: i32,
+	#[serde(default)]
+	pub incorrect: i32,
+	#[serde(default)]
+	pub schedule: JsonSchedule,
+	#[serde(default)]
+	pub reviews: Vec<JsonReview>,
+}
+
+// Same fields as `Schedule`, with missing ones taken from a new card
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields, default)]
+pub struct JsonSchedule {
+	pub ease_factor: f64,
+	pub interval: i32,
+	pub repetitions: i32,
+	pub due: i64,
+	pub stability: f64,
+	pub difficulty: f64,
+	pub last_review: i64,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct JsonReview {
+	pub reviewed_at: i64,
+	pub grade: i32,
+	#[serde(default)]
+	pub response_ms: i64,
+	#[serde(default = "default_algorithm")]
+	pub scheduler: String,
+	#[serde(default)]
+	pub interval_before: i32,
+	#[serde(default)]
+	pub interval_after: i32,
+}
+
+// What an import did
+pub struct JsonReport {
+	pub added: usize, // Flashcards made
+	pub reviews: usize,
+	pub skipped: usize, // Cards already imported or duplicates
+}
+
+fn default_algorithm() -> String {
+	Algorithm::Sm2.name().to_owned()
+}
+
+impl Default for JsonSchedule {
+	fn default() -> JsonSchedule {
+		JsonSchedule::from(Schedule::default())
+	}
+}
+
+impl From<Schedule> for JsonSchedule {
+	fn from(schedule: Schedule) -> JsonSchedule {
+		JsonSchedule {
+			ease_factor: schedule.ease_factor,
+			interval: schedule.interval,
+			repetitions: schedule.repetitions,
+			due: schedule.due,
+			stability: schedule.stability,
+			difficulty: schedule.difficulty,
+			last_review: schedule.last_review,
+		}
+	}
+}
+
+impl JsonSchedule {
+	fn to_schedule(&self) -> Schedule {
+		Schedule {
+			ease_factor: self.ease_factor,
+			interval: self.interval,
+			repetitions: self.repetitions,
+			due: self.due,
+			stability: self.stability,
+			difficulty: self.difficulty,
+			last_review: self.last_review,
+		}
+	}
+
+	//* Adds a problem for each field out of range, named from `path` */
+	fn check(&self, path: &str, problems: &mut Vec<String>) {
+		if self.ease_factor < 1.3 {
+			problems.push(format!("{}.ease_factor must be at least 1.3", path));
+		}
+		for (name, value) in [("interval", self.interval as i64), ("repetitions", self.repetitions as i64), ("due", self.due), ("last_review", self.last_review)] {
+			if value < 0 {
+				problems.push(format!("{}.{} can't be negative", path, name));
+			}
+		}
+		if self.stability < 0.0 {
+			problems.push(format!("{}.stability can't be negative", path));
+		}
+		if !(self.difficulty == 0.0 || (1.0..=10.0).contains(&self.difficulty)) {
+			problems.push(format!("{}.difficulty must be 0 (Not yet scheduled by FSRS) or from 1 to 10", path));
+		}
+	}
+}
+
+impl JsonCard {
+	//* The card as the other import formats read it. Cloze texts have no answer of their own */
+	fn import_card(&self) -> ImportCard {
+		let mut card: ImportCard = ImportCard::new(&self.question, if self.cloze_index > 0 { "" } else { &self.answer });
+		card.alternates = self.alternates.clone();
+		card.distractors = self.distractors.clone();
+		card.multiple_choice = self.multiple_choice;
+		card.correct = self.correct;
+		card.incorrect = self.incorrect;
+		card.schedule = self.schedule.to_schedule();
+		card
+	}
+
+	//* Adds a problem for everything wrong with the card on its own, named from `path` */
+	fn check(&self, path: &str, problems: &mut Vec<String>) {
+		if self.id.as_deref().is_some_and(|id| id.trim().is_empty()) {
+			problems.push(format!("{}.id can't be empty", path));
+		}
+		if self.question.trim().is_empty() {
+			problems.push(format!("{}.question can't be empty", path));
+		}
+		if self.cloze_index < 0 {
+			problems.push(format!("{}.cloze_index can't be negative", path));
+		} else if self.cloze_index > 0 {
+			if !cloze::indexes(&self.question).contains(&self.cloze_index) {
+				problems.push(format!("{}.question has no {{{{c{}::...}}}} deletion for its cloze_index", path, self.cloze_index));
+			} else if !self.answer.is_empty() && self.answer != cloze::answer(&self.question, self.cloze_index) {
+				problems.push(format!("{}.answer must be empty or the text of deletion c{}", path, self.cloze_index));
+			}
+			if self.reversed {
+				problems.push(format!("{}: cloze cards can't be reversed", path));
+			}
+			if self.group.is_none() {
+				problems.push(format!("{}: cloze cards need a group", path));
+			}
+		} else if self.answer.trim().is_empty() {
+			problems.push(format!("{}.answer can't be empty", path));
+		}
+		if self.reversed && self.group.is_none() {
+			problems.push(format!("{}: reversed cards need a group with the card they reverse", path));
+		}
+		if self.correct < 0 {
+			problems.push(format!("{}.correct can't be negative", path));
+		}
+		if self.incorrect < 0 {
+			problems.push(format!("{}.incorrect can't be negative", path));
+		}
+
+		self.schedule.check(&format!("{}.schedule", path), problems);
+		if let Some(tier) = &self.tier {
+			let expected: &str = scheduler::tier_name(self.schedule.to_schedule().tier());
+			if !["Weak", "Learning", "Strong"].contains(&tier.as_str()) {
+				problems.push(format!("{}.tier must be Weak, Learning or Strong", path));
+			} else if tier != expected {
+				problems.push(format!("{}.tier is {} but an interval of {} days makes it {}", path, tier, self.schedule.interval, expected));
+			}
+		}
+
+		for (index, review) in self.reviews.iter().enumerate() {
+			let path: String = format!("{}.reviews[{}]", path, index);
+			if Grade::from_number(review.grade).is_none() {
+				problems.push(format!("{}.grade must be from 1 (Again) to 4 (Easy)", path));
+			}
+			if Algorithm::from_name(&review.scheduler).is_none() {
+				problems.push(format!("{}.scheduler must be \"sm2\" or \"fsrs\"", path));
+			}
+			for (name, value) in [
+				("reviewed_at", review.reviewed_at), ("response_ms", review.response_ms),
+				("interval_before", review.interval_before as i64), ("interval_after", review.interval_after as i64),
+			] {
+				if value < 0 {
+					problems.push(format!("{}.{} can't be negative", path, name));
+				}
+			}
+		}
+	}
+}
+
+//* Checks the cards of one group fit together: every deletion of one cloze text, or a card and its reverse */
+fn check_group(group: i64, cards: &[(usize, &JsonCard)], problems: &mut Vec<String>) {
+	let (first_index, first): (usize, &JsonCard) = cards[0];
+	if first.cloze_index > 0 {
+		for (index, card) in &cards[1..] {
+			if card.cloze_index == 0 || card.question != first.question {
+				problems.push(format!("cards[{}]: group {} mixes it with a different cloze text than cards[{}]", index, group, first_index));
+			}
+		}
+		for deletion in cloze::indexes(&first.question) {
+			match cards.iter().filter(|(_, card)| card.cloze_index == deletion).count() {
+				0 => problems.push(format!("cards[{}]: group {} has no card for deletion c{}", first_index, group, deletion)),
+				1 => {},
+				_ => problems.push(format!("cards[{}]: group {} has more than one card for deletion c{}", first_index, group, deletion)),
+			}
+		}
+		return;
+	}
+
+	let forward: Vec<&(usize, &JsonCard)> = cards.iter().filter(|(_, card)| !card.reversed).collect();
+	let reverse: Vec<&(usize, &JsonCard)> = cards.iter().filter(|(_, card)| card.reversed).collect();
+	if cards.iter().any(|(_, card)| card.cloze_index > 0) || forward.len() != 1 || reverse.len() > 1 {
+		problems.push(format!("cards[{}]: group {} must be one cloze text, or one card and at most one reverse", first_index, group));
+	} else if let [(reverse_index, reverse)] = reverse[..] {
+		let (_, forward): (usize, &JsonCard) = *forward[0];
+		if reverse.question != forward.answer || reverse.answer != forward.question {
+			problems.push(format!("cards[{}]: a reversed card must swap the question and answer of the other card in group {}", reverse_index, group));
+		}
+	}
+}
+
+impl JsonDeck {
+	/* Reads and checks a deck file, returning every problem found if it can't be imported.
+	The format and version are read first, so a newer file is refused as newer rather than invalid. */
+	pub fn parse(text: &str) -> Result<JsonDeck, String> {
+		let value: serde_json::Value = serde_json::from_str(text).map_err(|e| format!("Not a JSON file: {}", e))?;
+		if value.get("format").and_then(serde_json::Value::as_str) != Some(FORMAT) {
+			return Err(format!("Not a flashcard deck, \"format\" should be \"{}\".", FORMAT));
+		}
+		match value.get("version").and_then(serde_json::Value::as_u64) {
+			Some(VERSION) => {},
+			Some(version) if version > VERSION => {
+				return Err(format!("The deck is version {}, made by a newer version of the app. Only version {} can be read.", version, VERSION));
+			},
+			_ => return Err("The deck has no valid \"version\".".to_owned()),
+		}
+
+		let deck: JsonDeck = serde_json::from_str(text).map_err(|e| format!("Invalid deck: {}", e))?;
+		let problems: Vec<String> = deck.problems();
+		match problems.len() {
+			0 => Ok(deck),
+			1 => Err(format!("Invalid deck: {}.", problems[0])),
+			count => Err(format!("Invalid deck, {} problems: {}.", count, problems.join("; "))),
+		}
+	}
+
+	//* Everything wrong with the deck, for which nothing is imported */
+	pub fn problems(&self) -> Vec<String> {
+		let mut problems: Vec<String> = Vec::new();
+		if self.subject.name.trim().is_empty() {
+			problems.push("subject.name can't be empty".to_owned());
+		}
+		if Algorithm::from_name(&self.subject.algorithm).is_none() {
+			problems.push("subject.algorithm must be \"sm2\" or \"fsrs\"".to_owned());
+		}
+		for (index, tag) in self.subject.tags.iter().enumerate() {
+			if tag.trim().is_empty() {
+				problems.push(format!("subject.tags[{}] can't be empty", index));
+			}
+		}
+
+		let mut ids: HashMap<&str, usize> = HashMap::new();
+		let mut groups: Vec<(i64, Vec<(usize, &JsonCard)>)> = Vec::new();
+		for (index, card) in self.cards.iter().enumerate() {
+			card.check(&format!("cards[{}]", index), &mut problems);
+			if let Some(id) = &card.id {
+				if let Some(earlier) = ids.insert(id, index) {
+					problems.push(format!("cards[{}].id is the same as cards[{}].id", index, earlier));
+				}
+			}
+			if let Some(group) = card.group {
+				match groups.iter_mut().find(|(number, _)| *number == group) {
+					Some((_, members)) => members.push((index, card)),
+					None => groups.push((group, vec![(index, card)])),
+				}
+			}
+		}
+		for (group, members) in &groups {
+			check_group(*group, members, &mut problems);
+		}
+		problems
+	}
+
+	//* Indexes of the cards of each group (Cards without one are on their own), in the order they appear */
+	fn groups(&self) -> Vec<Vec<usize>> {
+		let mut groups: Vec<(Option<i64>, Vec<usize>)> = Vec::new();
+		for (index, card) in self.cards.iter().enumerate() {
+			match groups.iter_mut().find(|(group, _)| card.group.is_some() && *group == card.group) {
+				Some((_, members)) => members.push(index),
+				None => groups.push((card.group, vec![index])),
+			}
+		}
+		groups.into_iter().map(|(_, members)| members).collect()
+	}
+
+	/* Works out what importing each card would do. subject_id is None if the subject doesn't exist yet.
+	Groups are imported whole, so one card already imported (Or a duplicate) skips its whole group. */
+	pub fn preview(&self, conn: &Connection, subject_id: Option<i64>) -> rusqlite::Result<Vec<PreviewRow>> {
+		let mut rows: Vec<PreviewRow> = Vec::new();
+		for (index, card) in self.cards.iter().enumerate() {
+			let existing: Option<i32> = match &card.id {
+				Some(id) => flashcards::find_by_uid(conn, id)?,
+				None => None,
+			};
+			let status: RowStatus = if existing.is_some() { RowStatus::Duplicate } else { RowStatus::Ready };
+			rows.push(PreviewRow { line: index + 1, card: card.import_card(), status });
+		}
+
+		// Compared by the first card of each group, since deletions of one cloze text share their question
+		let groups: Vec<Vec<usize>> = self.groups();
+		let mut first_rows: Vec<PreviewRow> = groups.iter().map(|members| rows[members[0]].clone()).collect();
+		match subject_id {
+			Some(subject_id) => formats::mark_duplicates(conn, subject_id, &mut first_rows)?,
+			None => formats::mark_duplicates_within(&mut first_rows),
+		}
+		for (members, first_row) in groups.iter().zip(&first_rows) {
+			let skipped: bool = first_row.status != RowStatus::Ready || members.iter().any(|index| rows[*index].status != RowStatus::Ready);
+			if skipped {
+				for index in members {
+					rows[*index].status = RowStatus::Duplicate;
+				}
+			}
+		}
+		Ok(rows)
+	}
+}
+
+//* Gives a card made from a deck file its counters, schedule, review log and id */
+fn restore_card(conn: &Connection, primary_key: i64, card: &JsonCard) -> rusqlite::Result<usize> {
+	conn.execute(
+		"UPDATE cards SET correct = ?1, incorrect = ?2 WHERE id = ?3;",
+		rusqlite::params![card.correct, card.incorrect, primary_key],
+	)?;
+	flashcards::set_schedule(conn, primary_key as i32, &card.schedule.to_schedule())?;
+	if let Some(id) = &card.id {
+		flashcards::set_uid(conn, primary_key as i32, id)?;
+	}
+	for review in &card.reviews {
+		if let Some(grade) = Grade::from_number(review.grade) { // Always, the deck has been checked
+			reviews::log_review(conn, &Review {
+				card_id: primary_key as i32,
+				reviewed_at: review.reviewed_at,
+				grade,
+				response_ms: review.response_ms,
+				algorithm: Algorithm::from_name(&review.scheduler).unwrap_or(Algorithm::Sm2),
+				interval_before: review.interval_before,
+				interval_after: review.interval_after,
+			})?;
+		}
+	}
+	Ok(card.reviews.len())
+}
+
+/* Adds the ready cards of a deck file to its subject (Made with the file's algorithm and tags if there
+isn't one with that name). Done in one transaction, so a failure part way through adds nothing. */
+pub fn import_deck(conn: &Connection, deck: &JsonDeck, rows: &[PreviewRow]) -> rusqlite::Result<JsonReport> {
+	let transaction: rusqlite::Transaction<'_> = conn.unchecked_transaction()?;
+	let name: &str = deck.subject.name.trim();
+	let exists: bool = database::get_subjects(&transaction)?.iter().any(|subject| subject.name == name);
+	let subject_id: i64 = database::get_or_add_subject(&transaction, name)?;
+	if !exists {
+		database::set_algorithm(&transaction, subject_id, Algorithm::from_name(&deck.subject.algorithm).unwrap_or(Algorithm::Sm2))?;
+		database::set_tags(&transaction, subject_id, &deck.subject.tags)?;
+	}
+
+	let mut report: JsonReport = JsonReport { added: 0, reviews: 0, skipped: 0 };
+	for members in deck.groups() {
+		if rows[members[0]].status != RowStatus::Ready {
+			report.skipped += members.len();
+			continue;
+		}
+
+		let first: &JsonCard = &deck.cards[members[0]];
+		let mut made: Vec<(i64, &JsonCard)> = Vec::new();
+		if first.cloze_index > 0 {
+			let primary_keys: Vec<i64> = flashcards::add_cloze_flashcards(&transaction, subject_id, &first.question)?;
+			for (primary_key, deletion) in primary_keys.into_iter().zip(cloze::indexes(&first.question)) {
+				if let Some(index) = members.iter().find(|index| deck.cards[**index].cloze_index == deletion) {
+					made.push((primary_key, &deck.cards[*index]));
+				}
+			}
+		} else {
+			let (forward, reverse): (Vec<&JsonCard>, Vec<&JsonCard>) = members.iter().map(|index| &deck.cards[*index]).partition(|card| !card.reversed);
+			let forward: &JsonCard = forward[0];
+			let primary_key: i64 = flashcards::add_new_flashcard(&transaction, subject_id, &forward.question, &forward.answer, &forward.alternates)?;
+			flashcards::set_multiple_choice(&transaction, primary_key as i32, forward.multiple_choice, &forward.distractors)?;
+			made.push((primary_key, forward));
+			if let Some(reverse) = reverse.first() {
+				let reverse_key: i64 = flashcards::add_reverse_flashcard(&transaction, primary_key as i32)?;
+				flashcards::set_alternates(&transaction, reverse_key as i32, &reverse.alternates)?;
+				flashcards::set_multiple_choice(&transaction, reverse_key as i32, reverse.multiple_choice, &reverse.distractors)?;
+				made.push((reverse_key, reverse));
+			}
+		}
+
+		for (primary_key, card) in made {
+			report.reviews += restore_card(&transaction, primary_key, card)?;
+			report.added += 1;
+		}
+	}
+	transaction.commit()?;
+	Ok(report)
+}
+
+//* Writes a whole subject as a deck file, giving its cards ids */
+pub fn export(conn: &Connection, subject: &Subject) -> Result<(String, usize), String> {
+	let database_error = |e: rusqlite::Error| format!("Couldn't read the flashcards: {}", e);
+	let flashcards: Vec<Flashcard> = flashcards::load_all_flashcards(conn, subject.id).map_err(database_error)?;
+	let tags: Vec<String> = database::get_tags(conn, subject.id).map_err(database_error)?;
+	let reviews: HashMap<i32, Vec<Review>> = reviews::load_subject_reviews(conn, subject.id).map_err(database_error)?;
+
+	let mut groups: Vec<i64> = Vec::new(); // Group ids in the database, numbered by position in the file
+	let mut cards: Vec<JsonCard> = Vec::new();
+	for flashcard in &flashcards {
+		let snapshot: FlashcardSnapshot = flashcards::load_snapshot(conn, flashcard.primary_key).map_err(database_error)?;
+		// Cards from notes are written as plain cards, since note types aren't part of the file
+		let group: Option<i64> = match (flashcard.group_id, flashcard.note_id) {
+			(Some(group_id), None) => Some(match groups.iter().position(|id| *id == group_id) {
+				Some(position) => position as i64 + 1,
+				None => {
+					groups.push(group_id);
+					groups.len() as i64
+				},
+			}),
+			_ => None,
+		};
+
+		cards.push(JsonCard {
+			id: Some(flashcards::card_uid(conn, flashcard.primary_key).map_err(database_error)?),
+			question: flashcard.question.clone(),
+			answer: flashcard.answer.clone(),
+			alternates: flashcard.alternates.clone(),
+			distractors: flashcard.distractors.clone(),
+			multiple_choice: flashcard.multiple_choice,
+			cloze_index: flashcard.cloze_index,
+			reversed: flashcard.reversed && group.is_some(),
+			group,
+			tier: Some(scheduler::tier_name(flashcard.category()).to_owned()),
+			correct: snapshot.correct,
+			incorrect: snapshot.incorrect,
+			schedule: JsonSchedule::from(flashcard.schedule),
+			reviews: reviews.get(&flashcard.primary_key).into_iter().flatten().map(|review| JsonReview {
+				reviewed_at: review.reviewed_at,
+				grade: review.grade as i32,
+				response_ms: review.response_ms,
+				scheduler: review.algorithm.name().to_owned(),
+				interval_before: review.interval_before,
+				interval_after: review.interval_after,
+			}).collect(),
+		});
+	}
+
+	// A reverse card left without the card it was made from is written as a plain card
+	let orphans: Vec<Option<i64>> = cards.iter()
+		.filter(|card| card.reversed && !cards.iter().any(|other| other.group == card.group && !other.reversed))
+		.map(|card| card.group)
+		.collect();
+	for card in cards.iter_mut().filter(|card| card.reversed && orphans.contains(&card.group)) {
+		card.reversed = false;
+		card.group = None;
+	}
+
+	let count: usize = cards.len();
+	let deck: JsonDeck = JsonDeck {
+		schema: Some(SCHEMA_ID.to_owned()),
+		format: FORMAT.to_owned(),
+		version: VERSION,
+		subject: JsonSubject { name: subject.name.clone(), algorithm: subject.algorithm.name().to_owned(), tags },
+		cards,
+	};
+	let text: String = serde_json::to_string_pretty(&deck).map_err(|e| format!("Couldn't write the deck: {}", e))?;
+	Ok((text, count))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use serde_json::{json, Value};
+
+	//* Problems of a deck holding these cards (Each needs at least a question) */
+	fn problems(cards: Value) -> Vec<String> {
+		let deck: JsonDeck = serde_json::from_value(json!({ "format": FORMAT, "version": VERSION, "subject": { "name": "French" }, "cards": cards })).unwrap();
+		deck.problems()
+	}
+
+	fn assert_problem(cards: Value, expected: &str) {
+		let found: Vec<String> = problems(cards);
+		assert!(found.iter().any(|problem| problem == expected), "{:?} not in {:?}", expected, found);
+	}
+
+	fn new_subject(conn: &Connection, name: &str, algorithm: Algorithm) -> Subject {
+		let id: i64 = database::get_or_add_subject(conn, name).unwrap();
+		database::set_algorithm(conn, id, algorithm).unwrap();
+		Subject { id, name: name.to_owned(), algorithm }
+	}
+
+	#[test]
+	fn round_trips_groups_schedules_and_reviews() {
+		let conn: Connection = database::open_database(":memory:").unwrap();
+		let subject: Subject = new_subject(&conn, "French", Algorithm::Fsrs);
+		database::set_tags(&conn, subject.id, &["languages".to_owned()]).unwrap();
+
+		let plain: i64 = flashcards::add_new_flashcard(&conn, subject.id, "red", "rouge", &["rouges".to_owned()]).unwrap();
+		flashcards::set_multiple_choice(&conn, plain as i32, true, &["bleu".to_owned()]).unwrap();
+		flashcards::record_answer(&conn, plain as i32, true).unwrap();
+		let schedule: Schedule = Schedule { interval: 8, repetitions: 3, due: 1_700_700_000, stability: 8.5, difficulty: 4.2, last_review: 1_700_000_000, ..Schedule::default() };
+		flashcards::set_schedule(&conn, plain as i32, &schedule).unwrap();
+		reviews::log_review(&conn, &Review {
+			card_id: plain as i32, reviewed_at: 1_700_000_000, grade: Grade::Good, response_ms: 2100,
+			algorithm: Algorithm::Fsrs, interval_before: 3, interval_after: 8,
+		}).unwrap();
+
+		let forward: i64 = flashcards::add_new_flashcard(&conn, subject.id, "dog", "chien", &[]).unwrap();
+		flashcards::add_reverse_flashcard(&conn, forward as i32).unwrap();
+		flashcards::add_cloze_flashcards(&conn, subject.id, "{{c1::Paris}} is in {{c2::France}}").unwrap();
+		let orphaned: i64 = flashcards::add_new_flashcard(&conn, subject.id, "cat", "chat", &[]).unwrap();
+		flashcards::add_reverse_flashcard(&conn, orphaned as i32).unwrap();
+		flashcards::remove_flashcard(&conn, orphaned as i32).unwrap();
+
+		let (text, count): (String, usize) = export(&conn, &subject).unwrap();
+		assert_eq!(count, 6);
+		let deck: JsonDeck = JsonDeck::parse(&text).unwrap();
+		assert_eq!(deck.cards[1].group, deck.cards[2].group);
+		assert!(deck.cards[2].reversed);
+		assert_eq!((deck.cards[3].group, deck.cards[3].cloze_index), (deck.cards[4].group, 1));
+		assert_eq!((deck.cards[5].question.as_str(), deck.cards[5].reversed, deck.cards[5].group), ("chat", false, None)); // Orphan is a plain card
+
+		// Into a new database, where the subject is made from the file
+		let other: Connection = database::open_database(":memory:").unwrap();
+		let rows: Vec<PreviewRow> = deck.preview(&other, None).unwrap();
+		assert!(rows.iter().all(|row| row.status == RowStatus::Ready));
+		let report: JsonReport = import_deck(&other, &deck, &rows).unwrap();
+		assert_eq!((report.added, report.reviews, report.skipped), (6, 1, 0));
+
+		let imported: Subject = database::get_subjects(&other).unwrap().remove(0);
+		assert_eq!(imported.algorithm, Algorithm::Fsrs);
+		let cards: Vec<Flashcard> = flashcards::load_all_flashcards(&other, imported.id).unwrap();
+		assert_eq!(cards[0].schedule, schedule);
+		assert_eq!(cards[0].distractors, vec!["bleu".to_owned()]);
+		assert_eq!(flashcards::find_reverse(&other, cards[1].primary_key).unwrap(), Some(cards[2].primary_key));
+		assert_eq!(cards[3].group_id, cards[4].group_id);
+		assert!(!cards[5].reversed && cards[5].group_id.is_none());
+		assert_eq!(export(&other, &imported).unwrap().0, text);
+
+		// Importing the same file again skips every card, as their ids are already there
+		let rows: Vec<PreviewRow> = deck.preview(&other, Some(imported.id)).unwrap();
+		let report: JsonReport = import_deck(&other, &deck, &rows).unwrap();
+		assert_eq!((report.added, report.skipped), (0, 6));
+	}
+
+	#[test]
+	fn refuses_other_files_and_newer_versions() {
+		let deck = |format: &str, version: Value| json!({ "format": format, "version": version, "subject": { "name": "French" }, "cards": [] }).to_string();
+		assert!(JsonDeck::parse(&deck(FORMAT, json!(1))).is_ok());
+		assert_eq!(
+			JsonDeck::parse(&deck(FORMAT, json!(2))).unwrap_err(),
+			"The deck is version 2, made by a newer version of the app. Only version 1 can be read."
+		);
+		// Even if the rest of it couldn't be read by this version
+		let newer: String = json!({ "format": FORMAT, "version": 7, "deck": {} }).to_string();
+		assert!(JsonDeck::parse(&newer).unwrap_err().starts_with("The deck is version 7"));
+		assert_eq!(JsonDeck::parse(&deck(FORMAT, json!("1"))).unwrap_err(), "The deck has no valid \"version\".");
+		assert_eq!(JsonDeck::parse(&deck("anki", json!(1))).unwrap_err(), format!("Not a flashcard deck, \"format\" should be \"{}\".", FORMAT));
+		assert!(JsonDeck::parse("{").unwrap_err().starts_with("Not a JSON file"));
+
+		let unknown: String = json!({ "format": FORMAT, "version": 1, "subject": { "name": "French", "colour": "red" }, "cards": [] }).to_string();
+		assert!(JsonDeck::parse(&unknown).unwrap_err().contains("unknown field `colour`"));
+	}
+
+	#[test]
+	fn reports_every_problem() {
+		let text: String = json!({ "format": FORMAT, "version": 1, "subject": { "name": " " }, "cards": [{ "question": "q" }] }).to_string();
+		assert_eq!(JsonDeck::parse(&text).unwrap_err(), "Invalid deck, 2 problems: subject.name can't be empty; cards[0].answer can't be empty.");
+		let text: String = json!({ "format": FORMAT, "version": 1, "subject": { "name": "French", "algorithm": "anki" }, "cards": [] }).to_string();
+		assert_eq!(JsonDeck::parse(&text).unwrap_err(), "Invalid deck: subject.algorithm must be \"sm2\" or \"fsrs\".");
+		let deck: JsonDeck = serde_json::from_value(json!({ "format": FORMAT, "version": 1, "subject": { "name": "French", "tags": ["a", ""] }, "cards": [] })).unwrap();
+		assert_eq!(deck.problems(), vec!["subject.tags[1] can't be empty".to_owned()]);
+		assert!(problems(json!([{ "question": "q", "answer": "a" }])).is_empty());
+	}
+
+	#[test]
+	fn checks_each_card() {
+		let cases: [(Value, &str); 22] = [
+			(json!({ "id": " ", "question": "q", "answer": "a" }), "cards[0].id can't be empty"),
+			(json!({ "question": " ", "answer": "a" }), "cards[0].question can't be empty"),
+			(json!({ "question": "q", "answer": "a", "cloze_index": -1 }), "cards[0].cloze_index can't be negative"),
+			(json!({ "question": "No deletions", "cloze_index": 1, "group": 1 }), "cards[0].question has no {{c1::...}} deletion for its cloze_index"),
+			(json!({ "question": "{{c1::a}}", "answer": "b", "cloze_index": 1, "group": 1 }), "cards[0].answer must be empty or the text of deletion c1"),
+			(json!({ "question": "{{c1::a}}", "cloze_index": 1, "group": 1, "reversed": true }), "cards[0]: cloze cards can't be reversed"),
+			(json!({ "question": "{{c1::a}}", "cloze_index": 1 }), "cards[0]: cloze cards need a group"),
+			(json!({ "question": "q", "answer": " " }), "cards[0].answer can't be empty"),
+			(json!({ "question": "q", "answer": "a", "reversed": true }), "cards[0]: reversed cards need a group with the card they reverse"),
+			(json!({ "question": "q", "answer": "a", "correct": -1 }), "cards[0].correct can't be negative"),
+			(json!({ "question": "q", "answer": "a", "incorrect": -1 }), "cards[0].incorrect can't be negative"),
+			(json!({ "question": "q", "answer": "a", "schedule": { "ease_factor": 1.2 } }), "cards[0].schedule.ease_factor must be at least 1.3"),
+			(json!({ "question": "q", "answer": "a", "schedule": { "interval": -1 } }), "cards[0].schedule.interval can't be negative"),
+			(json!({ "question": "q", "answer": "a", "schedule": { "last_review": -1 } }), "cards[0].schedule.last_review can't be negative"),
+			(json!({ "question": "q", "answer": "a", "schedule": { "stability": -0.5 } }), "cards[0].schedule.stability can't be negative"),
+			(json!({ "question": "q", "answer": "a", "schedule": { "difficulty": 0.5 } }), "cards[0].schedule.difficulty must be 0 (Not yet scheduled by FSRS) or from 1 to 10"),
+			(json!({ "question": "q", "answer": "a", "tier": "Mastered" }), "cards[0].tier must be Weak, Learning or Strong"),
+			(json!({ "question": "q", "answer": "a", "tier": "Strong" }), "cards[0].tier is Strong but an interval of 0 days makes it Weak"),
+			(json!({ "question": "q", "answer": "a", "reviews": [{ "reviewed_at": 1, "grade": 5 }] }), "cards[0].reviews[0].grade must be from 1 (Again) to 4 (Easy)"),
+			(json!({ "question": "q", "answer": "a", "reviews": [{ "reviewed_at": 1, "grade": 3, "scheduler": "leitner" }] }), "cards[0].reviews[0].scheduler must be \"sm2\" or \"fsrs\""),
+			(json!({ "question": "q", "answer": "a", "reviews": [{ "reviewed_at": -1, "grade": 3 }] }), "cards[0].reviews[0].reviewed_at can't be negative"),
+			(json!({ "question": "q", "answer": "a", "reviews": [{ "reviewed_at": 1, "grade": 3, "response_ms": -1 }] }), "cards[0].reviews[0].response_ms can't be negative"),
+		];
+		for (card, expected) in cases {
+			assert_problem(json!([card]), expected);
+		}
+		assert_problem(
+			json!([{ "id": "a", "question": "q", "answer": "a" }, { "id": "a", "question": "r", "answer": "b" }]),
+			"cards[1].id is the same as cards[0].id",
+		);
+	}
+
+	#[test]
+	fn checks_each_group() {
+		let text: &str = "{{c1::a}} {{c2::b}}";
+		assert_problem(
+			json!([{ "question": text, "cloze_index": 1, "group": 1 }, { "question": "{{c1::x}}", "cloze_index": 1, "group": 1 }]),
+			"cards[1]: group 1 mixes it with a different cloze text than cards[0]",
+		);
+		assert_problem(json!([{ "question": text, "cloze_index": 1, "group": 1 }]), "cards[0]: group 1 has no card for deletion c2");
+		assert_problem(
+			json!([
+				{ "question": text, "cloze_index": 1, "group": 1 }, { "question": text, "cloze_index": 1, "group": 1 },
+				{ "question": text, "cloze_index": 2, "group": 1 },
+			]),
+			"cards[0]: group 1 has more than one card for deletion c1",
+		);
+		assert_problem(
+			json!([{ "question": "a", "answer": "b", "group": 2 }, { "question": "c", "answer": "d", "group": 2 }]),
+			"cards[0]: group 2 must be one cloze text, or one card and at most one reverse",
+		);
+		assert_problem(
+			json!([{ "question": "a", "answer": "b", "group": 2 }, { "question": "a", "answer": "b", "group": 2, "reversed": true }]),
+			"cards[1]: a reversed card must swap the question and answer of the other card in group 2",
+		);
+		assert!(problems(json!([
+			{ "question": text, "cloze_index": 2, "group": 1 }, { "question": text, "cloze_index": 1, "group": 1 },
+			{ "question": "a", "answer": "b", "group": 2 }, { "question": "b", "answer": "a", "group": 2, "reversed": true },
+		])).is_empty());
+	}
+}
@@ -0,0 +1,450 @@
+use rusqlite::{ // Handles SQLite database
+	params,
+	Connection,
+};
+
+use crate::cloze;
+use crate::database::{self, Subject};
+use crate::flashcards::{self, Flashcard, FlashcardSnapshot};
+use crate::formats::{self, ImportCard, PreviewRow, RowStatus};
+
+/* **Explanation of Markdown decks**
+	---
+	tags: languages, french
+	---
+	# French
+
+	<!-- id: 3f9a0c21b7e4 -->
+	Q: dog
+	A: chien
+	A: le chien
+
+	Q: {{c1::Paris}} is the capital of France
+	---
+	Cat
+
+	Chat
+
+	- The first "# " heading is the subject. Files without one go into the subject being edited
+	- Front matter (Between "---" lines at the very top) holds the subject's tags, written as
+		"tags: a, b", "tags: [a, b]" or "tags:" followed by one "- tag" per line. It is only front
+		matter if it looks like YAML ("key: value" lines), otherwise the "---" separates cards
+	- "Q:" starts a card. The first "A:" is its answer and any more are alternate answers. Lines
+		after a "Q:" or "A:" carry it on. A question with no answer is a cloze text
+	- Cards can also be separated by "---" lines, with the first paragraph as the question and
+		the rest as the answer
+	- "<!-- id: ... -->" above a card is its id. Importing writes an id above every card added, so
+		importing the file again updates those cards instead of adding them again. Cards taken out
+		of the file are kept, since the file may only be part of a subject
+*/
+const ID_START: &str = "<!-- id:";
+const ID_END: &str = "-->";
+
+// A card read from a deck file
+#[derive(Clone, Debug)]
+pub struct MarkdownCard {
+	pub id: Option<String>,
+	pub line: usize, // Line the card starts on (From 0), where its id is written if it doesn't have one
+	pub card: ImportCard,
+}
+
+// Everything read from a deck file
+#[derive(Clone, Debug)]
+pub struct MarkdownDeck {
+	pub subject: Option<String>,
+	pub tags: Option<Vec<String>>, // None = no tags in the front matter, so the subject's tags are left alone
+	pub cards: Vec<MarkdownCard>,
+}
+
+// What an import did
+#[derive(Default)]
+pub struct MarkdownReport {
+	pub added: usize,
+	pub updated: usize,
+	pub unchanged: usize,
+	pub skipped: usize, // Duplicates and invalid cards
+	pub ids: Vec<(usize, String)>, // Line and id of each card added, to be written into the file
+}
+
+// Lines of one card while the file is read
+#[derive(Default)]
+struct Block {
+	id: Option<String>,
+	first_line: Option<usize>,
+	lines: Vec<String>,
+}
+
+impl Block {
+	fn has_question(&self) -> bool {
+		self.lines.iter().any(|line| line.trim_start().starts_with("Q:"))
+	}
+
+	//* Turns the lines into a card, or None if there are none (e.g. blank lines between "---") */
+	fn into_card(self) -> Option<MarkdownCard> {
+		let lines: Vec<&str> = self.lines.iter().map(|line| line.as_str()).collect();
+		if lines.iter().all(|line| line.trim().is_empty()) {
+			return None;
+		}
+
+		let (question, answers): (String, Vec<String>) = if lines.iter().any(|line| line.trim_start().starts_with("Q:") || line.trim_start().starts_with("A:")) {
+			let mut question: Vec<&str> = Vec::new();
+			let mut answers: Vec<Vec<&str>> = Vec::new();
+			for line in lines {
+				if let Some(rest) = line.trim_start().strip_prefix("Q:") {
+					question = vec![rest.trim()];
+					answers.clear();
+				} else if let Some(rest) = line.trim_start().strip_prefix("A:") {
+					answers.push(vec![rest.trim()]);
+				} else {
+					match answers.last_mut() {
+						Some(answer) => answer.push(line),
+						None => question.push(line),
+					}
+				}
+			}
+			(question.join("\n").trim().to_owned(), answers.iter().map(|answer| answer.join("\n").trim().to_owned()).collect())
+		} else {
+			// The question is the first paragraph, the answer is the rest
+			let split: usize = lines.iter().position(|line| line.trim().is_empty()).unwrap_or(lines.len());
+			(lines[..split].join("\n").trim().to_owned(), vec![lines[split..].join("\n").trim().to_owned()])
+		};
+
+		let mut card: ImportCard = ImportCard::new(&question, answers.first().map(|answer| answer.as_str()).unwrap_or(""));
+		card.alternates = answers.iter().skip(1).filter(|answer| !answer.is_empty()).cloned().collect();
+		Some(MarkdownCard { id: self.id, line: self.first_line.unwrap_or(0), card })
+	}
+}
+
+//* Reads the id out of a "<!-- id: ... -->" line */
+fn parse_id(line: &str) -> Option<String> {
+	let id: &str = line.strip_prefix(ID_START)?.strip_suffix(ID_END)?.trim();
+	(!id.is_empty()).then(|| id.to_owned())
+}
+
+//* Reads "a, b", "[a, b]" or "['a', 'b']" as a list of tags */
+fn parse_tags(value: &str) -> Vec<String> {
+	value.trim().trim_start_matches('[').trim_end_matches(']')
+		.split(',')
+		.map(|tag| tag.trim().trim_matches(['"', '\'']).to_owned())
+		.filter(|tag| !tag.is_empty())
+		.collect()
+}
+
+//* True for a "key: value" line of YAML front matter (Lowercase keys, so "Q:" and "A:" aren't) */
+fn is_key_line(line: &str) -> bool {
+	match line.split_once(':') {
+		Some((key, _)) => key.starts_with(|character: char| character.is_ascii_lowercase())
+			&& key.chars().all(|character| character.is_ascii_lowercase() || character.is_ascii_digit() || character == '_' || character == '-'),
+		None => false,
+	}
+}
+
+//* True if the lines after a first "---" are front matter rather than a card */
+fn is_front_matter(lines: &[&str]) -> bool {
+	lines.iter().any(|line| is_key_line(line))
+		&& lines.iter().all(|line| line.trim().is_empty() || is_key_line(line) || line.trim_start().starts_with("- ") || line.starts_with([' ', '\t']))
+}
+
+impl MarkdownDeck {
+	pub fn parse(text: &str) -> Result<MarkdownDeck, String> {
+		let lines: Vec<&str> = text.lines().collect();
+		let mut deck: MarkdownDeck = MarkdownDeck { subject: None, tags: None, cards: Vec::new() };
+
+		// Front matter
+		let mut body_start: usize = 0;
+		let closing: Option<usize> = lines.iter().skip(1).position(|line| line.trim() == "---").map(|position| position + 1);
+		if lines.first().map(|line| line.trim()) == Some("---") && is_front_matter(&lines[1..closing.unwrap_or(lines.len())]) {
+			let end: usize = closing.ok_or("The front matter starting on line 1 has no closing \"---\".")?;
+			let mut in_tag_list: bool = false;
+			for line in &lines[1..end] {
+				if let Some(value) = line.trim().strip_prefix("tags:") {
+					deck.tags = Some(parse_tags(value));
+					in_tag_list = value.trim().is_empty();
+				} else if let (true, Some(tag)) = (in_tag_list, line.trim().strip_prefix("- ")) {
+					deck.tags.get_or_insert_with(Vec::new).extend(parse_tags(tag));
+				} else {
+					in_tag_list = false; // Other keys are left for other tools
+				}
+			}
+			body_start = end + 1;
+		}
+
+		let mut blocks: Vec<Block> = Vec::new();
+		let mut current: Block = Block::default();
+		for (number, line) in lines.iter().enumerate().skip(body_start) {
+			let trimmed: &str = line.trim();
+			if trimmed == "---" {
+				blocks.push(std::mem::take(&mut current));
+			} else if trimmed.starts_with('#') && trimmed.trim_start_matches('#').starts_with(' ') {
+				if let (None, Some(name)) = (&deck.subject, trimmed.strip_prefix("# ")) {
+					deck.subject = Some(name.trim().to_owned());
+				}
+				blocks.push(std::mem::take(&mut current)); // Other headings only split cards up
+			} else if let Some(id) = parse_id(trimmed) {
+				if !current.lines.is_empty() || current.id.is_some() {
+					blocks.push(std::mem::take(&mut current));
+				}
+				current.id = Some(id);
+				current.first_line = Some(number);
+			} else if trimmed.starts_with("Q:") && current.has_question() {
+				blocks.push(std::mem::take(&mut current));
+				current.first_line = Some(number);
+				current.lines.push(line.trim_end().to_owned());
+			} else if !(trimmed.is_empty() && current.lines.is_empty()) {
+				current.first_line.get_or_insert(number);
+				current.lines.push(line.trim_end().to_owned());
+			}
+		}
+		blocks.push(current);
+
+		deck.cards = blocks.into_iter().filter_map(Block::into_card).collect();
+		Ok(deck)
+	}
+
+	//* Works out what importing each card would do. subject_id is None if the subject doesn't exist yet */
+	pub fn preview(&self, conn: &Connection, subject_id: Option<i64>) -> rusqlite::Result<Vec<PreviewRow>> {
+		let mut rows: Vec<PreviewRow> = Vec::new();
+		for (index, markdown_card) in self.cards.iter().enumerate() {
+			let card: &ImportCard = &markdown_card.card;
+			let repeated: Option<&MarkdownCard> = self.cards[..index].iter()
+				.find(|earlier| earlier.id.is_some() && earlier.id == markdown_card.id);
+			let existing: Option<i32> = match &markdown_card.id {
+				Some(id) => flashcards::find_by_uid(conn, id)?,
+				None => None,
+			};
+
+			let status: RowStatus = if let Some(problem) = card.problem() {
+				RowStatus::Invalid(problem)
+			} else if let Some(earlier) = repeated {
+				RowStatus::Invalid(format!("Same id as the card on line {}", earlier.line + 1))
+			} else if let Some(primary_key) = existing {
+				let snapshot: FlashcardSnapshot = flashcards::load_snapshot(conn, primary_key)?;
+				let unchanged: bool = if snapshot.cloze_index > 0 {
+					snapshot.question == card.question
+				} else {
+					snapshot.question == card.question && snapshot.answer == card.answer && snapshot.alternates == card.alternates
+				};
+				if unchanged {
+					RowStatus::Unchanged
+				} else if (snapshot.cloze_index > 0) != card.is_cloze() {
+					RowStatus::Invalid("Can't change between a cloze and a question/answer card, take the id out to add it as a new card".to_owned())
+				} else if snapshot.note_id.is_some() {
+					RowStatus::Invalid("Made from a note, edit the note instead".to_owned())
+				} else {
+					RowStatus::Update(primary_key)
+				}
+			} else {
+				RowStatus::Ready // New, or an id from another database
+			};
+			rows.push(PreviewRow { line: markdown_card.line + 1, card: card.clone(), status });
+		}
+
+		match subject_id {
+			Some(subject_id) => formats::mark_duplicates(conn, subject_id, &mut rows)?,
+			None => formats::mark_duplicates_within(&mut rows),
+		}
+		Ok(rows)
+	}
+}
+
+//* Edits a card from a deck file, keeping the id on its group if editing a cloze text removed the card itself */
+fn update_card(conn: &Connection, primary_key: i32, id: &str, card: &ImportCard) -> rusqlite::Result<()> {
+	let (cloze_index, group_id): (i32, Option<i64>) = conn.query_row(
+		"SELECT cloze_index, group_id FROM cards WHERE id = ?1;",
+		params![primary_key],
+		|row: &rusqlite::Row<'_>| Ok((row.get(0)?, row.get(1)?)),
+	)?;
+	if cloze_index == 0 {
+		return flashcards::edit_flashcard(conn, primary_key, &card.question, &card.answer, &card.alternates);
+	}
+
+	flashcards::edit_flashcard(conn, primary_key, &card.question, &cloze::answer(&card.question, cloze_index), &[])?;
+	if flashcards::find_by_uid(conn, id)?.is_none() {
+		let first: Option<i32> = conn.query_row(
+			"SELECT MIN(id) FROM cards WHERE group_id = ?1;",
+			params![group_id.unwrap_or(primary_key as i64)],
+			|row: &rusqlite::Row<'_>| row.get(0),
+		)?;
+		if let Some(first) = first {
+			flashcards::set_uid(conn, first, id)?;
+		}
+	}
+	Ok(())
+}
+
+/* Adds the new cards of a deck file to a subject (Made if there isn't one with that name) and updates
+the changed ones. Done in one transaction, so a failure part way through changes nothing. */
+pub fn import_deck(conn: &Connection, deck: &MarkdownDeck, rows: &[PreviewRow], subject_name: &str) -> rusqlite::Result<MarkdownReport> {
+	let transaction: rusqlite::Transaction<'_> = conn.unchecked_transaction()?;
+	let subject_id: i64 = database::get_or_add_subject(&transaction, subject_name)?;
+	if let Some(tags) = &deck.tags {
+		database::set_tags(&transaction, subject_id, tags)?;
+	}
+
+	let mut report: MarkdownReport = MarkdownReport::default();
+	for (markdown_card, row) in deck.cards.iter().zip(rows) {
+		let card: &ImportCard = &markdown_card.card;
+		match &row.status {
+			RowStatus::Ready => {
+				let primary_key: i64 = if card.is_cloze() {
+					flashcards::add_cloze_flashcards(&transaction, subject_id, &card.question)?[0]
+				} else {
+					flashcards::add_new_flashcard(&transaction, subject_id, &card.question, &card.answer, &card.alternates)?
+				};
+				match &markdown_card.id {
+					Some(id) => flashcards::set_uid(&transaction, primary_key as i32, id)?, // Keeps ids shared between databases
+					None => report.ids.push((markdown_card.line, flashcards::card_uid(&transaction, primary_key as i32)?)),
+				}
+				report.added += 1;
+			},
+			RowStatus::Update(primary_key) => {
+				update_card(&transaction, *primary_key, markdown_card.id.as_deref().unwrap_or(""), card)?;
+				report.updated += 1;
+			},
+			RowStatus::Unchanged => report.unchanged += 1,
+			RowStatus::Duplicate | RowStatus::Invalid(_) => report.skipped += 1,
+		}
+	}
+	transaction.commit()?;
+	Ok(report)
+}
+
+//* Writes "<!-- id: ... -->" above the cards added from a file, keeping the rest of it as it was */
+pub fn write_ids(text: &str, ids: &[(usize, String)]) -> String {
+	let mut written: String = String::new();
+	for (number, line) in text.split_inclusive('\n').enumerate() {
+		if let Some((_, id)) = ids.iter().find(|(id_line, _)| *id_line == number) {
+			let ending: &str = if line.ends_with("\r\n") { "\r\n" } else { "\n" };
+			written.push_str(&format!("{} {} {}{}", ID_START, id, ID_END, ending));
+		}
+		written.push_str(line);
+	}
+	written
+}
+
+//* Writes a subject as a deck file, giving its cards ids. Reverse siblings are left out, since they are made from their card */
+pub fn export(conn: &Connection, subject: &Subject) -> Result<(String, usize), String> {
+	let database_error = |e: rusqlite::Error| format!("Couldn't read the flashcards: {}", e);
+	let flashcards: Vec<Flashcard> = flashcards::load_all_flashcards(conn, subject.id).map_err(database_error)?;
+	let tags: Vec<String> = database::get_tags(conn, subject.id).map_err(database_error)?;
+
+	let mut text: String = String::new();
+	if !tags.is_empty() {
+		text.push_str(&format!("---\ntags: {}\n---\n", tags.join(", ")));
+	}
+	text.push_str(&format!("# {}\n", subject.name));
+
+	let mut groups: Vec<i64> = Vec::new(); // Cloze texts already written
+	let mut count: usize = 0;
+	for flashcard in flashcards.iter().filter(|flashcard| !flashcard.reversed) {
+		if flashcard.cloze_index > 0 {
+			let group_id: i64 = flashcard.group_id.unwrap_or(flashcard.primary_key as i64);
+			if groups.contains(&group_id) {
+				continue;
+			}
+			groups.push(group_id);
+		}
+
+		let id: String = flashcards::card_uid(conn, flashcard.primary_key).map_err(database_error)?;
+		text.push_str(&format!("\n{} {} {}\nQ: {}\n", ID_START, id, ID_END, flashcard.question));
+		if flashcard.cloze_index == 0 {
+			for answer in std::iter::once(&flashcard.answer).chain(&flashcard.alternates) {
+				text.push_str(&format!("A: {}\n", answer));
+			}
+		}
+		count += 1;
+	}
+	Ok((text, count))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::scheduler::Algorithm;
+
+	fn questions(deck: &MarkdownDeck) -> Vec<(&str, &str)> {
+		deck.cards.iter().map(|card| (card.card.question.as_str(), card.card.answer.as_str())).collect()
+	}
+
+	#[test]
+	fn parses_question_and_answer_cards() {
+		let deck: MarkdownDeck = MarkdownDeck::parse("# French\n\n<!-- id: abc123 -->\nQ: dog\nA: chien\nA: le chien\n\nQ: Two\nlines\nA: deux\n  lignes\nQ: {{c1::Paris}} is in France\n").unwrap();
+		assert_eq!(deck.subject.as_deref(), Some("French"));
+		assert_eq!(deck.tags, None);
+		assert_eq!(questions(&deck), vec![("dog", "chien"), ("Two\nlines", "deux\n  lignes"), ("{{c1::Paris}} is in France", "")]);
+		assert_eq!(deck.cards[0].id.as_deref(), Some("abc123"));
+		assert_eq!(deck.cards[0].line, 2);
+		assert_eq!(deck.cards[0].card.alternates, vec!["le chien".to_owned()]);
+		assert_eq!(deck.cards[1].id, None);
+		assert_eq!(deck.cards[2].line, 11);
+	}
+
+	#[test]
+	fn parses_separated_cards() {
+		let deck: MarkdownDeck = MarkdownDeck::parse("Cat\n\nChat\n---\nDog\n\nChien\n---\n\n---\n## Sub heading\nBird\n\nOiseau").unwrap();
+		assert_eq!(deck.subject, None);
+		assert_eq!(questions(&deck), vec![("Cat", "Chat"), ("Dog", "Chien"), ("Bird", "Oiseau")]);
+	}
+
+	#[test]
+	fn reads_tags_from_front_matter() {
+		let inline: MarkdownDeck = MarkdownDeck::parse("---\ntags: [languages, 'french']\nauthor: me\n---\n# French\nQ: a\nA: b").unwrap();
+		assert_eq!(inline.tags, Some(vec!["languages".to_owned(), "french".to_owned()]));
+		assert_eq!(inline.subject.as_deref(), Some("French"));
+		assert_eq!(inline.cards.len(), 1);
+
+		let list: MarkdownDeck = MarkdownDeck::parse("---\ntags:\n  - languages\n  - french\ntitle: x\n---\nQ: a\nA: b").unwrap();
+		assert_eq!(list.tags, Some(vec!["languages".to_owned(), "french".to_owned()]));
+
+		let unclosed: String = MarkdownDeck::parse("---\ntags: a, b\n").unwrap_err();
+		assert_eq!(unclosed, "The front matter starting on line 1 has no closing \"---\".");
+	}
+
+	#[test]
+	fn leading_separators_are_not_front_matter() {
+		// Starting with a separator keeps the first card
+		let deck: MarkdownDeck = MarkdownDeck::parse("---\nCat\n\nChat\n---\nDog\n\nChien\n").unwrap();
+		assert_eq!(deck.tags, None);
+		assert_eq!(questions(&deck), vec![("Cat", "Chat"), ("Dog", "Chien")]);
+
+		// With no second separator either
+		let deck: MarkdownDeck = MarkdownDeck::parse("---\nQ: dog\nA: chien\n").unwrap();
+		assert_eq!(questions(&deck), vec![("dog", "chien")]);
+	}
+
+	#[test]
+	fn writes_ids_above_added_cards() {
+		let text: &str = "# French\r\nQ: dog\r\nA: chien\r\n\r\nQ: cat\r\nA: chat";
+		let written: String = write_ids(text, &[(1, "aaa".to_owned()), (4, "bbb".to_owned())]);
+		assert_eq!(written, "# French\r\n<!-- id: aaa -->\r\nQ: dog\r\nA: chien\r\n\r\n<!-- id: bbb -->\r\nQ: cat\r\nA: chat");
+		assert_eq!(write_ids(text, &[]), text);
+
+		let deck: MarkdownDeck = MarkdownDeck::parse(&written).unwrap();
+		assert_eq!(deck.cards.iter().map(|card| card.id.clone()).collect::<Vec<Option<String>>>(), vec![Some("aaa".to_owned()), Some("bbb".to_owned())]);
+	}
+
+	#[test]
+	fn exports_decks_that_read_back_the_same() {
+		let conn: Connection = database::open_database(":memory:").unwrap();
+		let subject_id: i64 = database::get_or_add_subject(&conn, "French").unwrap();
+		database::set_tags(&conn, subject_id, &["languages".to_owned()]).unwrap();
+		let primary_key: i64 = flashcards::add_new_flashcard(&conn, subject_id, "dog", "chien", &["le chien".to_owned()]).unwrap();
+		flashcards::add_reverse_flashcard(&conn, primary_key as i32).unwrap();
+		flashcards::add_cloze_flashcards(&conn, subject_id, "{{c1::Paris}} is in {{c2::France}}").unwrap();
+		let subject: Subject = Subject { id: subject_id, name: "French".to_owned(), algorithm: Algorithm::Sm2 };
+
+		let (text, count): (String, usize) = export(&conn, &subject).unwrap();
+		assert_eq!(count, 2); // The reverse and the second cloze card are made from the others
+		let deck: MarkdownDeck = MarkdownDeck::parse(&text).unwrap();
+		assert_eq!(deck.subject.as_deref(), Some("French"));
+		assert_eq!(deck.tags, Some(vec!["languages".to_owned()]));
+		assert_eq!(questions(&deck), vec![("dog", "chien"), ("{{c1::Paris}} is in {{c2::France}}", "")]);
+		assert_eq!(deck.cards[0].card.alternates, vec!["le chien".to_owned()]);
+		assert!(deck.cards.iter().all(|card| card.id.is_some()));
+
+		// Importing it again changes nothing, and exporting again gives the same file
+		let rows: Vec<PreviewRow> = deck.preview(&conn, Some(subject_id)).unwrap();
+		assert!(rows.iter().all(|row| row.status == RowStatus::Unchanged));
+		assert_eq!(export(&conn, &subject).unwrap().0, text);
+	}
+}
@@ -0,0 +1,168 @@
+use std::collections::HashSet;
+
+use rusqlite::Connection; // Handles SQLite database
+
+use crate::cloze;
+use crate::flashcards::{self, Flashcard};
+use crate::matching::{self, MatchOptions};
+use crate::scheduler::Schedule;
+
+pub mod anki;
+pub mod delimited;
+pub mod json;
+pub mod markdown;
+pub mod plain;
+
+pub const MEDIA_DIR: &str = "media"; // Images and sounds of imported cards, next to flashcards.db
+
+// A card read from an import file, before it is added to a subject
+#[derive(Clone, Debug)]
+pub struct ImportCard {
+	pub question: String, // Cloze text if the answer is empty and the question has {{c1::...}} deletions
+	pub answer: String,
+	pub alternates: Vec<String>,
+	pub distractors: Vec<String>,
+	pub multiple_choice: bool,
+	pub correct: i32,
+	pub incorrect: i32,
+	pub schedule: Schedule, // Default = a new card, due straight away
+}
+
+impl ImportCard {
+	pub fn new(question: &str, answer: &str) -> ImportCard {
+		ImportCard {
+			question: question.to_owned(),
+			answer: answer.to_owned(),
+			alternates: Vec::new(),
+			distractors: Vec::new(),
+			multiple_choice: false,
+			correct: 0,
+			incorrect: 0,
+			schedule: Schedule::default(),
+		}
+	}
+
+	//* Cloze texts have no answer of their own, one card is made per deletion */
+	pub fn is_cloze(&self) -> bool {
+		self.answer.is_empty() && !cloze::indexes(&self.question).is_empty()
+	}
+
+	//* Checks the card has everything it needs, returning the problem if not */
+	pub fn problem(&self) -> Option<String> {
+		if self.question.is_empty() {
+			Some("No question".to_owned())
+		} else if self.answer.is_empty() && !self.is_cloze() {
+			Some("No answer".to_owned())
+		} else {
+			None
+		}
+	}
+}
+
+// Whether a row of an import file will be added
+#[derive(Clone, Debug, PartialEq)]
+pub enum RowStatus {
+	Ready,
+	Duplicate, // Same question and answer as a card in the subject or an earlier row
+	Invalid(String), // Why it can't be added
+	Update(i32), // Changes the card with this primary key (Deck files with card ids)
+	Unchanged, // The card with its id is already the same
+}
+
+// A row of an import file and what will happen to it
+#[derive(Clone, Debug)]
+pub struct PreviewRow {
+	pub line: usize, // Row/entry number in the file, starting at 1, for reporting problems
+	pub card: ImportCard,
+	pub status: RowStatus,
+}
+
+impl PreviewRow {
+	//* Label used in the preview list */
+	pub fn label(&self) -> String {
+		let status: String = match &self.status {
+			RowStatus::Ready => "Ready".to_owned(),
+			RowStatus::Duplicate => "Duplicate".to_owned(),
+			RowStatus::Invalid(problem) => problem.clone(),
+			RowStatus::Update(_) => "Update".to_owned(),
+			RowStatus::Unchanged => "Unchanged".to_owned(),
+		};
+		format!("{}. [{}] {}", self.line, status, self.card.question)
+	}
+}
+
+// How many rows of a preview are in each state
+pub struct PreviewCounts {
+	pub ready: usize,
+	pub duplicates: usize,
+	pub invalid: usize,
+	pub updates: usize,
+	pub unchanged: usize,
+}
+
+//* Counts the rows that will be added, skipped as duplicates, skipped as invalid, updated and left as they are */
+pub fn count_rows(rows: &[PreviewRow]) -> PreviewCounts {
+	PreviewCounts {
+		ready: rows.iter().filter(|row| row.status == RowStatus::Ready).count(),
+		duplicates: rows.iter().filter(|row| row.status == RowStatus::Duplicate).count(),
+		invalid: rows.iter().filter(|row| matches!(row.status, RowStatus::Invalid(_))).count(),
+		updates: rows.iter().filter(|row| matches!(row.status, RowStatus::Update(_))).count(),
+		unchanged: rows.iter().filter(|row| row.status == RowStatus::Unchanged).count(),
+	}
+}
+
+//* Question and answer as compared for duplicates, so differences in case, accents and spacing don't count */
+fn duplicate_key(question: &str, answer: &str) -> (String, String) {
+	let options: MatchOptions = MatchOptions::default();
+	(matching::normalise(question, &options), matching::normalise(answer, &options))
+}
+
+//* Marks ready rows whose question and answer are already in the subject, or in an earlier row, as duplicates */
+pub fn mark_duplicates(conn: &Connection, subject_id: i64, rows: &mut [PreviewRow]) -> rusqlite::Result<()> {
+	let existing: Vec<Flashcard> = flashcards::load_all_flashcards(conn, subject_id)?;
+	let seen: HashSet<(String, String)> = existing.iter()
+		.map(|flashcard| duplicate_key(&flashcard.question, if flashcard.cloze_index > 0 { "" } else { &flashcard.answer }))
+		.collect();
+	mark_duplicates_of(seen, rows);
+	Ok(())
+}
+
+//* Marks ready rows that repeat an earlier row as duplicates, for rows going into a subject that doesn't exist yet */
+pub fn mark_duplicates_within(rows: &mut [PreviewRow]) {
+	mark_duplicates_of(HashSet::new(), rows);
+}
+
+fn mark_duplicates_of(mut seen: HashSet<(String, String)>, rows: &mut [PreviewRow]) {
+	for row in rows.iter_mut().filter(|row| row.status == RowStatus::Ready) {
+		if !seen.insert(duplicate_key(&row.card.question, &row.card.answer)) {
+			row.status = RowStatus::Duplicate;
+		}
+	}
+}
+
+/* Adds every ready row to a subject, returning how many flashcards were made.
+Done in one transaction, so a failure part way through adds nothing. */
+pub fn import_rows(conn: &Connection, subject_id: i64, rows: &[PreviewRow]) -> rusqlite::Result<usize> {
+	let transaction: rusqlite::Transaction<'_> = conn.unchecked_transaction()?;
+	let mut added: usize = 0;
+	for row in rows.iter().filter(|row| row.status == RowStatus::Ready) {
+		let card: &ImportCard = &row.card;
+		let primary_keys: Vec<i64> = if card.is_cloze() {
+			flashcards::add_cloze_flashcards(&transaction, subject_id, &card.question)?
+		} else {
+			let primary_key: i64 = flashcards::add_new_flashcard(&transaction, subject_id, &card.question, &card.answer, &card.alternates)?;
+			flashcards::set_multiple_choice(&transaction, primary_key as i32, card.multiple_choice, &card.distractors)?;
+			vec![primary_key]
+		};
+		for primary_key in &primary_keys {
+			transaction.execute(
+				"UPDATE cards SET correct = ?1, incorrect = ?2 WHERE id = ?3;",
+				rusqlite::params![card.correct, card.incorrect, primary_key],
+			)?;
+			flashcards::set_schedule(&transaction, *primary_key as i32, &card.schedule)?;
+		}
+		added += primary_keys.len();
+	}
+	transaction.commit()?;
+	Ok(added)
+}
@@ -0,0 +1,140 @@
+use crate::formats::{ImportCard, PreviewRow, RowStatus};
+
+/* **Explanation of pasted text**
+Pasted cards are a term and a definition with a separator between them, and a separator between cards:
+	- Plain notes are usually "term - definition" (Or ":", "=") with one card per line
+	- Quizlet exports use a tab or comma between term and definition and a new line or semicolon
+		between cards, or custom separators chosen when exporting
+	- Cards with definitions over several lines are separated by blank lines
+Separators are tried in the order below, and the pair that splits the most cards into a term and a
+definition is picked. Ties go to the pair leaving fewer cards unsplit, then to the earlier separators.
+*/
+pub const TERM_SEPARATORS: [&str; 8] = ["\t", " - ", " – ", " — ", " -> ", " = ", ": ", ","];
+pub const CARD_SEPARATORS: [&str; 3] = ["\n", "\n\n", ";"];
+
+//* Name of a separator for buttons and messages, since tabs and new lines can't be seen */
+pub fn separator_name(separator: &str) -> String {
+	match separator {
+		"\t" => "Tab".to_owned(),
+		"\n" => "New line".to_owned(),
+		"\n\n" => "Blank line".to_owned(),
+		_ => format!("\"{}\"", separator),
+	}
+}
+
+//* Reads a separator typed into a text box, where \t is a tab and \n a new line */
+pub fn parse_separator(typed: &str) -> String {
+	typed.replace("\\t", "\t").replace("\\n", "\n")
+}
+
+/* Splits text into cards with the line each starts on, dropping blank ones.
+Windows line endings are treated as new lines */
+fn split_cards(text: &str, card_separator: &str) -> Vec<(usize, String)> {
+	let text: String = text.replace('\r', "");
+	let mut cards: Vec<(usize, String)> = Vec::new();
+	let mut line: usize = 1;
+	for card in text.split(card_separator) {
+		let leading: &str = &card[..card.len() - card.trim_start().len()];
+		if !card.trim().is_empty() {
+			cards.push((line + leading.matches('\n').count(), card.trim().to_owned()));
+		}
+		line += card.matches('\n').count() + card_separator.matches('\n').count();
+	}
+	cards
+}
+
+//* Works out the term and card separators of pasted text, returning (Term, card) */
+pub fn detect_separators(text: &str) -> (String, String) {
+	let mut best: (&str, &str) = (TERM_SEPARATORS[0], CARD_SEPARATORS[0]);
+	let mut best_score: (usize, f64) = (0, -1.0); // Cards split, then the fraction of cards split
+	for card_separator in CARD_SEPARATORS {
+		let cards: Vec<String> = split_cards(text, card_separator).into_iter().map(|(_, card)| card).collect();
+		if cards.is_empty() {
+			continue;
+		}
+		for term_separator in TERM_SEPARATORS {
+			let split: usize = cards.iter().filter(|card| card.contains(term_separator)).count();
+			let score: (usize, f64) = (split, split as f64 / cards.len() as f64);
+			if score.0 > best_score.0 || (score.0 == best_score.0 && score.1 > best_score.1) {
+				best = (term_separator, card_separator);
+				best_score = score;
+			}
+		}
+	}
+	(best.0.to_owned(), best.1.to_owned())
+}
+
+//* Reads every card of pasted text, splitting each at its first term separator */
+pub fn preview(text: &str, term_separator: &str, card_separator: &str) -> Vec<PreviewRow> {
+	split_cards(text, card_separator).into_iter().map(|(line, card)| {
+		let (card, status): (ImportCard, RowStatus) = match card.split_once(term_separator) {
+			Some((term, definition)) => {
+				let card: ImportCard = ImportCard::new(term.trim(), definition.trim());
+				let status: RowStatus = card.problem().map(RowStatus::Invalid).unwrap_or(RowStatus::Ready);
+				(card, status)
+			},
+			None => (ImportCard::new(&card, ""), RowStatus::Invalid(format!("No {} between term and definition", separator_name(term_separator)))),
+		};
+		PreviewRow { line, card, status }
+	}).collect()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn rows_keep_the_line_their_card_starts_on() {
+		let text: &str = "\n\nchat - cat\r\n\r\n\r\nchien\ndog - a dog\n\n\noiseau - bird\n";
+		let lines = |card_separator: &str| -> Vec<usize> { preview(text, " - ", card_separator).iter().map(|row| row.line).collect() };
+		assert_eq!(lines("\n"), vec![3, 6, 7, 10]);
+		assert_eq!(lines("\n\n"), vec![3, 6, 10]);
+		assert_eq!(lines(";"), vec![3]);
+	}
+
+	#[test]
+	fn detects_separators() {
+		let cases: [(&str, &str, &str, &str); 7] = [
+			("Quizlet tabs", "chat\tcat\nchien\tdog\noiseau\tbird", "\t", "\n"),
+			("Dashes per line", "chat - cat\nchien - dog\nJust a heading", " - ", "\n"),
+			("Blank line cards", "chat: a cat\nthat purrs\n\nchien: a dog\nthat barks", ": ", "\n\n"),
+			("Semicolon cards", "chat,cat;chien,dog;oiseau,bird", ",", ";"),
+			("Windows line endings", "chat = cat\r\nchien = dog", " = ", "\n"),
+			("Term tie goes to the earlier separator", "a - b = c\nd - e = f", " - ", "\n"),
+			("Nothing to split", "", "\t", "\n"),
+		];
+		for (name, text, term_separator, card_separator) in cases {
+			assert_eq!(detect_separators(text), (term_separator.to_owned(), card_separator.to_owned()), "{}", name);
+		}
+	}
+
+	#[test]
+	fn card_tie_goes_to_the_fraction_split() {
+		// Both split 2 cards, but by blank lines no card is left unsplit
+		assert_eq!(detect_separators("chat - cat\npurrs\n\nchien - dog"), (" - ".to_owned(), "\n\n".to_owned()));
+	}
+
+	#[test]
+	fn parses_typed_separators() {
+		let cases: [(&str, &str); 5] = [("\\t", "\t"), ("\\n", "\n"), ("\\n\\n", "\n\n"), (";", ";"), (" - ", " - ")];
+		for (typed, separator) in cases {
+			assert_eq!(parse_separator(typed), separator, "{}", typed);
+		}
+		assert_eq!(separator_name(&parse_separator("\\n\\n")), "Blank line");
+	}
+
+	#[test]
+	fn previews_invalid_rows() {
+		let rows: Vec<PreviewRow> = preview("chat,cat\nheading\n,dog\noiseau,\nle {{c1::chat}},", ",", "\n");
+		let statuses: Vec<RowStatus> = rows.iter().map(|row| row.status.clone()).collect();
+		assert_eq!(statuses, vec![
+			RowStatus::Ready,
+			RowStatus::Invalid("No \",\" between term and definition".to_owned()),
+			RowStatus::Invalid("No question".to_owned()),
+			RowStatus::Invalid("No answer".to_owned()),
+			RowStatus::Ready, // Cloze texts need no answer
+		]);
+		assert_eq!((rows[1].card.question.as_str(), rows[1].card.answer.as_str()), ("heading", ""));
+		assert_eq!(rows[0].card.answer, "cat");
+	}
+}
@@ -0,0 +1,348 @@
+use chrono::Utc; // Handles optimisation dates
+
+use rusqlite::{ // Handles SQLite database
+	params,
+	Connection,
+	OptionalExtension,
+};
+
+use crate::reviews::Review;
+use crate::scheduler::{Grade, Schedule, Scheduler, SECONDS_PER_DAY};
+
+pub const WEIGHT_COUNT: usize = 19;
+
+// FSRS-5 defaults, used until the weights have been optimised against the user's own reviews
+pub const DEFAULT_WEIGHTS: [f64; WEIGHT_COUNT] = [
+	0.40255, 1.18385, 3.173, 15.69105, 7.1949, 0.5345, 1.4604, 0.0046, 1.54575, 0.1192,
+	1.01925, 1.9395, 0.11, 0.29605, 2.2698, 0.2315, 2.9898, 0.51655, 0.6621,
+];
+
+// Lowest and highest value of each weight, so optimising can't produce a broken model
+const WEIGHT_BOUNDS: [(f64, f64); WEIGHT_COUNT] = [
+	(0.001, 100.0), (0.001, 100.0), (0.001, 100.0), (0.001, 100.0), (1.0, 10.0),
+	(0.001, 4.0), (0.001, 4.0), (0.001, 0.75), (0.0, 4.5), (0.0, 0.8),
+	(0.001, 3.5), (0.001, 5.0), (0.001, 0.25), (0.001, 0.9), (0.0, 4.0),
+	(0.0, 1.0), (1.0, 6.0), (0.0, 2.0), (0.0, 2.0),
+];
+
+const DECAY: f64 = -0.5;
+const FACTOR: f64 = 19.0 / 81.0; // Makes retrievability 90% after exactly `stability` days
+const DESIRED_RETENTION: f64 = 0.9;
+const MAXIMUM_INTERVAL: f64 = 36_500.0; // 100 years
+
+// Fewer scored reviews than this can't say much about the user's memory
+pub const MINIMUM_REVIEWS: usize = 100;
+const EPOCHS: usize = 200;
+const LEARNING_RATE: f64 = 0.04;
+
+/* **Explanation of FSRS**
+	- Every card has a stability (Days until recall drops to 90%) and a difficulty (1 to 10)
+	- Retrievability, the chance of remembering a card, is (1 + FACTOR * days / stability) ^ DECAY
+	- The first answer sets stability and difficulty from the grade alone
+	- Remembering a card raises its stability, more so when it is easy, its stability is
+		low and it was close to being forgotten. Forgetting it shrinks its stability
+	- Answering again on the same day nudges stability up or down by the grade
+	- The card is due again once retrievability is expected to drop to DESIRED_RETENTION
+*/
+pub struct Fsrs {
+	weights: [f64; WEIGHT_COUNT],
+}
+
+// Stability and difficulty of a card
+#[derive(Clone, Copy, Debug)]
+struct MemoryState {
+	stability: f64,
+	difficulty: f64,
+}
+
+impl Fsrs {
+	pub fn new(weights: [f64; WEIGHT_COUNT]) -> Fsrs {
+		Fsrs { weights }
+	}
+}
+
+impl Scheduler for Fsrs {
+	fn review(&self, schedule: &Schedule, grade: Grade, now: i64) -> Schedule {
+		let w: &[f64; WEIGHT_COUNT] = &self.weights;
+
+		let state: Option<MemoryState> = if schedule.stability > 0.0 {
+			Some(MemoryState { stability: schedule.stability, difficulty: schedule.difficulty })
+		} else if schedule.last_review > 0 {
+			// Scheduled by SM-2 until now, so its interval is the best guess of its stability
+			Some(MemoryState { stability: schedule.interval.max(1) as f64, difficulty: initial_difficulty(w, Grade::Good) })
+		} else {
+			None // New card
+		};
+		let elapsed_days: f64 = (now - schedule.last_review).max(0) as f64 / SECONDS_PER_DAY as f64;
+		let next_state: MemoryState = step(w, state, grade, elapsed_days);
+
+		let mut next: Schedule = *schedule;
+		next.stability = next_state.stability;
+		next.difficulty = next_state.difficulty;
+		next.interval = next_interval(next_state.stability);
+		next.repetitions = if grade == Grade::Again { 0 } else { schedule.repetitions + 1 };
+		next.due = now + next.interval as i64 * SECONDS_PER_DAY;
+		next.last_review = now;
+		next
+	}
+}
+
+//* Chance (0.0 to 1.0) of remembering a card after some days */
+fn retrievability(elapsed_days: f64, stability: f64) -> f64 {
+	(1.0 + FACTOR * elapsed_days / stability).powf(DECAY)
+}
+
+//* Days until retrievability drops to the desired retention */
+fn next_interval(stability: f64) -> i32 {
+	let interval: f64 = stability / FACTOR * (DESIRED_RETENTION.powf(1.0 / DECAY) - 1.0);
+	interval.round().clamp(1.0, MAXIMUM_INTERVAL) as i32
+}
+
+fn initial_difficulty(w: &[f64; WEIGHT_COUNT], grade: Grade) -> f64 {
+	(w[4] - (w[5] * (grade as i32 - 1) as f64).exp() + 1.0).clamp(1.0, 10.0)
+}
+
+//* Memory state of a card after answering it. `state` is None for a new card */
+fn step(w: &[f64; WEIGHT_COUNT], state: Option<MemoryState>, grade: Grade, elapsed_days: f64) -> MemoryState {
+	let state: MemoryState = match state {
+		Some(state) => state,
+		None => {
+			return MemoryState {
+				stability: w[grade as usize - 1].max(0.01),
+				difficulty: initial_difficulty(w, grade),
+			};
+		}
+	};
+	let g: f64 = grade as i32 as f64;
+
+	// Difficulty moves with the grade, slowing near 10, and drifts back towards an easy card's
+	let difficulty: f64 = state.difficulty - w[6] * (g - 3.0) * (10.0 - state.difficulty) / 9.0;
+	let difficulty: f64 = (w[7] * initial_difficulty(w, Grade::Easy) + (1.0 - w[7]) * difficulty).clamp(1.0, 10.0);
+
+	let stability: f64 = if elapsed_days < 1.0 {
+		state.stability * (w[17] * (g - 3.0 + w[18])).exp()
+	} else {
+		let r: f64 = retrievability(elapsed_days, state.stability);
+		if grade == Grade::Again {
+			let forgotten: f64 = w[11] * state.difficulty.powf(-w[12]) * ((state.stability + 1.0).powf(w[13]) - 1.0) * (w[14] * (1.0 - r)).exp();
+			forgotten.min(state.stability)
+		} else {
+			let hard_penalty: f64 = if grade == Grade::Hard { w[15] } else { 1.0 };
+			let easy_bonus: f64 = if grade == Grade::Easy { w[16] } else { 1.0 };
+			state.stability * (1.0 + w[8].exp() * (11.0 - state.difficulty) * state.stability.powf(-w[9])
+				* ((w[10] * (1.0 - r)).exp() - 1.0) * hard_penalty * easy_bonus)
+		}
+	};
+
+	MemoryState {
+		stability: stability.clamp(0.01, MAXIMUM_INTERVAL),
+		difficulty,
+	}
+}
+
+//* Average log loss of the weights' recall predictions over the review log, with how many reviews were scored */
+fn log_loss(w: &[f64; WEIGHT_COUNT], reviews: &[Review]) -> (f64, usize) {
+	let mut total: f64 = 0.0;
+	let mut scored: usize = 0;
+
+	let mut state: Option<MemoryState> = None;
+	let mut last: Option<&Review> = None;
+	for review in reviews.iter() {
+		let same_card: bool = last.is_some_and(|last| last.card_id == review.card_id);
+		if !same_card {
+			state = None;
+		}
+
+		let elapsed_days: f64 = match last {
+			Some(last) if same_card => (review.reviewed_at - last.reviewed_at).max(0) as f64 / SECONDS_PER_DAY as f64,
+			_ => 0.0,
+		};
+		if let Some(state) = state {
+			// Only reviews on a later day say anything about long term memory
+			if elapsed_days >= 1.0 {
+				let predicted: f64 = retrievability(elapsed_days, state.stability).clamp(0.0001, 0.9999);
+				let remembered: bool = review.grade != Grade::Again;
+				total -= if remembered { predicted.ln() } else { (1.0 - predicted).ln() };
+				scored += 1;
+			}
+		}
+
+		state = Some(step(w, state, review.grade, elapsed_days));
+		last = Some(review);
+	}
+
+	if scored == 0 {
+		(0.0, 0)
+	} else {
+		(total / scored as f64, scored)
+	}
+}
+
+/* Fits the weights to the user's review log (Grouped by card, oldest first) by minimising log loss
+with Adam, using numerical gradients. Returns the weights and how many reviews they were fitted to. */
+pub fn optimise(reviews: &[Review], start: [f64; WEIGHT_COUNT]) -> Result<([f64; WEIGHT_COUNT], usize), String> {
+	let (start_loss, scored): (f64, usize) = log_loss(&start, reviews);
+	if scored < MINIMUM_REVIEWS {
+		return Err(format!("Need {} reviews spread over several days to optimise, only have {}", MINIMUM_REVIEWS, scored));
+	}
+
+	let mut weights: [f64; WEIGHT_COUNT] = start;
+	let mut best: ([f64; WEIGHT_COUNT], f64) = (start, start_loss);
+	let mut first_moment: [f64; WEIGHT_COUNT] = [0.0; WEIGHT_COUNT];
+	let mut second_moment: [f64; WEIGHT_COUNT] = [0.0; WEIGHT_COUNT];
+
+	for epoch in 1..=EPOCHS {
+		// Central differences, with steps scaled to each weight's size
+		let mut gradient: [f64; WEIGHT_COUNT] = [0.0; WEIGHT_COUNT];
+		for index in 0..WEIGHT_COUNT {
+			let step_size: f64 = 1e-4 * weights[index].abs().max(1.0);
+			let mut up: [f64; WEIGHT_COUNT] = weights;
+			let mut down: [f64; WEIGHT_COUNT] = weights;
+			up[index] += step_size;
+			down[index] -= step_size;
+			gradient[index] = (log_loss(&up, reviews).0 - log_loss(&down, reviews).0) / (2.0 * step_size);
+		}
+
+		for index in 0..WEIGHT_COUNT {
+			first_moment[index] = 0.9 * first_moment[index] + 0.1 * gradient[index];
+			second_moment[index] = 0.999 * second_moment[index] + 0.001 * gradient[index].powi(2);
+			let corrected_first: f64 = first_moment[index] / (1.0 - 0.9_f64.powi(epoch as i32));
+			let corrected_second: f64 = second_moment[index] / (1.0 - 0.999_f64.powi(epoch as i32));
+
+			let (lowest, highest): (f64, f64) = WEIGHT_BOUNDS[index];
+			weights[index] -= LEARNING_RATE * corrected_first / (corrected_second.sqrt() + 1e-8);
+			weights[index] = weights[index].clamp(lowest, highest);
+		}
+
+		let loss: f64 = log_loss(&weights, reviews).0;
+		if loss < best.1 {
+			best = (weights, loss);
+		}
+	}
+
+	Ok((best.0, scored))
+}
+
+//* Reads the optimised weights, or the defaults if they have never been optimised */
+pub fn load_weights(conn: &Connection) -> rusqlite::Result<[f64; WEIGHT_COUNT]> {
+	let stored: Option<String> = conn.query_row(
+		"SELECT weights FROM fsrs_weights WHERE id = 1;",
+		params![],
+		|row: &rusqlite::Row<'_>| row.get(0),
+	).optional()?;
+
+	let parsed: Option<[f64; WEIGHT_COUNT]> = stored.and_then(|stored| {
+		let values: Vec<f64> = stored.split(',').filter_map(|value| value.trim().parse::<f64>().ok()).collect();
+		values.try_into().ok()
+	});
+	Ok(parsed.unwrap_or(DEFAULT_WEIGHTS))
+}
+
+//* Stores optimised weights, along with how many reviews they were fitted to */
+pub fn save_weights(conn: &Connection, weights: &[f64; WEIGHT_COUNT], reviews: usize) -> rusqlite::Result<()> {
+	let stored: String = weights.iter().map(|weight| weight.to_string()).collect::<Vec<String>>().join(",");
+	conn.execute(
+		"INSERT OR REPLACE INTO fsrs_weights (id, weights, reviews, optimised_at) VALUES (1, ?1, ?2, ?3);",
+		params![stored, reviews as i64, Utc::now().timestamp()],
+	)?;
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::scheduler::Algorithm;
+
+	fn close(actual: f64, expected: f64) -> bool {
+		(actual - expected).abs() < 1e-4
+	}
+
+	fn review(card_id: i32, day: i64, grade: Grade) -> Review {
+		Review {
+			card_id, reviewed_at: day * SECONDS_PER_DAY, grade, response_ms: 0,
+			algorithm: Algorithm::Fsrs, interval_before: 0, interval_after: 0,
+		}
+	}
+
+	//* Cards reviewed on days 0, 1, 4, 10 and 25, mostly remembered and forgotten more often the longer the gap */
+	fn synthetic_log(cards: i32) -> Vec<Review> {
+		let mut reviews: Vec<Review> = Vec::new();
+		for card_id in 1..=cards {
+			for (index, day) in [0, 1, 4, 10, 25].into_iter().enumerate() {
+				let forgotten: bool = (card_id * 7 + index as i32 * 3) % (8 - index as i32) == 0;
+				reviews.push(review(card_id, day, if forgotten { Grade::Again } else { Grade::Good }));
+			}
+		}
+		reviews
+	}
+
+	#[test]
+	fn first_review_matches_fsrs5() {
+		// S0 = w[grade - 1], D0 = w4 - e^(w5 * (grade - 1)) + 1
+		let again: MemoryState = step(&DEFAULT_WEIGHTS, None, Grade::Again, 0.0);
+		assert!(close(again.stability, 0.40255));
+		assert!(close(again.difficulty, 7.1949));
+
+		let good: MemoryState = step(&DEFAULT_WEIGHTS, None, Grade::Good, 0.0);
+		assert!(close(good.stability, 3.173));
+		assert!(close(good.difficulty, 5.28243));
+
+		let easy: MemoryState = step(&DEFAULT_WEIGHTS, None, Grade::Easy, 0.0);
+		assert!(close(easy.stability, 15.69105));
+		assert!(close(easy.difficulty, 3.22450));
+	}
+
+	#[test]
+	fn good_after_its_interval_grows_stability() {
+		let good: MemoryState = step(&DEFAULT_WEIGHTS, None, Grade::Good, 0.0);
+		let next: MemoryState = step(&DEFAULT_WEIGHTS, Some(good), Grade::Good, 3.0);
+		// Difficulty only mean reverts on Good, so it barely moves
+		assert!(close(next.difficulty, 0.0046 * 3.22450 + 0.9954 * 5.28243));
+		assert!(next.stability > 10.0 && next.stability < 12.0);
+
+		let forgotten: MemoryState = step(&DEFAULT_WEIGHTS, Some(good), Grade::Again, 3.0);
+		assert!(forgotten.stability < good.stability);
+		assert!(forgotten.difficulty > good.difficulty);
+	}
+
+	#[test]
+	fn interval_is_the_stability_at_90_percent() {
+		assert!(close(retrievability(10.0, 10.0), 0.9));
+		assert_eq!(next_interval(3.173), 3);
+		assert_eq!(next_interval(0.01), 1);
+		assert_eq!(next_interval(1e9), MAXIMUM_INTERVAL as i32);
+	}
+
+	#[test]
+	fn schedules_new_cards_from_the_first_grade() {
+		let now: i64 = 1_700_000_000;
+		let next: Schedule = Fsrs::new(DEFAULT_WEIGHTS).review(&Schedule::default(), Grade::Good, now);
+		assert!(close(next.stability, 3.173));
+		assert_eq!(next.interval, 3);
+		assert_eq!(next.due, now + 3 * SECONDS_PER_DAY);
+		assert_eq!(next.last_review, now);
+	}
+
+	#[test]
+	fn optimise_needs_enough_reviews() {
+		let reviews: Vec<Review> = synthetic_log(10); // 40 scored reviews
+		let error: String = optimise(&reviews, DEFAULT_WEIGHTS).unwrap_err();
+		assert_eq!(error, format!("Need {} reviews spread over several days to optimise, only have 40", MINIMUM_REVIEWS));
+
+		// Answers on the same day aren't scored
+		let same_day: Vec<Review> = (0..200).map(|index| review(index / 2, 0, Grade::Good)).collect();
+		assert!(optimise(&same_day, DEFAULT_WEIGHTS).is_err());
+	}
+
+	#[test]
+	fn optimise_does_not_raise_the_loss() {
+		let reviews: Vec<Review> = synthetic_log(40);
+		let (start_loss, _): (f64, usize) = log_loss(&DEFAULT_WEIGHTS, &reviews);
+		let (weights, scored): ([f64; WEIGHT_COUNT], usize) = optimise(&reviews, DEFAULT_WEIGHTS).unwrap();
+		assert_eq!(scored, 160);
+		assert!(log_loss(&weights, &reviews).0 <= start_loss);
+		for (weight, (lowest, highest)) in weights.iter().zip(WEIGHT_BOUNDS) {
+			assert!((lowest..=highest).contains(weight));
+		}
+	}
+}
@@ -0,0 +1,140 @@
+use rusqlite::Connection; // Handles SQLite database
+
+use crate::flashcards::{self, FlashcardSnapshot};
+
+// A single saved change to a flashcard
+struct Edit {
+	before: FlashcardSnapshot,
+	after: FlashcardSnapshot,
+}
+
+/* Undo/redo history of the edits made during one visit to the edit stage.
+Each entry stores the whole flashcard before and after, so undoing a reset
+also brings back the old tier and correct/incorrect counters. */
+#[derive(Default)]
+pub struct EditHistory {
+	undo_stack: Vec<Edit>,
+	redo_stack: Vec<Edit>,
+}
+
+impl EditHistory {
+	pub fn new() -> EditHistory {
+		EditHistory::default()
+	}
+
+	//* Stores a change that has already been written to the database */
+	pub fn record(&mut self, before: FlashcardSnapshot, after: FlashcardSnapshot) {
+		if before == after {
+			return; // Nothing changed
+		}
+		self.undo_stack.push(Edit { before, after });
+		self.redo_stack.clear(); // A new edit replaces anything that was undone
+	}
+
+	pub fn can_undo(&self) -> bool {
+		!self.undo_stack.is_empty()
+	}
+
+	pub fn can_redo(&self) -> bool {
+		!self.redo_stack.is_empty()
+	}
+
+	//* Reverts the most recent edit, returning the primary key of the flashcard changed */
+	pub fn undo(&mut self, conn: &Connection) -> rusqlite::Result<Option<i32>> {
+		let edit: Edit = match self.undo_stack.pop() {
+			Some(edit) => edit,
+			None => return Ok(None),
+		};
+
+		if let Err(e) = flashcards::write_snapshot(conn, &edit.before) {
+			self.undo_stack.push(edit); // Keep the history in step with the database
+			return Err(e);
+		}
+
+		let primary_key: i32 = edit.before.primary_key;
+		self.redo_stack.push(edit);
+		Ok(Some(primary_key))
+	}
+
+	//* Re-applies the most recently undone edit, returning the primary key of the flashcard changed */
+	pub fn redo(&mut self, conn: &Connection) -> rusqlite::Result<Option<i32>> {
+		let edit: Edit = match self.redo_stack.pop() {
+			Some(edit) => edit,
+			None => return Ok(None),
+		};
+
+		if let Err(e) = flashcards::write_snapshot(conn, &edit.after) {
+			self.redo_stack.push(edit);
+			return Err(e);
+		}
+
+		let primary_key: i32 = edit.after.primary_key;
+		self.undo_stack.push(edit);
+		Ok(Some(primary_key))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::database;
+	use crate::scheduler::Schedule;
+
+	//* A reviewed card, and the connection holding it */
+	fn reviewed_card() -> (Connection, i32) {
+		let conn: Connection = database::open_database(":memory:").unwrap();
+		let subject_id: i64 = database::get_or_add_subject(&conn, "French").unwrap();
+		let primary_key: i32 = flashcards::add_new_flashcard(&conn, subject_id, "chat", "cat", &["kitten".to_owned()]).unwrap() as i32;
+		flashcards::record_answer(&conn, primary_key, true).unwrap();
+		let schedule: Schedule = Schedule { interval: 6, repetitions: 2, due: 1_000_000, last_review: 500_000, ..Schedule::default() };
+		flashcards::set_schedule(&conn, primary_key, &schedule).unwrap();
+		(conn, primary_key)
+	}
+
+	#[test]
+	fn undoes_and_redoes_a_reset() {
+		let (conn, primary_key): (Connection, i32) = reviewed_card();
+		let before: FlashcardSnapshot = flashcards::load_snapshot(&conn, primary_key).unwrap();
+		flashcards::reset_flashcard(&conn, primary_key).unwrap();
+		let after: FlashcardSnapshot = flashcards::load_snapshot(&conn, primary_key).unwrap();
+		assert_eq!((after.correct, after.schedule.interval), (0, 0));
+
+		let mut history: EditHistory = EditHistory::new();
+		history.record(before.clone(), after.clone());
+		assert!(history.can_undo() && !history.can_redo());
+
+		assert_eq!(history.undo(&conn).unwrap(), Some(primary_key));
+		assert_eq!(flashcards::load_snapshot(&conn, primary_key).unwrap(), before);
+		assert!(!history.can_undo() && history.can_redo());
+		assert_eq!(history.undo(&conn).unwrap(), None);
+
+		assert_eq!(history.redo(&conn).unwrap(), Some(primary_key));
+		assert_eq!(flashcards::load_snapshot(&conn, primary_key).unwrap(), after);
+		assert!(history.can_undo() && !history.can_redo());
+	}
+
+	#[test]
+	fn unchanged_edits_are_not_recorded() {
+		let (conn, primary_key): (Connection, i32) = reviewed_card();
+		let snapshot: FlashcardSnapshot = flashcards::load_snapshot(&conn, primary_key).unwrap();
+		let mut history: EditHistory = EditHistory::new();
+		history.record(snapshot.clone(), snapshot);
+		assert!(!history.can_undo());
+	}
+
+	#[test]
+	fn failed_undos_change_nothing() {
+		let (conn, primary_key): (Connection, i32) = reviewed_card();
+		let before: FlashcardSnapshot = flashcards::load_snapshot(&conn, primary_key).unwrap();
+		flashcards::edit_flashcard(&conn, primary_key, "chien", "dog", &[]).unwrap();
+		let after: FlashcardSnapshot = flashcards::load_snapshot(&conn, primary_key).unwrap();
+		let mut history: EditHistory = EditHistory::new();
+		history.record(before, after.clone());
+
+		// The schedule is written after the question, so a half-done undo would show "chat"
+		conn.execute_batch("CREATE TRIGGER no_schedules BEFORE UPDATE OF due ON cards BEGIN SELECT RAISE(ABORT, 'locked'); END;").unwrap();
+		assert!(history.undo(&conn).is_err());
+		assert_eq!(flashcards::load_snapshot(&conn, primary_key).unwrap(), after);
+		assert!(history.can_undo() && !history.can_redo());
+	}
+}
@@ -0,0 +1,22 @@
+/* **Explanation of flashcard_core**
+Everything that isn't drawing: subjects, flashcards, scheduling, revision sessions, settings and
+file formats. The window (flashcard-revision) and the command line (flashcards) are frontends over it.
+	- `deck::Deck` opens the database and is the way in for subjects and their flashcards
+	- Modules keep their free functions taking a `Connection`, for everything the deck doesn't cover
+	- Messages are logged with the `log` crate, so they only show if the frontend sets up a logger
+*/
+
+pub mod choices;
+pub mod cloze;
+pub mod database;
+pub mod deck;
+pub mod flashcards;
+pub mod formats;
+pub mod fsrs;
+pub mod history;
+pub mod matching;
+pub mod notes;
+pub mod revision;
+pub mod reviews;
+pub mod scheduler;
+pub mod settings;
@@ -0,0 +1,324 @@
+use unicode_normalization::{char::is_combining_mark, UnicodeNormalization}; // Handles accents and look-alike characters
+
+// Words dropped when articles are ignored
+const ARTICLES: [&str; 3] = ["a", "an", "the"];
+
+// Which differences between a typed answer and the real one are forgiven (Set in settings.toml)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MatchOptions {
+	pub ignore_accents: bool, // "café" matches "cafe"
+	pub ignore_punctuation: bool, // "don't!" matches "dont"
+	pub ignore_articles: bool, // "the cat" matches "cat"
+	pub typo_tolerance: bool, // A few typos are allowed, more for longer answers
+}
+
+impl Default for MatchOptions {
+	fn default() -> MatchOptions {
+		MatchOptions {
+			ignore_accents: true,
+			ignore_punctuation: true,
+			ignore_articles: true,
+			typo_tolerance: true,
+		}
+	}
+}
+
+// How close a typed answer was to the real one
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AnswerMatch {
+	Exact, // Same once normalised
+	Close, // Within the typo tolerance
+	Wrong,
+}
+
+//* Removes "(ignored)" qualifiers, so "run (quickly)" only needs "run" */
+pub fn strip_qualifiers(text: &str) -> String {
+	let mut stripped: String = String::new();
+	let mut depth: usize = 0;
+	for character in text.chars() {
+		match character {
+			'(' => depth += 1,
+			')' if depth > 0 => depth -= 1,
+			_ if depth == 0 => stripped.push(character),
+			_ => {},
+		}
+	}
+	stripped
+}
+
+/* **Explanation of answer normalising**
+	- NFKC turns look-alike characters into one form (e.g. full width letters, ligatures)
+	- Everything is lowercased
+	- Accents are removed by splitting characters into letter + accent (NFD) and dropping the accents
+	- Punctuation is turned into spaces, so "well-known" and "well known" match
+	- Articles are dropped and runs of spaces are collapsed into one
+*/
+pub fn normalise(text: &str, options: &MatchOptions) -> String {
+	let mut normalised: String = text.nfkc().collect::<String>().to_lowercase();
+
+	if options.ignore_accents {
+		normalised = normalised.nfd().filter(|character| !is_combining_mark(*character)).nfc().collect();
+	}
+	if options.ignore_punctuation {
+		normalised = normalised.chars()
+			.filter(|character| *character != '\'' && *character != '’') // "don't" matches "dont"
+			.map(|character| if character.is_alphanumeric() { character } else { ' ' })
+			.collect();
+	}
+
+	normalised.split_whitespace()
+		.filter(|word| !(options.ignore_articles && ARTICLES.contains(word)))
+		.collect::<Vec<&str>>()
+		.join(" ")
+}
+
+//* Typos allowed for an answer of this many characters */
+pub fn tolerance(length: usize) -> usize {
+	match length {
+		0..=3 => 0, // Too short, one typo could be a different word
+		4..=7 => 1,
+		8..=14 => 2,
+		_ => length / 7,
+	}
+}
+
+//* Fewest single character insertions, deletions or substitutions that turn one text into the other */
+pub fn edit_distance(first: &[char], second: &[char]) -> usize {
+	let mut previous: Vec<usize> = (0..=second.len()).collect();
+	for (i, first_char) in first.iter().enumerate() {
+		let mut current: Vec<usize> = vec![i + 1];
+		for (j, second_char) in second.iter().enumerate() {
+			let substitution: usize = previous[j] + usize::from(first_char != second_char);
+			current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+		}
+		previous = current;
+	}
+	previous[second.len()]
+}
+
+//* Checks a typed answer against one accepted answer, returning how close it was and the edit distance */
+fn check_one(typed: &str, answer: &str, options: &MatchOptions) -> (AnswerMatch, usize) {
+	let mut options: MatchOptions = *options;
+	let stripped: String = strip_qualifiers(answer);
+	let answer: String = if stripped.trim().is_empty() {
+		answer.to_owned() // Whole answer is in brackets, so it is kept
+	} else {
+		stripped
+	};
+	if normalise(&answer, &options).is_empty() {
+		// Answer is only punctuation or articles (e.g. "a"), so those have to be kept
+		options.ignore_punctuation = false;
+		options.ignore_articles = false;
+	}
+	let typed: Vec<char> = normalise(&strip_qualifiers(typed), &options).chars().collect();
+	let answer: Vec<char> = normalise(&answer, &options).chars().collect();
+
+	let distance: usize = edit_distance(&typed, &answer);
+	if typed == answer {
+		(AnswerMatch::Exact, 0)
+	} else if options.typo_tolerance && !typed.is_empty() && distance <= tolerance(answer.len()) {
+		(AnswerMatch::Close, distance)
+	} else {
+		(AnswerMatch::Wrong, distance)
+	}
+}
+
+//* Checks a typed answer against every accepted answer, returning the best result and which answer it was for */
+pub fn check_answer(typed: &str, answers: &[String], options: &MatchOptions) -> (AnswerMatch, usize) {
+	let mut best: (AnswerMatch, usize, usize) = (AnswerMatch::Wrong, usize::MAX, 0);
+	for (index, answer) in answers.iter().enumerate() {
+		let (result, distance): (AnswerMatch, usize) = check_one(typed, answer, options);
+		let rank = |result: AnswerMatch| match result {
+			AnswerMatch::Exact => 0,
+			AnswerMatch::Close => 1,
+			AnswerMatch::Wrong => 2,
+		};
+		if (rank(result), distance) < (rank(best.0), best.1) {
+			best = (result, distance, index);
+		}
+	}
+	(best.0, best.2)
+}
+
+// Part of an answer shown by the diff, and whether it matched the other answer
+#[derive(Clone, Debug, PartialEq)]
+pub struct DiffSegment {
+	pub text: String,
+	pub matched: bool,
+}
+
+fn push_segment(segments: &mut Vec<DiffSegment>, character: char, matched: bool) {
+	match segments.last_mut() {
+		Some(last) if last.matched == matched => last.text.push(character),
+		_ => segments.push(DiffSegment { text: character.to_string(), matched }),
+	}
+}
+
+/* Lines up the typed answer with the real one (Ignoring case), returning the segments of each.
+Unmatched segments of the typed answer are mistakes, unmatched segments of the real answer were missed. */
+pub fn diff(typed: &str, answer: &str) -> (Vec<DiffSegment>, Vec<DiffSegment>) {
+	let typed: Vec<char> = typed.trim().chars().collect();
+	let answer: Vec<char> = answer.trim().chars().collect();
+	let same = |first: char, second: char| first.to_lowercase().eq(second.to_lowercase());
+
+	// Edit distance table, walked back from the end to find which characters line up
+	let mut table: Vec<Vec<usize>> = vec![vec![0; answer.len() + 1]; typed.len() + 1];
+	for (i, row) in table.iter_mut().enumerate() {
+		row[0] = i;
+	}
+	table[0] = (0..=answer.len()).collect();
+	for i in 1..=typed.len() {
+		for j in 1..=answer.len() {
+			let substitution: usize = table[i - 1][j - 1] + usize::from(!same(typed[i - 1], answer[j - 1]));
+			table[i][j] = substitution.min(table[i - 1][j] + 1).min(table[i][j - 1] + 1);
+		}
+	}
+
+	let mut typed_flags: Vec<bool> = vec![false; typed.len()];
+	let mut answer_flags: Vec<bool> = vec![false; answer.len()];
+	let (mut i, mut j): (usize, usize) = (typed.len(), answer.len());
+	while i > 0 && j > 0 {
+		if same(typed[i - 1], answer[j - 1]) && table[i][j] == table[i - 1][j - 1] {
+			typed_flags[i - 1] = true;
+			answer_flags[j - 1] = true;
+			i -= 1;
+			j -= 1;
+		} else if table[i][j] == table[i - 1][j - 1] + 1 {
+			i -= 1; // Substitution, neither matched
+			j -= 1;
+		} else if table[i][j] == table[i - 1][j] + 1 {
+			i -= 1; // Extra typed character
+		} else {
+			j -= 1; // Missed character
+		}
+	}
+
+	let mut typed_segments: Vec<DiffSegment> = Vec::new();
+	for (character, matched) in typed.iter().zip(typed_flags) {
+		push_segment(&mut typed_segments, *character, matched);
+	}
+	let mut answer_segments: Vec<DiffSegment> = Vec::new();
+	for (character, matched) in answer.iter().zip(answer_flags) {
+		push_segment(&mut answer_segments, *character, matched);
+	}
+	(typed_segments, answer_segments)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const STRICT: MatchOptions = MatchOptions { ignore_accents: false, ignore_punctuation: false, ignore_articles: false, typo_tolerance: false };
+
+	fn check(typed: &str, answer: &str, options: MatchOptions) -> AnswerMatch {
+		check_answer(typed, &[answer.to_owned()], &options).0
+	}
+
+	fn segments(parts: &[(&str, bool)]) -> Vec<DiffSegment> {
+		parts.iter().map(|(text, matched)| DiffSegment { text: text.to_string(), matched: *matched }).collect()
+	}
+
+	#[test]
+	fn options_forgive_their_differences() {
+		let cases: [(&str, &str, MatchOptions, AnswerMatch); 14] = [
+			("cafe", "café", MatchOptions { ignore_accents: true, ..STRICT }, AnswerMatch::Exact),
+			("cafe", "café", STRICT, AnswerMatch::Wrong),
+			("CAFÉ", "café", STRICT, AnswerMatch::Exact), // Case never matters
+			("ﬁne", "fine", STRICT, AnswerMatch::Exact), // Ligatures are split by NFKC
+			("dont", "don't!", MatchOptions { ignore_punctuation: true, ..STRICT }, AnswerMatch::Exact),
+			("well known", "well-known", MatchOptions { ignore_punctuation: true, ..STRICT }, AnswerMatch::Exact),
+			("dont", "don't!", STRICT, AnswerMatch::Wrong),
+			("cat", "the cat", MatchOptions { ignore_articles: true, ..STRICT }, AnswerMatch::Exact),
+			("an apple", "apple", MatchOptions { ignore_articles: true, ..STRICT }, AnswerMatch::Exact),
+			("cat", "the cat", STRICT, AnswerMatch::Wrong),
+			("theatre", "the atre", MatchOptions { ignore_articles: true, ..STRICT }, AnswerMatch::Wrong), // Only whole words
+			("a", "a", MatchOptions::default(), AnswerMatch::Exact), // Answers that are only an article keep it
+			("", "a", MatchOptions::default(), AnswerMatch::Wrong),
+			("  the   Cat  ", "cat", MatchOptions::default(), AnswerMatch::Exact),
+		];
+		for (typed, answer, options, expected) in cases {
+			assert_eq!(check(typed, answer, options), expected, "{:?} against {:?} with {:?}", typed, answer, options);
+		}
+	}
+
+	#[test]
+	fn tolerance_grows_with_length() {
+		let cases: [(usize, usize); 9] = [(0, 0), (3, 0), (4, 1), (7, 1), (8, 2), (14, 2), (15, 2), (21, 3), (70, 10)];
+		for (length, typos) in cases {
+			assert_eq!(tolerance(length), typos, "length {}", length);
+		}
+	}
+
+	#[test]
+	fn typos_within_the_tolerance_are_close() {
+		let typos: MatchOptions = MatchOptions { typo_tolerance: true, ..STRICT };
+		let cases: [(&str, &str, AnswerMatch); 9] = [
+			("cta", "cat", AnswerMatch::Wrong), // 3 letters, no typos allowed
+			("dgo", "dog", AnswerMatch::Wrong),
+			("helo", "hello", AnswerMatch::Close), // 5 letters, 1 typo
+			("hel", "hello", AnswerMatch::Wrong),
+			("mitochondira", "mitochondria", AnswerMatch::Close), // 12 letters, 2 typos
+			("mitchondira", "mitochondria", AnswerMatch::Wrong),
+			("photosynthesys", "photosynthesis", AnswerMatch::Close),
+			("", "hello", AnswerMatch::Wrong), // Nothing typed is never close
+			("helo", "hello", AnswerMatch::Close),
+		];
+		for (typed, answer, expected) in cases {
+			assert_eq!(check(typed, answer, typos), expected, "{:?} against {:?}", typed, answer);
+		}
+		assert_eq!(check("helo", "hello", STRICT), AnswerMatch::Wrong);
+	}
+
+	#[test]
+	fn edit_distance_counts_single_changes() {
+		let distance = |first: &str, second: &str| edit_distance(&first.chars().collect::<Vec<char>>(), &second.chars().collect::<Vec<char>>());
+		assert_eq!(distance("", ""), 0);
+		assert_eq!(distance("", "abc"), 3);
+		assert_eq!(distance("kitten", "sitting"), 3);
+		assert_eq!(distance("flaw", "lawn"), 2);
+		assert_eq!(distance("ab", "ba"), 2); // Swaps count as two
+	}
+
+	#[test]
+	fn qualifiers_are_not_needed() {
+		assert_eq!(strip_qualifiers("run (quickly)"), "run ");
+		assert_eq!(strip_qualifiers("(to) be (or (not))"), " be ");
+		assert_eq!(strip_qualifiers("a) b"), "a) b"); // Unopened brackets are kept
+
+		let cases: [(&str, &str, AnswerMatch); 5] = [
+			("run", "run (quickly)", AnswerMatch::Exact),
+			("run (fast)", "run (quickly)", AnswerMatch::Exact),
+			("be", "(to) be", AnswerMatch::Exact),
+			("none", "(none)", AnswerMatch::Exact), // Whole answer in brackets is kept
+			("quickly", "run (quickly)", AnswerMatch::Wrong),
+		];
+		for (typed, answer, expected) in cases {
+			assert_eq!(check(typed, answer, MatchOptions::default()), expected, "{:?} against {:?}", typed, answer);
+		}
+	}
+
+	#[test]
+	fn picks_the_best_accepted_answer() {
+		let answers = |answers: &[&str]| answers.iter().map(|answer| answer.to_string()).collect::<Vec<String>>();
+		let options: MatchOptions = MatchOptions::default();
+		assert_eq!(check_answer("color", &answers(&["colour", "color"]), &options), (AnswerMatch::Exact, 1));
+		assert_eq!(check_answer("cat", &answers(&["cats", "cat"]), &options), (AnswerMatch::Exact, 1)); // Exact beats an earlier close one
+		assert_eq!(check_answer("colr", &answers(&["colour", "color"]), &options), (AnswerMatch::Close, 1));
+		assert_eq!(check_answer("hous", &answers(&["home", "house"]), &options), (AnswerMatch::Close, 1)); // Closest of the close ones
+		assert_eq!(check_answer("dog", &answers(&["chien", "dogs are"]), &options).0, AnswerMatch::Wrong);
+		assert_eq!(check_answer("dog", &[], &options).0, AnswerMatch::Wrong);
+	}
+
+	#[test]
+	fn diff_marks_mistakes_and_missed_characters() {
+		let (typed, answer): (Vec<DiffSegment>, Vec<DiffSegment>) = diff("Helo", "hello");
+		assert_eq!(typed, segments(&[("Helo", true)]));
+		assert_eq!(answer, segments(&[("he", true), ("l", false), ("lo", true)])); // Either l could be the missed one
+
+		let (typed, answer): (Vec<DiffSegment>, Vec<DiffSegment>) = diff(" cst ", "cat");
+		assert_eq!(typed, segments(&[("c", true), ("s", false), ("t", true)]));
+		assert_eq!(answer, segments(&[("c", true), ("a", false), ("t", true)]));
+
+		assert_eq!(diff("", "cat"), (Vec::new(), segments(&[("cat", false)])));
+	}
+}
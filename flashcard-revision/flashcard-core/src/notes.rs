@@ -0,0 +1,523 @@
+use rusqlite::{ // Handles SQLite database
+	params,
+	Connection,
+	OptionalExtension,
+};
+
+// Most fields a note type can have, so each field gets its own row in the flashcard box
+pub const MAX_FIELDS: usize = 5;
+
+// Makes one card from the fields of a note, e.g. front "{{Word}}" and back "{{Meaning}}"
+#[derive(Clone, Debug, PartialEq)]
+pub struct CardTemplate {
+	pub name: String,
+	pub front: String, // Question, with {{Field}} replaced by the note's value
+	pub back: String, // Answer, with {{Field}} replaced by the note's value
+}
+
+// Named fields and the cards made from them. Users can make their own
+#[derive(Clone, Debug, PartialEq)]
+pub struct NoteType {
+	pub id: i64, // Primary key in the note_types table (0 = not saved yet)
+	pub name: String,
+	pub fields: Vec<String>,
+	pub templates: Vec<CardTemplate>,
+}
+
+// Values for the fields of a note type. Cards are made from a note, not edited on their own
+#[derive(Clone, Debug, PartialEq)]
+pub struct Note {
+	pub id: i64, // Primary key in the notes table
+	pub subject_id: i64,
+	pub note_type_id: i64,
+	pub values: Vec<String>, // One per field of the note type, in the same order
+}
+
+/* **Explanation of templates**
+	- {{Field}} is replaced by the value of the field with that name (Spaces around the name are ignored)
+	- Anything else in braces is left as it is
+	- A card is only made if at least one field used on its front has a value, so optional
+		fields (e.g. Example) don't make blank cards
+*/
+pub fn render(template: &str, fields: &[String], values: &[String]) -> (String, bool) {
+	let mut rendered: String = String::new();
+	let mut has_value: bool = false;
+	let mut rest: &str = template;
+
+	while let Some(start) = rest.find("{{") {
+		rendered.push_str(&rest[..start]);
+		let after: &str = &rest[start + 2..];
+		let field: Option<(usize, usize)> = after.find("}}")
+			.and_then(|end| fields.iter().position(|field| field == after[..end].trim()).map(|index| (index, end)));
+
+		match field {
+			Some((index, end)) => {
+				let value: &str = values.get(index).map(|value| value.as_str()).unwrap_or("");
+				has_value |= !value.trim().is_empty();
+				rendered.push_str(value);
+				rest = &after[end + 2..];
+			},
+			None => {
+				rendered.push_str("{{");
+				rest = after;
+			}
+		}
+	}
+	rendered.push_str(rest);
+	(rendered, has_value)
+}
+
+//* The cards a note makes, as (template position, question, answer) */
+pub fn generate(note_type: &NoteType, values: &[String]) -> Vec<(usize, String, String)> {
+	let mut cards: Vec<(usize, String, String)> = Vec::new();
+	for (position, template) in note_type.templates.iter().enumerate() {
+		let (front, has_value): (String, bool) = render(&template.front, &note_type.fields, values);
+		if has_value {
+			let (back, _): (String, bool) = render(&template.back, &note_type.fields, values);
+			cards.push((position, front.trim().to_owned(), back.trim().to_owned()));
+		}
+	}
+	cards
+}
+
+//* Checks a note type can be saved, returning what is wrong with it if not */
+pub fn validate(note_type: &NoteType) -> Result<(), String> {
+	if note_type.name.trim().is_empty() {
+		return Err("Note types need a name.".to_owned());
+	}
+	if note_type.fields.is_empty() || note_type.fields.len() > MAX_FIELDS {
+		return Err(format!("Note types need 1 to {} fields.", MAX_FIELDS));
+	}
+	for (index, field) in note_type.fields.iter().enumerate() {
+		if note_type.fields[..index].contains(field) {
+			return Err(format!("There are two fields called {}.", field));
+		}
+	}
+	if note_type.templates.is_empty() {
+		return Err("Note types need at least one card template.".to_owned());
+	}
+	for template in note_type.templates.iter() {
+		let uses_field: bool = note_type.fields.iter().any(|field| render(&template.front, std::slice::from_ref(field), &["x".to_owned()]).1);
+		if !uses_field {
+			return Err(format!("The front of {} doesn't use any fields.", template.name));
+		}
+	}
+	Ok(())
+}
+
+//* Reads card templates written one per line as "Name: front -> back" */
+pub fn parse_templates(text: &str) -> Result<Vec<CardTemplate>, String> {
+	let mut templates: Vec<CardTemplate> = Vec::new();
+	for line in text.lines().map(|line| line.trim()).filter(|line| !line.is_empty()) {
+		let (name, sides): (Option<&str>, &str) = match line.split_once(':') {
+			Some((name, sides)) if !name.contains("{{") && sides.contains("->") => (Some(name.trim()), sides),
+			_ => (None, line), // No name, so it is named after its position
+		};
+		let (front, back): (&str, &str) = sides.split_once("->")
+			.ok_or_else(|| format!("\"{}\" needs a front and back, e.g. Card: {{{{Front}}}} -> {{{{Back}}}}", line))?;
+		let name: String = match name {
+			Some(name) if !name.is_empty() => name.to_owned(),
+			_ => format!("Card {}", templates.len() + 1),
+		};
+		templates.push(CardTemplate { name, front: front.trim().to_owned(), back: back.trim().to_owned() });
+	}
+	Ok(templates)
+}
+
+//* Writes card templates in the form read by `parse_templates` */
+pub fn format_templates(templates: &[CardTemplate]) -> String {
+	templates.iter()
+		.map(|template| format!("{}: {} -> {}", template.name, template.front, template.back))
+		.collect::<Vec<String>>()
+		.join("\n")
+}
+
+//* Loads every note type, oldest first */
+pub fn load_note_types(conn: &Connection) -> rusqlite::Result<Vec<NoteType>> {
+	let mut stmt: rusqlite::Statement<'_> = conn.prepare("SELECT id, name FROM note_types ORDER BY id;")?;
+	let mut note_types: Vec<NoteType> = stmt.query_map(params![], |row: &rusqlite::Row<'_>| {
+		Ok(NoteType { id: row.get(0)?, name: row.get(1)?, fields: Vec::new(), templates: Vec::new() })
+	})?.collect::<rusqlite::Result<_>>()?;
+
+	for note_type in note_types.iter_mut() {
+		let mut stmt: rusqlite::Statement<'_> = conn.prepare("SELECT name FROM note_fields WHERE note_type_id = ?1 ORDER BY position;")?;
+		note_type.fields = stmt.query_map(params![note_type.id], |row: &rusqlite::Row<'_>| row.get(0))?
+			.collect::<rusqlite::Result<_>>()?;
+
+		let mut stmt: rusqlite::Statement<'_> = conn.prepare(
+			"SELECT name, front, back FROM card_templates WHERE note_type_id = ?1 ORDER BY position;"
+		)?;
+		note_type.templates = stmt.query_map(params![note_type.id], |row: &rusqlite::Row<'_>| {
+			Ok(CardTemplate { name: row.get(0)?, front: row.get(1)?, back: row.get(2)? })
+		})?.collect::<rusqlite::Result<_>>()?;
+	}
+	Ok(note_types)
+}
+
+//* Loads one note type */
+pub fn load_note_type(conn: &Connection, note_type_id: i64) -> rusqlite::Result<NoteType> {
+	load_note_types(conn)?.into_iter()
+		.find(|note_type| note_type.id == note_type_id)
+		.ok_or(rusqlite::Error::QueryReturnedNoRows)
+}
+
+/* For each new field or template, the position of the old one it carries on from (None = new).
+Each test is tried in turn, then unmatched ones at the same position are paired, so renaming in place keeps its values */
+fn carried_over<T>(old: &[T], new: &[T], tests: &[fn(&T, &T) -> bool]) -> Vec<Option<usize>> {
+	let mut matched: Vec<Option<usize>> = vec![None; new.len()];
+	let mut used: Vec<bool> = vec![false; old.len()];
+	for test in tests {
+		for (index, item) in new.iter().enumerate() {
+			if matched[index].is_some() {
+				continue;
+			}
+			if let Some(position) = (0..old.len()).find(|position| !used[*position] && test(&old[*position], item)) {
+				matched[index] = Some(position);
+				used[position] = true;
+			}
+		}
+	}
+	for index in 0..new.len().min(old.len()) {
+		if matched[index].is_none() && !used[index] {
+			matched[index] = Some(index);
+			used[index] = true;
+		}
+	}
+	matched
+}
+
+/* **Explanation of saving note types**
+Values and cards are stored by position, so when a note type changes they are moved to follow
+the fields and templates they belong to:
+	- Fields are followed by name, templates by their front and back, then by name
+	- Values of removed fields are dropped, and cards of removed templates are removed with their reviews
+	- Cards keep their schedule and review history when their template moves
+Everything happens in one transaction, so a failure leaves the note type as it was.
+*/
+pub fn save_note_type(conn: &Connection, note_type: &NoteType) -> rusqlite::Result<i64> {
+	// Imports save note types inside their own transaction
+	let transaction: Option<rusqlite::Transaction<'_>> = if conn.is_autocommit() { Some(conn.unchecked_transaction()?) } else { None };
+
+	let note_type_id: i64 = if note_type.id == 0 {
+		conn.execute("INSERT INTO note_types (name) VALUES (?1);", params![note_type.name])?;
+		conn.last_insert_rowid()
+	} else {
+		conn.execute("UPDATE note_types SET name = ?1 WHERE id = ?2;", params![note_type.name, note_type.id])?;
+		note_type.id
+	};
+
+	let mut stmt: rusqlite::Statement<'_> = conn.prepare("SELECT id FROM notes WHERE note_type_id = ?1;")?;
+	let note_ids: Vec<i64> = stmt.query_map(params![note_type_id], |row: &rusqlite::Row<'_>| row.get(0))?
+		.collect::<rusqlite::Result<_>>()?;
+	if !note_ids.is_empty() {
+		let old: NoteType = load_note_type(conn, note_type_id)?;
+		let fields: Vec<Option<usize>> = carried_over(&old.fields, &note_type.fields, &[|old, new| old == new]);
+		let templates: Vec<Option<usize>> = carried_over(&old.templates, &note_type.templates, &[
+			|old, new| old.front == new.front && old.back == new.back,
+			|old, new| old.name == new.name,
+		]);
+
+		for note_id in note_ids.iter() {
+			let values: Vec<String> = load_note(conn, *note_id)?.values;
+			let moved: Vec<String> = fields.iter()
+				.map(|old| old.and_then(|position| values.get(position).cloned()).unwrap_or_default())
+				.collect();
+			set_values(conn, *note_id, &moved)?;
+		}
+
+		let mut stmt: rusqlite::Statement<'_> = conn.prepare(
+			"SELECT cards.id, cards.template_position FROM cards JOIN notes ON cards.note_id = notes.id WHERE notes.note_type_id = ?1;"
+		)?;
+		let cards: Vec<(i32, i64)> = stmt.query_map(params![note_type_id], |row: &rusqlite::Row<'_>| Ok((row.get(0)?, row.get(1)?)))?
+			.collect::<rusqlite::Result<_>>()?;
+		for (primary_key, old_position) in cards {
+			match templates.iter().position(|old| *old == Some(old_position as usize)) {
+				Some(position) => conn.execute("UPDATE cards SET template_position = ?1 WHERE id = ?2;", params![position as i64, primary_key])?,
+				None => conn.execute("DELETE FROM cards WHERE id = ?1;", params![primary_key])?, // Reviews go with it (ON DELETE CASCADE)
+			};
+		}
+	}
+
+	conn.execute("DELETE FROM note_fields WHERE note_type_id = ?1;", params![note_type_id])?;
+	for (position, field) in note_type.fields.iter().enumerate() {
+		conn.execute(
+			"INSERT INTO note_fields (note_type_id, position, name) VALUES (?1, ?2, ?3);",
+			params![note_type_id, position as i64, field],
+		)?;
+	}
+	conn.execute("DELETE FROM card_templates WHERE note_type_id = ?1;", params![note_type_id])?;
+	for (position, template) in note_type.templates.iter().enumerate() {
+		conn.execute(
+			"INSERT INTO card_templates (note_type_id, position, name, front, back) VALUES (?1, ?2, ?3, ?4, ?5);",
+			params![note_type_id, position as i64, template.name, template.front, template.back],
+		)?;
+	}
+	for note_id in note_ids {
+		sync_note_cards(conn, note_id)?;
+	}
+
+	if let Some(transaction) = transaction {
+		transaction.commit()?;
+	}
+	Ok(note_type_id)
+}
+
+//* Returns how many notes use a note type */
+pub fn count_notes(conn: &Connection, note_type_id: i64) -> rusqlite::Result<i64> {
+	conn.query_row("SELECT COUNT(*) FROM notes WHERE note_type_id = ?1;", params![note_type_id], |row: &rusqlite::Row<'_>| row.get(0))
+}
+
+//* Removes a note type. Only used once no notes use it */
+pub fn delete_note_type(conn: &Connection, note_type_id: i64) -> rusqlite::Result<()> {
+	conn.execute("DELETE FROM note_types WHERE id = ?1;", params![note_type_id])?;
+	Ok(())
+}
+
+//* Reads one note and its values */
+pub fn load_note(conn: &Connection, note_id: i64) -> rusqlite::Result<Note> {
+	let (subject_id, note_type_id): (i64, i64) = conn.query_row(
+		"SELECT subject_id, note_type_id FROM notes WHERE id = ?1;",
+		params![note_id],
+		|row: &rusqlite::Row<'_>| Ok((row.get(0)?, row.get(1)?)),
+	)?;
+	let mut stmt: rusqlite::Statement<'_> = conn.prepare("SELECT value FROM note_values WHERE note_id = ?1 ORDER BY position;")?;
+	let values: Vec<String> = stmt.query_map(params![note_id], |row: &rusqlite::Row<'_>| row.get(0))?
+		.collect::<rusqlite::Result<_>>()?;
+
+	Ok(Note { id: note_id, subject_id, note_type_id, values })
+}
+
+fn set_values(conn: &Connection, note_id: i64, values: &[String]) -> rusqlite::Result<()> {
+	conn.execute("DELETE FROM note_values WHERE note_id = ?1;", params![note_id])?;
+	for (position, value) in values.iter().enumerate() {
+		conn.execute(
+			"INSERT INTO note_values (note_id, position, value) VALUES (?1, ?2, ?3);",
+			params![note_id, position as i64, value],
+		)?;
+	}
+	Ok(())
+}
+
+//* Creates a note and its cards (Due straight away), returning the note's primary key and how many cards it made */
+pub fn add_note(conn: &Connection, subject_id: i64, note_type_id: i64, values: &[String]) -> rusqlite::Result<(i64, usize)> {
+	conn.execute("INSERT INTO notes (subject_id, note_type_id) VALUES (?1, ?2);", params![subject_id, note_type_id])?;
+	let note_id: i64 = conn.last_insert_rowid();
+	set_values(conn, note_id, values)?;
+	Ok((note_id, sync_note_cards(conn, note_id)?))
+}
+
+//* Stores new values for a note and remakes its cards, returning how many it has */
+pub fn edit_note(conn: &Connection, note_id: i64, values: &[String]) -> rusqlite::Result<usize> {
+	set_values(conn, note_id, values)?;
+	sync_note_cards(conn, note_id)
+}
+
+//* Returns the note a card was made from, if any */
+pub fn note_of_card(conn: &Connection, primary_key: i32) -> rusqlite::Result<Option<i64>> {
+	conn.query_row("SELECT note_id FROM cards WHERE id = ?1;", params![primary_key], |row: &rusqlite::Row<'_>| row.get(0))
+		.optional()
+		.map(|note_id: Option<Option<i64>>| note_id.flatten())
+}
+
+//* Returns the primary keys of the cards made from a note, in template order */
+pub fn cards_of_note(conn: &Connection, note_id: i64) -> rusqlite::Result<Vec<i32>> {
+	let mut stmt: rusqlite::Statement<'_> = conn.prepare("SELECT id FROM cards WHERE note_id = ?1 ORDER BY template_position;")?;
+	let cards = stmt.query_map(params![note_id], |row: &rusqlite::Row<'_>| row.get(0))?;
+
+	cards.collect()
+}
+
+/* **Explanation of note syncing**
+Every card of a note stores its rendered question and answer, so revision works the same for
+every card. After a note or its type changes:
+	- Cards whose template still makes a card get the new question and answer, keeping their schedule
+	- Cards whose template no longer makes a card (e.g. a field was emptied) are removed
+	- Templates that now make a card get a new card, due straight away
+All cards of a note are one group, so only one of them is revised per day.
+*/
+pub fn sync_note_cards(conn: &Connection, note_id: i64) -> rusqlite::Result<usize> {
+	let note: Note = load_note(conn, note_id)?;
+	let note_type: NoteType = load_note_type(conn, note.note_type_id)?;
+	let generated: Vec<(usize, String, String)> = generate(&note_type, &note.values);
+
+	let mut stmt: rusqlite::Statement<'_> = conn.prepare("SELECT id, template_position FROM cards WHERE note_id = ?1;")?;
+	let existing: Vec<(i32, usize)> = stmt.query_map(params![note_id], |row: &rusqlite::Row<'_>| {
+		Ok((row.get(0)?, row.get::<_, i64>(1)? as usize))
+	})?.collect::<rusqlite::Result<_>>()?;
+
+	for (primary_key, position) in existing.iter() {
+		match generated.iter().find(|(generated_position, _, _)| generated_position == position) {
+			Some((_, question, answer)) => {
+				conn.execute("UPDATE cards SET question = ?1, answer = ?2 WHERE id = ?3;", params![question, answer, primary_key])?;
+			},
+			None => {
+				conn.execute("DELETE FROM cards WHERE id = ?1;", params![primary_key])?;
+			}
+		}
+	}
+	for (position, question, answer) in generated.iter() {
+		if !existing.iter().any(|(_, existing_position)| existing_position == position) {
+			conn.execute(
+				"INSERT INTO cards (subject_id, question, answer, correct, incorrect, note_id, template_position) VALUES (?1, ?2, ?3, 0, 0, ?4, ?5);",
+				params![note.subject_id, question, answer, note_id, *position as i64],
+			)?;
+		}
+	}
+	conn.execute(
+		"UPDATE cards SET group_id = (SELECT MIN(id) FROM cards WHERE note_id = ?1) WHERE note_id = ?1;",
+		params![note_id],
+	)?;
+	Ok(generated.len())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::database;
+
+	fn strings(items: &[&str]) -> Vec<String> {
+		items.iter().map(|item| item.to_string()).collect()
+	}
+
+	fn template(name: &str, front: &str, back: &str) -> CardTemplate {
+		CardTemplate { name: name.to_owned(), front: front.to_owned(), back: back.to_owned() }
+	}
+
+	fn vocabulary() -> NoteType {
+		NoteType {
+			id: 0,
+			name: "Vocabulary".to_owned(),
+			fields: strings(&["Word", "Meaning", "Example"]),
+			templates: vec![
+				template("Recognise", "{{Word}}", "{{Meaning}}"),
+				template("Recall", "{{Meaning}}", "{{Word}}"),
+				template("Use", "{{Example}}", "{{Word}}"),
+			],
+		}
+	}
+
+	//* (template position, question, interval) of each card of a note */
+	fn cards(conn: &Connection, note_id: i64) -> Vec<(i64, String, i32)> {
+		let mut stmt: rusqlite::Statement<'_> = conn.prepare(
+			"SELECT template_position, question, interval FROM cards WHERE note_id = ?1 ORDER BY template_position;"
+		).unwrap();
+		stmt.query_map(params![note_id], |row: &rusqlite::Row<'_>| Ok((row.get(0)?, row.get(1)?, row.get(2)?))).unwrap()
+			.collect::<rusqlite::Result<_>>().unwrap()
+	}
+
+	#[test]
+	fn renders_fields_by_name() {
+		let fields: Vec<String> = strings(&["Word", "Meaning"]);
+		let cases: [(&str, &[&str], &str, bool); 5] = [
+			("{{Word}}", &["chat", "cat"], "chat", true),
+			("{{ Meaning }} ({{Word}})", &["chat", "cat"], "cat (chat)", true),
+			("{{Word}}", &["", "cat"], "", false),
+			("{{Other}} {{c1::x}}", &["chat", "cat"], "{{Other}} {{c1::x}}", false),
+			("{{Meaning}}", &["chat"], "", false), // Missing values are blank
+		];
+		for (template, values, rendered, has_value) in cases {
+			assert_eq!(render(template, &fields, &strings(values)), (rendered.to_owned(), has_value), "{}", template);
+		}
+	}
+
+	#[test]
+	fn generates_cards_only_for_filled_fronts() {
+		let note_type: NoteType = vocabulary();
+		let generated: Vec<(usize, String, String)> = generate(&note_type, &strings(&["chat", "cat", ""]));
+		assert_eq!(generated, vec![(0, "chat".to_owned(), "cat".to_owned()), (1, "cat".to_owned(), "chat".to_owned())]);
+		assert_eq!(generate(&note_type, &strings(&["chat", "cat", "Le chat dort"])).len(), 3);
+	}
+
+	#[test]
+	fn validates_note_types() {
+		let mut too_many: NoteType = vocabulary();
+		too_many.fields = strings(&["A", "B", "C", "D", "E", "F"]);
+		let mut repeated: NoteType = vocabulary();
+		repeated.fields = strings(&["Word", "Word"]);
+		let mut blank_front: NoteType = vocabulary();
+		blank_front.templates.push(template("Empty", "Hello", "{{Word}}"));
+		let mut unnamed: NoteType = vocabulary();
+		unnamed.name = " ".to_owned();
+		let mut no_templates: NoteType = vocabulary();
+		no_templates.templates.clear();
+
+		assert_eq!(validate(&vocabulary()), Ok(()));
+		let cases: [(NoteType, &str); 5] = [
+			(unnamed, "Note types need a name."),
+			(too_many, "Note types need 1 to 5 fields."),
+			(repeated, "There are two fields called Word."),
+			(no_templates, "Note types need at least one card template."),
+			(blank_front, "The front of Empty doesn't use any fields."),
+		];
+		for (note_type, problem) in cases {
+			assert_eq!(validate(&note_type), Err(problem.to_owned()));
+		}
+	}
+
+	#[test]
+	fn parses_and_formats_templates() {
+		let templates: Vec<CardTemplate> = parse_templates("Recognise: {{Word}} -> {{Meaning}}\n\n{{Meaning}} -> {{Word}}").unwrap();
+		assert_eq!(templates, vec![template("Recognise", "{{Word}}", "{{Meaning}}"), template("Card 2", "{{Meaning}}", "{{Word}}")]);
+		assert_eq!(parse_templates(&format_templates(&templates)).unwrap(), templates);
+		assert!(parse_templates("{{Word}}").is_err());
+	}
+
+	#[test]
+	fn resaving_keeps_values_with_their_fields() {
+		let conn: Connection = database::open_database(":memory:").unwrap();
+		let subject_id: i64 = database::get_or_add_subject(&conn, "French").unwrap();
+		let mut note_type: NoteType = vocabulary();
+		note_type.id = save_note_type(&conn, &note_type).unwrap();
+		let (note_id, made): (i64, usize) = add_note(&conn, subject_id, note_type.id, &strings(&["chat", "cat", "Le chat dort"])).unwrap();
+		assert_eq!(made, 3);
+
+		// Removing a middle field
+		note_type.fields = strings(&["Word", "Example"]);
+		note_type.templates = vec![template("Recognise", "{{Word}}", "{{Example}}"), template("Use", "{{Example}}", "{{Word}}")];
+		save_note_type(&conn, &note_type).unwrap();
+		assert_eq!(load_note(&conn, note_id).unwrap().values, strings(&["chat", "Le chat dort"]));
+
+		// Renaming in place keeps the value
+		note_type.fields = strings(&["Word", "Sentence"]);
+		note_type.templates = vec![template("Recognise", "{{Word}}", "{{Sentence}}"), template("Use", "{{Sentence}}", "{{Word}}")];
+		save_note_type(&conn, &note_type).unwrap();
+		assert_eq!(load_note(&conn, note_id).unwrap().values, strings(&["chat", "Le chat dort"]));
+		assert_eq!(cards(&conn, note_id).iter().map(|card| card.1.as_str()).collect::<Vec<&str>>(), vec!["chat", "Le chat dort"]);
+	}
+
+	#[test]
+	fn resaving_keeps_cards_with_their_templates() {
+		let conn: Connection = database::open_database(":memory:").unwrap();
+		let subject_id: i64 = database::get_or_add_subject(&conn, "French").unwrap();
+		let mut note_type: NoteType = vocabulary();
+		note_type.id = save_note_type(&conn, &note_type).unwrap();
+		let (note_id, _): (i64, usize) = add_note(&conn, subject_id, note_type.id, &strings(&["chat", "cat", "Le chat dort"])).unwrap();
+		conn.execute("UPDATE cards SET interval = template_position + 10 WHERE note_id = ?1;", params![note_id]).unwrap();
+
+		// Removing the middle template moves the last one's card, schedule and all, into its place
+		note_type.templates.remove(1);
+		save_note_type(&conn, &note_type).unwrap();
+		assert_eq!(cards(&conn, note_id), vec![(0, "chat".to_owned(), 10), (1, "Le chat dort".to_owned(), 12)]);
+
+		// Reordering follows the templates' content
+		note_type.templates.reverse();
+		save_note_type(&conn, &note_type).unwrap();
+		assert_eq!(cards(&conn, note_id), vec![(0, "Le chat dort".to_owned(), 12), (1, "chat".to_owned(), 10)]);
+		assert_eq!(load_note_type(&conn, note_type.id).unwrap().templates, note_type.templates);
+	}
+
+	#[test]
+	fn failed_saves_change_nothing() {
+		let conn: Connection = database::open_database(":memory:").unwrap();
+		let subject_id: i64 = database::get_or_add_subject(&conn, "French").unwrap();
+		let mut note_type: NoteType = vocabulary();
+		note_type.id = save_note_type(&conn, &note_type).unwrap();
+		add_note(&conn, subject_id, note_type.id, &strings(&["chat", "cat", ""])).unwrap();
+
+		conn.execute_batch("CREATE TRIGGER no_new_cards BEFORE INSERT ON cards BEGIN SELECT RAISE(ABORT, 'full'); END;").unwrap();
+		let mut changed: NoteType = note_type.clone();
+		changed.templates.push(template("Spell", "{{Meaning}}?", "{{Word}}"));
+		assert!(save_note_type(&conn, &changed).is_err());
+		assert_eq!(load_note_type(&conn, note_type.id).unwrap(), note_type);
+	}
+}
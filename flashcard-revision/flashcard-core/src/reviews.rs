@@ -0,0 +1,89 @@
+use std::collections::HashMap;
+
+use rusqlite::{ // Handles SQLite database
+	params,
+	Connection,
+};
+
+use crate::scheduler::{Algorithm, Grade};
+
+// One answer to one flashcard, as stored in the reviews table
+#[derive(Clone, Debug)]
+pub struct Review {
+	pub card_id: i32,
+	pub reviewed_at: i64, // Seconds since epoch
+	pub grade: Grade,
+	pub response_ms: i64, // Time from the question being shown to the answer being given
+	pub algorithm: Algorithm, // Scheduler that rescheduled the card
+	pub interval_before: i32, // Days
+	pub interval_after: i32, // Days
+}
+
+//* Adds an answer to the review log */
+pub fn log_review(conn: &Connection, review: &Review) -> rusqlite::Result<()> {
+	conn.execute(
+		"INSERT INTO reviews (card_id, reviewed_at, grade, response_ms, scheduler, interval_before, interval_after)
+			VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7);",
+		params![
+			review.card_id, review.reviewed_at, review.grade as i32, review.response_ms,
+			review.algorithm.name(), review.interval_before, review.interval_after
+		],
+	)?;
+	Ok(())
+}
+
+//* Loads every review of every card, grouped by card and oldest first */
+pub fn load_reviews(conn: &Connection) -> rusqlite::Result<Vec<Review>> {
+	query_reviews(conn, "", params![])
+}
+
+//* Loads the reviews of one subject's cards, oldest first, keyed by card */
+pub fn load_subject_reviews(conn: &Connection, subject_id: i64) -> rusqlite::Result<HashMap<i32, Vec<Review>>> {
+	let mut by_card: HashMap<i32, Vec<Review>> = HashMap::new();
+	for review in query_reviews(conn, "WHERE card_id IN (SELECT id FROM cards WHERE subject_id = ?1)", params![subject_id])? {
+		by_card.entry(review.card_id).or_default().push(review);
+	}
+	Ok(by_card)
+}
+
+//* Counts the reviews of one subject's cards */
+pub fn count_subject_reviews(conn: &Connection, subject_id: i64) -> rusqlite::Result<usize> {
+	conn.query_row(
+		"SELECT COUNT(*) FROM reviews JOIN cards ON reviews.card_id = cards.id WHERE cards.subject_id = ?1;",
+		params![subject_id],
+		|row: &rusqlite::Row<'_>| row.get::<_, i64>(0),
+	).map(|count: i64| count as usize)
+}
+
+fn query_reviews(conn: &Connection, filter: &str, params: &[&dyn rusqlite::ToSql]) -> rusqlite::Result<Vec<Review>> {
+	let mut stmt: rusqlite::Statement<'_> = conn.prepare(&format!(
+		"SELECT card_id, reviewed_at, grade, response_ms, scheduler, interval_before, interval_after FROM reviews {}
+			ORDER BY card_id, reviewed_at, id;",
+		filter,
+	))?;
+	let reviews = stmt.query_map(params, |row: &rusqlite::Row<'_>| {
+		let grade: Option<Grade> = Grade::from_number(row.get(2)?);
+		let algorithm: String = row.get(4)?;
+		let review = |grade: Grade| -> rusqlite::Result<Review> {
+			Ok(Review {
+				card_id: row.get(0)?,
+				reviewed_at: row.get(1)?,
+				grade,
+				response_ms: row.get(3)?,
+				algorithm: Algorithm::from_name(&algorithm).unwrap_or(Algorithm::Sm2),
+				interval_before: row.get(5)?,
+				interval_after: row.get(6)?,
+			})
+		};
+		grade.map(review).transpose()
+	})?;
+
+	let mut loaded: Vec<Review> = Vec::new();
+	for review in reviews {
+		// Rows with an unknown grade can't be replayed, so they are left out
+		if let Some(review) = review? {
+			loaded.push(review);
+		}
+	}
+	Ok(loaded)
+}
@@ -0,0 +1,244 @@
+use chrono::Utc; // Handles review times
+
+use rand::Rng; // Handles random card order
+
+use rusqlite::Connection; // Handles SQLite database
+
+use crate::choices;
+use crate::database::Subject;
+use crate::flashcards::{self, Flashcard};
+use crate::matching::{self, AnswerMatch, MatchOptions};
+use crate::reviews::{self, Review};
+use crate::scheduler::{self, Grade, Schedule, Scheduler};
+
+//* Returns the index of a random flashcard from those not yet selected */
+pub fn get_random_flashcard(list_of_indexes: &[usize], length: usize) -> usize {
+	let mut rng: rand::prelude::ThreadRng = rand::thread_rng();
+	loop {
+		let rand_number: usize = rng.gen_range(0..length);
+		if !list_of_indexes.contains(&rand_number) {
+			return rand_number; // Flash card not done yet
+		}
+	}
+}
+
+/* **Explanation of burying siblings**
+Cards made from the same note (Both directions of a card, or the deletions of a cloze text)
+give each other away, so only one of them is revised per day:
+	- Cards whose sibling was already reviewed today are buried
+	- Of the siblings still due, only the first (Most overdue) is kept
+Buried cards stay due, so they come up in tomorrow's session.
+*/
+pub fn bury_siblings(due: Vec<Flashcard>, all: &[Flashcard], since: i64) -> Vec<Flashcard> {
+	let mut seen_groups: Vec<i64> = all.iter()
+		.filter(|flashcard| flashcard.schedule.last_review >= since)
+		.filter_map(|flashcard| flashcard.group_id)
+		.collect();
+
+	let mut kept: Vec<Flashcard> = Vec::new();
+	for flashcard in due {
+		match flashcard.group_id {
+			Some(group_id) if seen_groups.contains(&group_id) => {}, // Buried
+			Some(group_id) => {
+				seen_groups.push(group_id);
+				kept.push(flashcard);
+			},
+			None => kept.push(flashcard),
+		}
+	}
+	kept
+}
+
+/* **Explanation of a revision session**
+	- Flashcards due today are loaded from the cards table, with siblings buried
+	- Flashcards are randomly selected, without repeats, using `get_random_flashcard`
+	- Each answer bumps the card's correct/incorrect counter, is added to the review log and
+		reschedules the card with the subject's algorithm straight away
+	- Cards whose tier changes are noted so the results stage can show what moved up or down
+*/
+pub struct RevisionSession {
+	pub subject: Subject,
+	scheduler: Box<dyn Scheduler>, // Algorithm chosen for the subject
+	pub flashcards: Vec<Flashcard>,
+	answer_pool: Vec<String>, // Answers of the subject's cards, for multiple choice cards without distractors
+	cards_selected: Vec<usize>, // Indexes of flashcards already chosen to prevent repeats
+	current: Option<usize>, // Index of the flashcard currently being asked
+	pub cards_done: i32,
+	pub correct_total: i32,
+	pub moved_up: Vec<i32>, // Stores primary_key of cards that reached a higher tier
+	pub moved_down: Vec<i32>, // Stores primary_key of cards that dropped to a lower tier
+	pub missed: Vec<i32>, // Stores primary_key of every incorrect answer
+	tiers_practised: Vec<i32>, // Tiers the practised cards were in, for the subject's revision dates
+	accuracy_before: f64, // Subject accuracy when the session started
+}
+
+// Post revision breakdown shown on the results stage
+pub struct RevisionSummary {
+	pub subject: Subject,
+	pub cards_practised: i32,
+	pub correct_total: i32,
+	pub moved_up: Vec<String>, // Questions of the cards moving upwards
+	pub moved_down: Vec<String>, // Questions of the cards moving downwards
+	pub accuracy_before: f64,
+	pub accuracy_after: f64,
+	pub missed: Vec<Flashcard>,
+}
+
+impl RevisionSummary {
+	pub fn percent_accuracy(&self) -> f64 {
+		if self.cards_practised == 0 {
+			0.0
+		} else {
+			(self.correct_total as f64 / self.cards_practised as f64) * 100.0
+		}
+	}
+}
+
+impl RevisionSession {
+	//* Loads every flashcard of the subject due today, ready for revision */
+	pub fn new(conn: &Connection, subject: &Subject) -> rusqlite::Result<RevisionSession> {
+		let due: Vec<Flashcard> = flashcards::load_due_flashcards(conn, subject.id, scheduler::end_of_today())?;
+		let all: Vec<Flashcard> = flashcards::load_all_flashcards(conn, subject.id)?;
+		RevisionSession::with_flashcards(conn, subject, bury_siblings(due, &all, scheduler::start_of_today()))
+	}
+
+	//* Revises a chosen list of flashcards (e.g. those missed last session) */
+	pub fn with_flashcards(conn: &Connection, subject: &Subject, flashcards: Vec<Flashcard>) -> rusqlite::Result<RevisionSession> {
+		let accuracy_before: f64 = flashcards::get_subject_accuracy(conn, subject.id)?;
+
+		Ok(RevisionSession {
+			subject: subject.clone(),
+			scheduler: scheduler::load_scheduler(conn, subject.algorithm)?,
+			flashcards,
+			answer_pool: flashcards::load_answer_pool(conn, subject.id)?,
+			cards_selected: Vec::new(),
+			current: None,
+			cards_done: 0,
+			correct_total: 0,
+			moved_up: Vec::new(),
+			moved_down: Vec::new(),
+			missed: Vec::new(),
+			tiers_practised: Vec::new(),
+			accuracy_before,
+		})
+	}
+
+	//* Picks the next random flashcard, returning None once every card has been practised */
+	pub fn next_flashcard(&mut self) -> Option<&Flashcard> {
+		if self.cards_selected.len() >= self.flashcards.len() {
+			self.current = None;
+			return None;
+		}
+
+		let index_of_question: usize = get_random_flashcard(&self.cards_selected, self.flashcards.len());
+		self.cards_selected.push(index_of_question);
+		self.current = Some(index_of_question);
+		self.flashcards.get(index_of_question)
+	}
+
+	pub fn current_flashcard(&self) -> Option<&Flashcard> {
+		self.current.and_then(|index| self.flashcards.get(index))
+	}
+
+	//* Shuffled options for the current flashcard, or nothing if it is answered by typing */
+	pub fn options(&self, matching: &MatchOptions) -> Vec<String> {
+		match self.current_flashcard() {
+			Some(flashcard) if flashcard.multiple_choice => choices::build_options(flashcard, &self.answer_pool, matching),
+			_ => Vec::new(),
+		}
+	}
+
+	//* Checks the typed answer against every accepted answer of the current flashcard, returning the closest one */
+	pub fn check_answer(&self, input: &str, options: &MatchOptions) -> (AnswerMatch, String) {
+		match self.current_flashcard() {
+			Some(flashcard) => {
+				let answers: Vec<String> = flashcard.accepted_answers();
+				let (result, closest): (AnswerMatch, usize) = matching::check_answer(input, &answers, options);
+				(result, answers[closest].clone())
+			},
+			None => (AnswerMatch::Wrong, String::new()),
+		}
+	}
+
+	//* Logs and schedules the answer to the current flashcard, returning its new accuracy */
+	pub fn record_answer(&mut self, conn: &Connection, grade: Grade, response_ms: i64) -> rusqlite::Result<f64> {
+		let flashcard: &mut Flashcard = match self.current.and_then(|index| self.flashcards.get_mut(index)) {
+			Some(flashcard) => flashcard,
+			None => return Ok(0.0),
+		};
+		let primary_key: i32 = flashcard.primary_key;
+
+		let correct: bool = grade != Grade::Again; // Hard still counts as remembered
+		let now: i64 = Utc::now().timestamp();
+		let tier_before: i32 = flashcard.category();
+		let interval_before: i32 = flashcard.schedule.interval;
+		let schedule: Schedule = self.scheduler.review(&flashcard.schedule, grade, now);
+
+		// One transaction, so the counters, schedule and review log (Which FSRS is fitted to) can't disagree
+		let transaction: rusqlite::Transaction<'_> = conn.unchecked_transaction()?;
+		flashcards::record_answer(&transaction, primary_key, correct)?;
+		flashcards::set_schedule(&transaction, primary_key, &schedule)?;
+		reviews::log_review(&transaction, &Review {
+			card_id: primary_key,
+			reviewed_at: now,
+			grade,
+			response_ms,
+			algorithm: self.subject.algorithm,
+			interval_before,
+			interval_after: schedule.interval,
+		})?;
+		transaction.commit()?;
+		flashcard.schedule = schedule; // Only once it has been stored
+		let tier_after: i32 = flashcard.category();
+
+		if tier_after > tier_before {
+			self.moved_up.push(primary_key);
+		} else if tier_after < tier_before {
+			self.moved_down.push(primary_key);
+		}
+		if !self.tiers_practised.contains(&tier_before) {
+			self.tiers_practised.push(tier_before);
+		}
+
+		if correct {
+			self.correct_total += 1;
+		} else {
+			self.missed.push(primary_key);
+		}
+
+		self.cards_done += 1;
+		flashcards::get_accuracy(conn, primary_key)
+	}
+
+	//* Stores the revision date of every tier practised */
+	pub fn finish(&mut self, conn: &Connection) -> rusqlite::Result<()> {
+		for &tier in self.tiers_practised.iter() {
+			flashcards::update_revision_date(conn, self.subject.id, tier)?;
+		}
+		Ok(())
+	}
+
+	//* Builds the post revision breakdown. Call after `finish` */
+	pub fn summary(&self, conn: &Connection) -> rusqlite::Result<RevisionSummary> {
+		Ok(RevisionSummary {
+			subject: self.subject.clone(),
+			cards_practised: self.cards_done,
+			correct_total: self.correct_total,
+			moved_up: self.questions_of(&self.moved_up),
+			moved_down: self.questions_of(&self.moved_down),
+			accuracy_before: self.accuracy_before,
+			accuracy_after: flashcards::get_subject_accuracy(conn, self.subject.id)?,
+			missed: self.flashcards.iter()
+				.filter(|flashcard| self.missed.contains(&flashcard.primary_key))
+				.cloned()
+				.collect(),
+		})
+	}
+
+	fn questions_of(&self, primary_keys: &[i32]) -> Vec<String> {
+		primary_keys.iter()
+			.filter_map(|primary_key| self.flashcards.iter().find(|flashcard| flashcard.primary_key == *primary_key))
+			.map(|flashcard| flashcard.display_question())
+			.collect()
+	}
+}
@@ -0,0 +1,267 @@
+use chrono::{Duration, Local, NaiveTime, TimeZone}; // Handles due dates
+
+use rusqlite::Connection; // Handles SQLite database
+
+use crate::fsrs::{self, Fsrs};
+
+pub const SECONDS_PER_DAY: i64 = 86_400;
+
+// Shortest intervals (In days) of the learning and strong tiers
+const LEARNING_INTERVAL: i32 = 2;
+const STRONG_INTERVAL: i32 = 21;
+
+// How well a card was remembered, as stored in the review log
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Grade {
+	Again = 1, // Forgotten
+	Hard = 2,
+	Good = 3,
+	Easy = 4,
+}
+
+impl Grade {
+	pub const ALL: [Grade; 4] = [Grade::Again, Grade::Hard, Grade::Good, Grade::Easy];
+
+	pub fn name(&self) -> &'static str {
+		match self {
+			Grade::Again => "Again",
+			Grade::Hard => "Hard",
+			Grade::Good => "Good",
+			Grade::Easy => "Easy",
+		}
+	}
+
+	pub fn from_number(number: i32) -> Option<Grade> {
+		match number {
+			1 => Some(Grade::Again),
+			2 => Some(Grade::Hard),
+			3 => Some(Grade::Good),
+			4 => Some(Grade::Easy),
+			_ => None,
+		}
+	}
+}
+
+// Every scheduling field stored for a card. Each algorithm only uses some of them
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Schedule {
+	pub ease_factor: f64, // SM-2
+	pub interval: i32, // Days until the card is next due
+	pub repetitions: i32, // Correct answers in a row
+	pub due: i64, // Seconds since epoch (0 = new card, due straight away)
+	pub stability: f64, // FSRS, days for recall to drop to 90% (0 = not yet scheduled by FSRS)
+	pub difficulty: f64, // FSRS, 1 to 10
+	pub last_review: i64, // Seconds since epoch (0 = never reviewed)
+}
+
+impl Default for Schedule {
+	fn default() -> Schedule {
+		Schedule {
+			ease_factor: 2.5,
+			interval: 0,
+			repetitions: 0,
+			due: 0,
+			stability: 0.0,
+			difficulty: 0.0,
+			last_review: 0,
+		}
+	}
+}
+
+impl Schedule {
+	//* Tier shown for the card, worked out from its interval. 0 = weak, 1 = learning, 2 = strong */
+	pub fn tier(&self) -> i32 {
+		if self.interval >= STRONG_INTERVAL {
+			2
+		} else if self.interval >= LEARNING_INTERVAL {
+			1
+		} else {
+			0
+		}
+	}
+}
+
+// A spaced repetition algorithm. Tiers are worked out from the interval, so they work with any of them
+pub trait Scheduler {
+	//* Returns the schedule after answering with the given grade at `now` (Seconds since epoch) */
+	fn review(&self, schedule: &Schedule, grade: Grade, now: i64) -> Schedule;
+}
+
+/* **Explanation of SM-2**
+	- Every card has an ease factor (Starting at 2.5), an interval in days and a count of
+		correct answers in a row (repetitions)
+	- Grades are turned into a quality from 0 to 5 (Again = 1, Hard = 3, Good = 4, Easy = 5)
+	- A quality of 3 or more is a correct answer. The first correct answer is due again in
+		1 day, the second in 6 days, and every one after that in interval * ease factor days
+	- A quality under 3 starts the repetitions again (Due tomorrow) without changing the ease factor
+	- The ease factor moves by 0.1 - (5 - q) * (0.08 + (5 - q) * 0.02), never going below 1.3
+*/
+pub struct Sm2;
+
+impl Scheduler for Sm2 {
+	fn review(&self, schedule: &Schedule, grade: Grade, now: i64) -> Schedule {
+		let quality: u8 = match grade {
+			Grade::Again => 1,
+			Grade::Hard => 3,
+			Grade::Good => 4,
+			Grade::Easy => 5,
+		};
+		let mut next: Schedule = *schedule;
+
+		if quality >= 3 {
+			next.interval = match schedule.repetitions {
+				0 => 1,
+				1 => 6,
+				_ => (schedule.interval as f64 * schedule.ease_factor).round() as i32,
+			};
+			next.repetitions += 1;
+
+			let lapse: f64 = (5 - quality) as f64;
+			next.ease_factor = (schedule.ease_factor + 0.1 - lapse * (0.08 + lapse * 0.02)).max(1.3);
+		} else {
+			next.interval = 1;
+			next.repetitions = 0;
+		}
+
+		next.due = now + next.interval as i64 * SECONDS_PER_DAY;
+		next.last_review = now;
+		next
+	}
+}
+
+// Algorithm chosen for a subject (Stored by name in the subjects table)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Algorithm {
+	Sm2,
+	Fsrs,
+}
+
+impl Algorithm {
+	pub fn name(&self) -> &'static str {
+		match self {
+			Algorithm::Sm2 => "sm2",
+			Algorithm::Fsrs => "fsrs",
+		}
+	}
+
+	pub fn from_name(name: &str) -> Option<Algorithm> {
+		match name {
+			"sm2" => Some(Algorithm::Sm2),
+			"fsrs" => Some(Algorithm::Fsrs),
+			_ => None,
+		}
+	}
+
+	//* Name shown on screen */
+	pub fn display_name(&self) -> &'static str {
+		match self {
+			Algorithm::Sm2 => "SM-2",
+			Algorithm::Fsrs => "FSRS",
+		}
+	}
+
+	pub fn toggled(&self) -> Algorithm {
+		match self {
+			Algorithm::Sm2 => Algorithm::Fsrs,
+			Algorithm::Fsrs => Algorithm::Sm2,
+		}
+	}
+}
+
+//* Builds the scheduler for an algorithm, with FSRS using the user's optimised weights */
+pub fn load_scheduler(conn: &Connection, algorithm: Algorithm) -> rusqlite::Result<Box<dyn Scheduler>> {
+	match algorithm {
+		Algorithm::Sm2 => Ok(Box::new(Sm2)),
+		Algorithm::Fsrs => Ok(Box::new(Fsrs::new(fsrs::load_weights(conn)?))),
+	}
+}
+
+//* Returns the display name of a tier */
+pub fn tier_name(tier: i32) -> &'static str {
+	match tier {
+		0 => "Weak",
+		1 => "Learning",
+		_ => "Strong",
+	}
+}
+
+//* Seconds since epoch at the end of today (Local time), so anything due before it is due today */
+pub fn end_of_today() -> i64 {
+	let tomorrow = Local::now().date_naive() + Duration::days(1);
+	match Local.from_local_datetime(&tomorrow.and_time(NaiveTime::MIN)).earliest() {
+		Some(midnight) => midnight.timestamp(),
+		None => Local::now().timestamp() + SECONDS_PER_DAY, // Midnight skipped by a clock change
+	}
+}
+
+//* Seconds since epoch at the start of today (Local time), so anything reviewed after it was reviewed today */
+pub fn start_of_today() -> i64 {
+	match Local.from_local_datetime(&Local::now().date_naive().and_time(NaiveTime::MIN)).earliest() {
+		Some(midnight) => midnight.timestamp(),
+		None => end_of_today() - SECONDS_PER_DAY, // Midnight skipped by a clock change
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const NOW: i64 = 1_700_000_000;
+
+	//* Reviews a new card with each grade in turn, returning every schedule */
+	fn review_all(grades: &[Grade]) -> Vec<Schedule> {
+		let mut schedule: Schedule = Schedule::default();
+		grades.iter().map(|grade| {
+			schedule = Sm2.review(&schedule, *grade, NOW);
+			schedule
+		}).collect()
+	}
+
+	#[test]
+	fn correct_answers_go_1_then_6_then_interval_times_ease() {
+		let schedules: Vec<Schedule> = review_all(&[Grade::Good, Grade::Good, Grade::Good, Grade::Good]);
+		let intervals: Vec<i32> = schedules.iter().map(|schedule| schedule.interval).collect();
+		assert_eq!(intervals, vec![1, 6, 15, 38]); // 6 * 2.5, then 15 * 2.5 rounded
+		assert!(schedules.iter().all(|schedule| (schedule.ease_factor - 2.5).abs() < 1e-9)); // Good leaves the ease factor alone
+		assert_eq!(schedules[3].repetitions, 4);
+		assert_eq!((schedules[3].due, schedules[3].last_review), (NOW + 38 * SECONDS_PER_DAY, NOW));
+	}
+
+	#[test]
+	fn grades_move_the_ease_factor() {
+		let cases: [(Grade, f64); 3] = [(Grade::Easy, 2.6), (Grade::Good, 2.5), (Grade::Hard, 2.36)];
+		for (grade, ease_factor) in cases {
+			let next: Schedule = Sm2.review(&Schedule::default(), grade, NOW);
+			assert!((next.ease_factor - ease_factor).abs() < 1e-9, "{:?} gave {}", grade, next.ease_factor);
+		}
+	}
+
+	#[test]
+	fn ease_factor_never_drops_below_1_3() {
+		let schedules: Vec<Schedule> = review_all(&[Grade::Hard; 12]);
+		assert!(schedules.iter().all(|schedule| schedule.ease_factor >= 1.3));
+		assert!((schedules[11].ease_factor - 1.3).abs() < 1e-9);
+		let floored: Schedule = Sm2.review(&Schedule { ease_factor: 1.35, ..Schedule::default() }, Grade::Hard, NOW);
+		assert!((floored.ease_factor - 1.3).abs() < 1e-9);
+	}
+
+	#[test]
+	fn lapses_reset_repetitions_but_not_ease() {
+		let learnt: Schedule = Schedule { ease_factor: 2.2, interval: 15, repetitions: 3, ..Schedule::default() };
+		let lapsed: Schedule = Sm2.review(&learnt, Grade::Again, NOW);
+		assert_eq!((lapsed.interval, lapsed.repetitions, lapsed.due), (1, 0, NOW + SECONDS_PER_DAY));
+		assert!((lapsed.ease_factor - 2.2).abs() < 1e-9);
+
+		// Relearning starts again from 1 and 6 days
+		let relearnt: Schedule = Sm2.review(&Sm2.review(&lapsed, Grade::Good, NOW), Grade::Good, NOW);
+		assert_eq!(relearnt.interval, 6);
+	}
+
+	#[test]
+	fn tiers_change_at_2_and_21_days() {
+		let cases: [(i32, i32); 6] = [(0, 0), (1, 0), (2, 1), (20, 1), (21, 2), (365, 2)];
+		for (interval, tier) in cases {
+			assert_eq!(Schedule { interval, ..Schedule::default() }.tier(), tier, "{} days", interval);
+		}
+	}
+}
@@ -0,0 +1,187 @@
+use std::fs; // Handles reading and writing files
+
+use toml::Table; // Handles TOML files for configuration and preferences
+
+use crate::matching::MatchOptions;
+
+pub const SETTINGS_PATH: &str = "./src/settings.toml";
+
+// Every user setting stored in settings.toml (Defaults are listed in settings.md)
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Settings {
+	pub fullscreen: bool,
+	pub number_of_subjects: u16, // "If anyone needs more than 65,535 subjects, they... have a problem" - Copilot
+	pub matching: MatchOptions, // How forgiving answer checking is
+}
+
+impl Settings {
+	/* Reads settings from a parsed settings.toml. Missing or invalid values fall
+	back to their defaults and are reported so that the user can be told. */
+	pub fn from_table(table: &Table) -> (Settings, Vec<String>) {
+		let mut settings: Settings = Settings::default();
+		let mut problems: Vec<String> = Vec::new();
+
+		match table.get("fullscreen").map(|value| value.as_bool()) {
+			Some(Some(fullscreen)) => settings.fullscreen = fullscreen,
+			Some(None) => problems.push("Fullscreen setting is not a boolean".to_owned()),
+			None => problems.push("Fullscreen setting is missing".to_owned()),
+		}
+
+		match table.get("number_of_subjects").map(|value| value.as_integer()) {
+			Some(Some(number)) => match validate_number_of_subjects(&number.to_string()) {
+				Ok(number) => settings.number_of_subjects = number,
+				Err(e) => problems.push(e),
+			},
+			Some(None) => problems.push("Subject number setting is not an integer".to_owned()),
+			None => problems.push("Subject number setting is missing".to_owned()),
+		}
+
+		// Answer checking was added later, so missing values quietly use their defaults
+		let matching: [(&str, &mut bool); 4] = [
+			("ignore_accents", &mut settings.matching.ignore_accents),
+			("ignore_punctuation", &mut settings.matching.ignore_punctuation),
+			("ignore_articles", &mut settings.matching.ignore_articles),
+			("typo_tolerance", &mut settings.matching.typo_tolerance),
+		];
+		for (key, value) in matching {
+			match table.get(key).map(|setting| setting.as_bool()) {
+				Some(Some(setting)) => *value = setting,
+				Some(None) => problems.push(format!("{} setting is not a boolean", key)),
+				None => {},
+			}
+		}
+
+		(settings, problems)
+	}
+
+	pub fn to_table(&self) -> Table {
+		let mut table: Table = Table::new();
+		table.insert("fullscreen".to_owned(), toml::Value::Boolean(self.fullscreen));
+		table.insert("number_of_subjects".to_owned(), toml::Value::Integer(self.number_of_subjects as i64));
+		table.insert("ignore_accents".to_owned(), toml::Value::Boolean(self.matching.ignore_accents));
+		table.insert("ignore_punctuation".to_owned(), toml::Value::Boolean(self.matching.ignore_punctuation));
+		table.insert("ignore_articles".to_owned(), toml::Value::Boolean(self.matching.ignore_articles));
+		table.insert("typo_tolerance".to_owned(), toml::Value::Boolean(self.matching.typo_tolerance));
+		table
+	}
+}
+
+//* Checks a typed number of subjects, returning it if valid */
+pub fn validate_number_of_subjects(input: &str) -> Result<u16, String> {
+	let input: &str = input.trim();
+	if input.is_empty() {
+		return Err("Number of subjects can't be empty".to_owned());
+	}
+	if !input.chars().all(|character| character.is_ascii_digit()) {
+		return Err("Number of subjects must be a whole number".to_owned());
+	}
+	input.parse::<u16>().map_err(|_| "Number of subjects must be 65,535 or less".to_owned())
+}
+
+pub fn save_settings(settings: Table) {
+	// Write settings to file
+	fs::write(SETTINGS_PATH,
+	toml::to_string(&settings)
+	.expect("Cannot convert settings to string")
+	.as_bytes())
+	.expect("Cannot write settings to settings.toml");
+}
+
+//* Create or read settings file, returning the settings and any problems found while reading them */
+pub fn load_settings() -> (Settings, Vec<String>) {
+	if !fs::exists(SETTINGS_PATH).expect("Cannot verify existence of settings.toml") {
+		// Settings file does not exist :(
+		let settings: Settings = Settings::default();
+		save_settings(settings.to_table());
+		return (settings, Vec::new());
+	}
+
+	// Settings file exists :)
+	read_settings()
+}
+
+//* Read the settings file without making one, for frontends that don't own it. Defaults are used if there isn't one */
+pub fn read_settings() -> (Settings, Vec<String>) {
+	let contents: String = match fs::read_to_string(SETTINGS_PATH) {
+		Ok(contents) => contents,
+		Err(e) if e.kind() == std::io::ErrorKind::NotFound => return (Settings::default(), Vec::new()),
+		Err(e) => return (Settings::default(), vec![format!("Cannot read settings.toml: {}", e)]),
+	};
+	match toml::from_str::<Table>(&contents) {
+		Ok(table) => Settings::from_table(&table),
+		Err(e) => (Settings::default(), vec![format!("Cannot parse settings.toml: {}", e.message())]),
+	}
+}
+
+//* Lists the settings that need the user's attention (Empty if none do) */
+pub fn check_settings(settings: &Settings, subject_count: usize) -> Vec<String> {
+	let mut warnings: Vec<String> = Vec::new();
+	if settings.number_of_subjects as usize != subject_count {
+		warnings.push(format!(
+			"Number of subjects ({}) doesn't match the {} in the database",
+			settings.number_of_subjects, subject_count
+		));
+	}
+	warnings
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn table(text: &str) -> Table {
+		toml::from_str(text).unwrap()
+	}
+
+	#[test]
+	fn reads_every_setting() {
+		let (settings, problems): (Settings, Vec<String>) = Settings::from_table(&table("
+			fullscreen = true
+			number_of_subjects = 4
+			ignore_accents = true
+			typo_tolerance = true
+		"));
+		assert!(problems.is_empty());
+		assert!(settings.fullscreen);
+		assert_eq!(settings.number_of_subjects, 4);
+		assert!(settings.matching.ignore_accents && settings.matching.typo_tolerance);
+		assert_eq!(Settings::from_table(&settings.to_table()), (settings, Vec::new()));
+	}
+
+	#[test]
+	fn reports_missing_and_invalid_settings() {
+		let (settings, problems): (Settings, Vec<String>) = Settings::from_table(&table("ignore_articles = 1"));
+		assert_eq!(settings, Settings::default());
+		assert_eq!(problems, vec![
+			"Fullscreen setting is missing".to_owned(),
+			"Subject number setting is missing".to_owned(),
+			"ignore_articles setting is not a boolean".to_owned(),
+		]);
+
+		let (settings, problems): (Settings, Vec<String>) = Settings::from_table(&table("fullscreen = \"yes\"\nnumber_of_subjects = 70000"));
+		assert!(!settings.fullscreen);
+		assert_eq!(settings.number_of_subjects, 0);
+		assert_eq!(problems, vec!["Fullscreen setting is not a boolean".to_owned(), "Number of subjects must be 65,535 or less".to_owned()]);
+
+		let (_, problems): (Settings, Vec<String>) = Settings::from_table(&table("fullscreen = false\nnumber_of_subjects = 1.5"));
+		assert_eq!(problems, vec!["Subject number setting is not an integer".to_owned()]);
+	}
+
+	#[test]
+	fn validates_number_of_subjects() {
+		assert_eq!(validate_number_of_subjects(" 12 "), Ok(12));
+		assert_eq!(validate_number_of_subjects("0"), Ok(0));
+		assert_eq!(validate_number_of_subjects("65535"), Ok(65535));
+		assert_eq!(validate_number_of_subjects("65536"), Err("Number of subjects must be 65,535 or less".to_owned()));
+		assert_eq!(validate_number_of_subjects(""), Err("Number of subjects can't be empty".to_owned()));
+		assert_eq!(validate_number_of_subjects("-3"), Err("Number of subjects must be a whole number".to_owned()));
+		assert_eq!(validate_number_of_subjects("3.5"), Err("Number of subjects must be a whole number".to_owned()));
+	}
+
+	#[test]
+	fn checks_the_number_of_subjects() {
+		let settings: Settings = Settings { number_of_subjects: 2, ..Settings::default() };
+		assert!(check_settings(&settings, 2).is_empty());
+		assert_eq!(check_settings(&settings, 3).len(), 1);
+	}
+}
@@ -1,15 +1,31 @@
-use std::{fs, time::SystemTime}; // Handles reading and writing files
+use std::time::SystemTime; // Handles load timing
 
 use macroquad::prelude::*; // Handles window display
 
 use miniquad::window::dpi_scale;
-use rusqlite::{ // Handles SQLite database
-	params,
-	Connection,
+use rusqlite::Connection; // Handles SQLite database
+
+use flashcard_core::database::{self, Subject};
+use flashcard_core::deck::{self, Deck};
+use flashcard_core::settings::{self, check_settings, Settings};
+
+mod stages;
+mod widgets;
+
+use stages::{
+	edit::EditScreen,
+	editor::EditorScreen,
+	import_export::ImportExportScreen,
+	note_types::NoteTypesScreen,
+	paste::PasteScreen,
+	results::ResultsScreen,
+	revision::RevisionScreen,
+	settings::SettingsScreen,
+	StageAssets,
 };
+use widgets::{canvas_rect, draw_stage_element};
 
-use toml::Table; // Handles TOML files for configuration and preferences
-
+#[allow(dead_code)] // up, add and down are read once those buttons get pressed textures
 struct States {
 	up: bool,
 	add: bool,
@@ -71,27 +87,6 @@ async fn loading_screen() {
 	next_frame().await;
 }
 
-fn get_centre(font: Font, font_size: u16, text: &str) -> Vec2 {
-	let centre = get_text_center(
-		text,
-		Some(&font),
-		font_size,
-		1.0,
-		0.0);
-
-	return centre;
-}
-
-fn get_length(text: &str, font_size: u16, font: &Font) -> TextDimensions {
-	let dimensions: TextDimensions = measure_text(
-		text,
-		Some(font),
-		font_size,
-		1.0);
-
-	return dimensions;
-}
-
 async fn load_stage_element(file_name: &str) -> Texture2D {
 	let path: String = format!("./src/assets/images/stage_elements/{}", file_name.to_string().trim());
 	info!("Loading {0} from path: {1}", file_name, path.as_str());
@@ -101,7 +96,7 @@ async fn load_stage_element(file_name: &str) -> Texture2D {
 		Ok(texture) => Ok(texture),
 		Err(e) => {
 			error!("Failed to load texture from path: {}. Error: {:?}", path, e);
-			Err(format!("Load fallback texture")) // No semicolon *important*
+			Err("Load fallback texture".to_owned()) // No semicolon *important*
 		}
 	};
 
@@ -111,21 +106,21 @@ async fn load_stage_element(file_name: &str) -> Texture2D {
 
 	if result == Err("Load fallback texture".to_owned()) {
 		info!("Attempting to load fallback texture");
-		let recovery_path: String = format!("./src/assets/images/stage_elements/failed_to_load.png");
+		let recovery_path: String = "./src/assets/images/stage_elements/failed_to_load.png".to_owned();
 		// Hours spent trying to work out why path wasn't working without .png: 3
 		info!("CRASH PREVENTION: Loading {0} from path: {1}", file_name, recovery_path.as_str());
 		result = match load_texture(&recovery_path).await {
 			Ok(texture) => Ok(texture),
 			Err(_e) => {
 				error!("Irrecoverable!!!");
-				Err(format!("Don't delete textures."))
+				Err("Don't delete textures.".to_owned())
 			}
 		};
 	};
 
 	result_ok = result.unwrap();
 	result_ok.set_filter(FilterMode::Linear);
-	return result_ok
+	result_ok
 }
 
 async fn load_icon_element(file_name: &str) -> Texture2D {
@@ -137,7 +132,7 @@ async fn load_icon_element(file_name: &str) -> Texture2D {
 		Ok(texture) => Ok(texture),
 		Err(e) => {
 			error!("Failed to load texture from path: {}. Error: {:?}", path, e);
-			Err(format!("Load fallback texture")) // No semicolon *important*
+			Err("Load fallback texture".to_owned()) // No semicolon *important*
 		}
 	};
 
@@ -147,63 +142,61 @@ async fn load_icon_element(file_name: &str) -> Texture2D {
 
 	if result == Err("Load fallback texture".to_owned()) {
 		info!("Attempting to load fallback texture");
-		let recovery_path: String = format!("./src/assets/images/stage_elements/failed_to_load.png");
+		let recovery_path: String = "./src/assets/images/stage_elements/failed_to_load.png".to_owned();
 		// Hours spent trying to work out why path wasn't working without .png: 3
 		info!("CRASH PREVENTION: Loading {0} from path: {1}", file_name, recovery_path.as_str());
 		result = match load_texture(&recovery_path).await {
 			Ok(texture) => Ok(texture),
 			Err(_e) => {
 				error!("Irrecoverable!!!");
-				Err(format!("Don't delete textures."))
+				Err("Don't delete textures.".to_owned())
 			}
 		};
 	};
 
 	result_ok = result.unwrap();
 	result_ok.set_filter(FilterMode::Linear);
-	return result_ok
-}
-
-fn save_settings(settings: Table) {
-	// Write settings to file
-	fs::write("./src/settings.toml",
-	toml::to_string(&settings)
-	.expect("Cannot convert settings to string")
-	.as_bytes())
-	.expect("Cannot write settings to settings.toml");
+	result_ok
 }
 
-fn get_subject_names(conn: Connection) -> Vec<String> {
-	let mut stmt: rusqlite::Statement<'_> = conn.prepare("SELECT name FROM subjects;").unwrap();
-	return stmt.query_map(params![], |row: &rusqlite::Row<'_>| {
-		Ok(row.get::<_, String>(0)?)
-	}).unwrap().map(|subject| subject.unwrap()).collect();
-}
+// Shows the messages logged by flashcard_core (Database upgrades, subjects added...) like the window's own
+struct CoreLogger;
 
-fn subject_exists(subject_number: u16, page: i32, subjects_per_page: i32, subjects: &Vec<String>) -> bool {
-	if subject_number + (page as u16 * subjects_per_page as u16) <= subjects.len() as u16 {
-		// Subject exists as it is less than or equal to the length of the subject list
+impl log::Log for CoreLogger {
+	fn enabled(&self, _metadata: &log::Metadata<'_>) -> bool {
 		true
-	} else {
-		// Subject does not exist :(
-		false
 	}
+
+	fn log(&self, record: &log::Record<'_>) {
+		match record.level() {
+			log::Level::Error => error!("{}", record.args()),
+			log::Level::Warn => warn!("{}", record.args()),
+			log::Level::Info => info!("{}", record.args()),
+			log::Level::Debug | log::Level::Trace => debug!("{}", record.args()),
+		}
+	}
+
+	fn flush(&self) {}
 }
 
+static CORE_LOGGER: CoreLogger = CoreLogger;
+
 #[macroquad::main(conf)]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
 	// ## User settings ##
 	// Settings variables
-	let settings: Table;
-	let mut fullscreen: bool;
-	let mut num_of_subjects: u16 = 0; // "If anyone needs more than 65,535 subjects, they... have a problem" - Copilot
-	// ^^ Needs a default value to prevent uninitialized variable error ^^
+	let mut settings: Settings;
+	let mut settings_problems: Vec<String>; // Invalid values found in settings.toml
+
+	if let Err(e) = log::set_logger(&CORE_LOGGER).map(|()| log::set_max_level(log::LevelFilter::Info)) {
+		error!("Failed to show database messages. Error: {:?}", e);
+	}
 	
 	info!("Miniquad DPI: {}", dpi_scale());
 	info!("Macroquad DPI: {}", screen_dpi_scale());
 
 	// Struct to control whether buttons need to be gray or purple
-	let states = States {
+	let mut states = States {
 		up: false,
 		down: false,
 		add: false,
@@ -211,47 +204,18 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 	};
 
 	// Application variables
-	let mut text: &str;
 	let mut texture_chosen: &Texture2D;
 	let mut width: f32;
 	let mut height: f32;
-	let mut loading: bool = true;
 
 	// Create or read settings file
-	if !fs::exists("./src/settings.toml").expect("Cannot verify existence of settings.toml") {
-		// Settings file does not exist :(
-		info!("Creating settings file...");
-		// Create settings file
-		settings = toml::toml! {
-			fullscreen = false
-			number_of_subjects = 0
-		};
-
-		// Set settings
-		fullscreen = settings.get("fullscreen").expect("Cannot retrieve fullscreen setting from settings.toml")
-		.as_bool()
-		.expect("Fullscreen setting is not a boolean");
-
-		// Save settings to file
-		save_settings(settings);
-	} else {
-		// Settings file exists :)
-		info!("Loading settings.toml...");
-		// Load settings file
-		settings = toml::from_str(fs::read_to_string("./src/settings.toml").expect("Cannot read settings.toml").as_str()).expect("Cannot parse settings.toml");
-		
-		// Retrieve settings from db
-		fullscreen = settings.get("fullscreen").expect("Cannot retrieve fullscreen setting from settings.toml")
-		.as_bool()
-		.expect("Fullscreen setting is not a boolean");
-
-		num_of_subjects = settings.get("number_of_subjects").expect("Cannot retrieve number_of_subjects setting from settings.toml")
-		.as_integer()
-		.expect("Subject number setting is not an integer")
-		as u16; //* Remember to change if number of subjects needs updating */
+	info!("Loading settings.toml...");
+	(settings, settings_problems) = settings::load_settings();
+	for problem in settings_problems.iter() {
+		error!("{}. Using default value.", problem);
 	}
 
-	if fullscreen == true {
+	if settings.fullscreen {
 		set_fullscreen(true);
 	}
 
@@ -267,6 +231,12 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 	// Stages
 	let stage0_no_blank: Texture2D = load_stage_element("stage0_no_blank.png").await;
 	let stage0_arrows_blank: Texture2D = load_stage_element("stage0_arrows_blank.png").await;
+	let header: Texture2D = load_stage_element("header.png").await;
+	let flashcard_box: Texture2D = load_stage_element("flashcard_box.png").await;
+
+	// Buttons
+	let settings_button: Texture2D = load_stage_element("settings_button.png").await;
+	let settings_button_pressed: Texture2D = load_stage_element("settings_button_pressed.png").await;
 
 	// Icons
 	let settings_notification: Texture2D = load_icon_element("settings_notification.png").await;
@@ -277,39 +247,44 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 	// Font
 	let open_sans_reg: Font = load_ttf_font("./src/assets/fonts/OpenSans-Regular.ttf").await.unwrap();
 
+	let stage_assets = StageAssets {
+		font: open_sans_reg.clone(),
+		header,
+		flashcard_box,
+		settings_notification,
+	};
+
 	// ## SQLite database ##
-	let conn: Connection = Connection::open("flashcards.db")?; // Creates/opens database
-
-	// Storage of tables
-	let _ = conn.execute(
-		"CREATE TABLE IF NOT EXISTS subjects (
-			id INTEGER PRIMARY KEY,
-			name TEXT NOT NULL,
-			date_weak_revised INTEGER NOT NULL,
-			date_learning_revised INTEGER NOT NULL,
-			date_strong_revised INTEGER NOT NULL
-		);", // Stores dates as seconds since epoch
-		params![],
-	);
+	let deck: Deck = Deck::open(database::DATABASE_PATH)?; // Creates/opens database and its tables
+	let conn: &Connection = deck.conn(); // For the stages
 
 	// ## Window settings ##
 	// Subject settings
-	let subjects: Vec<String> = get_subject_names(conn);
+	let mut subjects: Vec<Subject> = deck.subjects()?;
 	// ^^ This will need updating when the database is updated later in the program ^^
 	let page: i32 = 0; // This allows for one page per subject so should not be too small
 	let subjects_per_page: i32 = 6;
-	let mut creating_subject: bool = false;
+	let creating_subject: bool = false;
 
 	/* Stage settings
 	0 = Subject selection/Settings, 1 = Changing settings,
 	2 = Revision, 3 = Results, 4 = Add/Remove flashcards,
-	5 = Edit flashcards */
+	5 = Edit flashcards, 6 = Note types, 7 = Import/Export,
+	8 = Paste flashcards */
 	let mut stage: u8 = 0;
+	let mut revision_screen: Option<RevisionScreen> = None; // Set when a subject is chosen
+	let mut results_screen: Option<ResultsScreen> = None; // Set when a revision session finishes
+	let mut editor_screen: Option<EditorScreen> = None; // Set when adding/removing flashcards
+	let mut edit_screen: Option<EditScreen> = None; // Set when editing flashcards
+	let mut settings_screen: Option<SettingsScreen> = None; // Set when the settings button is pressed
+	let mut note_types_screen: Option<NoteTypesScreen> = None; // Set when editing note types from stage 4
+	let mut import_export_screen: Option<ImportExportScreen> = None; // Set when importing/exporting from stage 4
+	let mut paste_screen: Option<PasteScreen> = None; // Set when pasting flashcards from stage 7
 	
 	// General colours
 	let background_colour: Color = Color::from_rgba(0, 0, 0, 255); //rgb(0, 0, 0)
 	let text_colour: Color = Color::from_rgba(0, 0, 0, 255); //rgb(222, 222, 222)
-	let bounding_box: Color = Color::from_rgba(0, 80, 27, 255);    //rgb(0, 80, 27)
+	let _bounding_box: Color = Color::from_rgba(0, 80, 27, 255);    //rgb(0, 80, 27)
 	// ^^ Alpha must be set to 0 in production ^^
 
 	// Card colours
@@ -320,15 +295,14 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 	// ## Debug variable displays ##
 	println!();
 	info!("Settings:");
-	info!("Fullscreen: {}", fullscreen);
-	info!("Number of subjects: {}", num_of_subjects);
+	info!("Fullscreen: {}", settings.fullscreen);
+	info!("Number of subjects: {}", settings.number_of_subjects);
 	// Info environment statements
 	////info!("Screen width: {}", screen_width()); // On my machine: 984 by 668
 	////info!("Screen height: {}", screen_height());
 	println!();
 
 	// ## Main loop ##
-	loading = false;
 	let time_loaded = SystemTime::now()
 		.duration_since(time_started)
 		.expect("Time went backwards");
@@ -345,7 +319,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
 		if stage == 0 {
 			// # Forward/Back buttons #
-			if num_of_subjects > 6 { // 6 subjects is maximum for display
+			if settings.number_of_subjects > 6 { // 6 subjects is maximum for display
 				// Display buttons in purple
 				texture_chosen = &stage0_no_blank;
 			} else { // Otherwise don't display
@@ -360,7 +334,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 			// factor on my display
 			height = width/16.0*9.0;
 			draw_texture_ex(
-				&texture_chosen,
+				texture_chosen,
 				screen_width()/2.0 - width/2.0,
 				0.0,
 				WHITE,
@@ -371,28 +345,46 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 				},
 			);// End of subject display loop
 
+			// # Settings button #
+			// Drawn over the gear on the stage texture, on the 3840x2160 canvas
+			let settings_rect: Rect = canvas_rect(3220., 63., 240., 240.);
+			states.settings = settings_rect.contains(mouse_position().into()) && is_mouse_button_down(MouseButton::Left);
+			if states.settings {
+				draw_stage_element(&settings_button_pressed, settings_rect);
+			} else {
+				draw_stage_element(&settings_button, settings_rect);
+			}
+
+			// Notification shows when a setting needs the user's attention
+			if !settings_problems.is_empty() || !check_settings(&settings, subjects.len()).is_empty() {
+				draw_stage_element(&stage_assets.settings_notification, canvas_rect(3405., 28., 110., 110.));
+			}
+
 			// Debug variable displays
 			draw_text(&get_fps().to_string(), 20.0, 20.0, 20.0, WHITE);
 			draw_text(&mouse_position().0.to_string(), 20.0, 100.0, 20.0, WHITE);
 			draw_text(&mouse_position().1.to_string(), 20.0, 150.0, 20.0, WHITE);
 			
 			// # Display subjects #
-			let mut sub_number: usize = (0 + (page) * subjects_per_page) as usize;
+			let mut sub_number: usize = (page * subjects_per_page) as usize;
 			// Check that all 6 subjects can be drawn
 
-			for _ in (0+page*subjects_per_page)..(page*subjects_per_page+subjects_per_page) {
+			for row in 0..subjects_per_page {
 				if sub_number >= subjects.len() {
 					////info!("Not displaying subject with number {} due to lack of existence...", sub_number);
 					break;
 				} else {
 					////info!("Drawing subject with number {}", sub_number);
+					// Each subject sits in its own 208 high row of the subject box
+					let row_rect: Rect = canvas_rect(1060., 334. + 208. * row as f32, 1780., 208.);
+					let font_size: u16 = widgets::canvas_font_size(100.);
 					draw_text_ex(
-						subjects[sub_number].as_str(),
-						405., // 405 for all items on my machine
-						180., // 180 for first item on my machine
+						subjects[sub_number].name.as_str(),
+						row_rect.x,
+						row_rect.y + row_rect.h / 2. - widgets::get_centre(&open_sans_reg, font_size, "A").y,
 						TextParams {
 							font: Some(&open_sans_reg),
-							font_size: (40),
+							font_size,
 							////font_scale: (),
 							////font_scale_aspect: (),
 							color: (text_colour),
@@ -406,98 +398,33 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 			// # Check mouse collisions #
 			if is_mouse_button_pressed(MouseButton::Left) {
 				info!("[E] Mouse click registered at {:?}", mouse_position());
-				// Check if mouse if on the subject box
-				if mouse_position().0 >= (378.) { // Subject box inner bounds
-					if mouse_position().1 >= 128. {
-						// On my machine
-						// May have to stop nesting these statements
-						if mouse_position().0 <= (1068.) { // Subject box outer bounds
-							if mouse_position().1 <= (670.) {
-								// ^^ Y coord doesn't seem to be used correctly ^^
-								// Maybe 4 if statements would fix this issue?
-								// Also on my machine
-								info!("[H] Mouse click indentified as within subject box");
-								// Identify which subject was clicked
-								// 670/6*1+128 is the wrong number. Around 239.66666666666667
-								// Actual desired number is around 202.5 for the first number
-								// Actual desired number is around 282.5 for the second number
-								// This jump is 80
-								// The actual jump in the program is 111.6666666666667
-								// The program jump is off by 31.6666666666667
-								// This can easily be rectified by switching to 80 (On my machine)
-								if mouse_position().1 <= (80. * 1. + 128.) {
-									// Subject one
-									info!("[H] Mouse click identified as subject one");
-									if subject_exists(1, page, subjects_per_page, &subjects) {
-										info!("[H] Subject click handled as subject exists");
-										// Subject clicked and must now be handled
-									} else {
-										info!("[H] Subject click not handled as subject exists");
-									}
-
-								} else if mouse_position().1 <= (80. * 2. + 128.) {
-									// Subject two
-									info!("[H] Mouse click identified as subject two");
-									if subject_exists(1, page, subjects_per_page, &subjects) {
-										info!("[H] Subject click handled as subject exists");
-										// Subject clicked and must now be handled
-									} else {
-										info!("[H] Subject click not handled as subject exists");
-									}
-
-								} else if mouse_position().1 <= (80. * 3. + 128.) {
-									// Subject three
-									info!("[H] Mouse click identified as subject three");
-									if subject_exists(1, page, subjects_per_page, &subjects) {
-										info!("[H] Subject click handled as subject exists");
-										// Subject clicked and must now be handled
-									} else {
-										info!("[H] Subject click not handled as subject exists");
-									}
-									
-								} else if mouse_position().1 <= (80. * 4. + 128.) {
-									// Subject four
-									info!("[H] Mouse click identified as subject four");
-									if subject_exists(1, page, subjects_per_page, &subjects) {
-										info!("[H] Subject click handled as subject exists");
-										// Subject clicked and must now be handled
-									} else {
-										info!("[H] Subject click not handled as subject exists");
-									}
-									
-								} else if mouse_position().1 <= (80. * 5. + 128.) {
-									// Subject five
-									info!("[H] Mouse click identified as subject five");
-									if subject_exists(1, page, subjects_per_page, &subjects) {
-										info!("[H] Subject click handled as subject exists");
-										// Subject clicked and must now be handled
-									} else {
-										info!("[H] Subject click not handled as subject exists");
-									}
-									
-								} else if mouse_position().1 <= (80. * 6. + 128.) { // This bound is too large?
-									// Subject six
-									info!("[H] Mouse click identified as subject six");
-									if subject_exists(1, page, subjects_per_page, &subjects) {
-										info!("[H] Subject click handled as subject exists");
-										// Subject clicked and must now be handled
-									} else {
-										info!("[H] Subject click not handled as subject exists");
-									}
-									
-								} else {
-									error!("[E] Mouse click not identified as any subject despite being within subject box");
-								}
-							}
-						}
+				if settings_rect.contains(mouse_position().into()) {
+					info!("[H] Mouse click identified as settings button");
+					settings_screen = Some(SettingsScreen::new(conn, &settings, &settings_problems));
+					stage = 1;
+				}
+				// Subject box on the 3840x2160 stage texture, scaled to the window
+				let subject_box: Rect = canvas_rect(1000., 334., 1840., 208. * subjects_per_page as f32);
+				if subject_box.contains(mouse_position().into()) {
+					info!("[H] Mouse click indentified as within subject box");
+					// Identify which subject was clicked (Rows are 208 high on the texture)
+					let row: usize = ((mouse_position().1 - subject_box.y) / (subject_box.h / subjects_per_page as f32)) as usize;
+					info!("[H] Mouse click identified as subject {}", row + 1);
+					match deck::subject_at(&subjects, page as usize, subjects_per_page as usize, row) {
+						Some(subject) => {
+							info!("[H] Subject click handled as subject exists");
+							revision_screen = Some(RevisionScreen::new(conn, subject, settings.matching));
+							stage = 2;
+						},
+						None => info!("[H] Subject click not handled as subject does not exist"),
 					}
 				}
 			}
 
 			// Handle edge case
 			// Not needed in this stage but will be needed in future stages
-			if creating_subject == true {
-				if num_of_subjects - 65535 == 0 {
+			if creating_subject {
+				if settings.number_of_subjects == u16::MAX {
 					error!("Cannot create subject: Maximum number (65,535) of subjects reached.");
 				} else {
 					// Create a subject
@@ -506,14 +433,129 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
 		} else if stage == 1 {
 			// Change settings
+			match settings_screen.as_mut() {
+				Some(screen) => {
+					if let Some(next_stage) = screen.update(conn, &stage_assets) {
+						settings = screen.settings.clone(); // Keep whatever was saved
+						settings_problems = screen.problems.clone();
+						match deck.subjects() {
+							Ok(loaded) => subjects = loaded, // Algorithms may have changed
+							Err(e) => error!("Failed to reload subjects. Error: {:?}", e),
+						}
+						stage = next_stage;
+					}
+				},
+				None => stage = 0,
+			}
 		} else if stage == 2 {
 			// Revision
+			match revision_screen.as_mut() {
+				Some(screen) => {
+					if let Some(next_stage) = screen.update(conn, &stage_assets) {
+						if next_stage == 3 {
+							results_screen = screen.summary.take().map(ResultsScreen::new);
+						} else if next_stage == 4 {
+							editor_screen = Some(EditorScreen::new(conn, &screen.subject));
+						} else if next_stage == 5 {
+							edit_screen = Some(EditScreen::new(conn, &screen.subject));
+						}
+						stage = next_stage;
+					}
+				},
+				None => stage = 0, // No subject chosen
+			}
 		} else if stage == 3 {
 			// Results
+			match results_screen.as_mut() {
+				Some(screen) => {
+					if let Some(next_stage) = screen.update(&stage_assets) {
+						if next_stage == 2 {
+							// Re-run only the missed cards
+							revision_screen = Some(screen.missed_cards_screen(conn, settings.matching));
+						}
+						stage = next_stage;
+					}
+				},
+				None => stage = 0, // Nothing to show
+			}
 		} else if stage == 4 {
 			// Add/Remove flashcards
+			match editor_screen.as_mut() {
+				Some(screen) => {
+					if let Some(next_stage) = screen.update(conn, &stage_assets) {
+						if next_stage == 6 {
+							note_types_screen = Some(NoteTypesScreen::new(conn));
+						} else if next_stage == 7 {
+							import_export_screen = Some(ImportExportScreen::new(&screen.subject));
+						} else if let Some(revision) = revision_screen.as_mut() {
+							revision.refresh(conn); // Cards may have been added, removed or reset
+						}
+						stage = next_stage;
+					}
+				},
+				None => stage = 0, // No subject chosen
+			}
 		} else if stage == 5 {
 			// Edit flashcards
+			match edit_screen.as_mut() {
+				Some(screen) => {
+					if let Some(next_stage) = screen.update(conn, &stage_assets) {
+						if let Some(revision) = revision_screen.as_mut() {
+							revision.refresh(conn); // Cards may have been added, removed or reset
+						}
+						stage = next_stage;
+					}
+				},
+				None => stage = 0, // No subject chosen
+			}
+		} else if stage == 6 {
+			// Note types
+			match note_types_screen.as_mut() {
+				Some(screen) => {
+					if let Some(next_stage) = screen.update(conn, &stage_assets) {
+						if let Some(editor) = editor_screen.as_mut() {
+							editor.reload_note_types(conn);
+							editor.reload(conn); // Saving a note type remakes the cards of its notes
+						}
+						stage = next_stage;
+					}
+				},
+				None => stage = 4,
+			}
+		} else if stage == 7 {
+			// Import/Export
+			match import_export_screen.as_mut() {
+				Some(screen) => {
+					if let Some(next_stage) = screen.update(conn, &stage_assets) {
+						if next_stage == 8 {
+							paste_screen = Some(PasteScreen::new(&screen.subject)); // Subjects and cards are reloaded when leaving Import/Export
+						} else {
+							if let Some(editor) = editor_screen.as_mut() {
+								editor.reload(conn); // Cards may have been imported
+							}
+							if let Some(revision) = revision_screen.as_mut() {
+								revision.refresh(conn);
+							}
+							match deck.subjects() {
+								Ok(loaded) => subjects = loaded, // Anki decks are imported as new subjects
+								Err(e) => error!("Failed to reload subjects. Error: {:?}", e),
+							}
+						}
+						stage = next_stage;
+					}
+				},
+				None => stage = 4,
+			}
+		} else if stage == 8 {
+			// Paste flashcards
+			match paste_screen.as_mut() {
+				Some(screen) => {
+					if let Some(next_stage) = screen.update(conn, &stage_assets) {
+						stage = next_stage;
+					}
+				},
+				None => stage = 7,
+			}
 		} else {
 			panic!("ERROR 1: Invalid stage number");
 		}
@@ -524,6 +566,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 		////info!("Mouse position: {:?}", mouse_position());
 
 		// End section (Nothing past this point please)
+		clear_input_queue(); // Typed characters not used by a text box this frame are dropped
 		next_frame().await;
 	}
 	
@@ -0,0 +1,94 @@
+use macroquad::prelude::*; // Handles window display
+
+use flashcard_core::flashcards::Flashcard;
+use flashcard_core::scheduler::tier_name;
+
+use crate::stages::{StageAssets, BOX_Y, ROW_HEIGHT};
+use crate::widgets::{canvas_rect, canvas_scale, draw_label, truncate_to_width, GRAY, PURPLE};
+
+// Panel to the left of the flashcard box on the design canvas
+const LIST_X: f32 = 60.0;
+const LIST_WIDTH: f32 = 880.0;
+const LIST_ROW_HEIGHT: f32 = ROW_HEIGHT / 2.0;
+const VISIBLE_ROWS: usize = 14; // Same height as the flashcard box
+
+// Scrollable list of a subject's flashcards (Or anything else) that one can be picked from
+pub struct CardList {
+	pub selected: Option<usize>, // Index into the flashcards passed to `update`
+	scroll: usize, // First flashcard shown
+}
+
+impl CardList {
+	pub fn new() -> CardList {
+		CardList {
+			selected: None,
+			scroll: 0,
+		}
+	}
+
+	//* Draws the list and handles scrolling and selection. Returns true when the selection changes */
+	pub fn update(&mut self, flashcards: &[Flashcard], assets: &StageAssets) -> bool {
+		let labels: Vec<String> = flashcards.iter()
+			.map(|flashcard| {
+				let text: String = format!("[{}] {}", tier_name(flashcard.category()), flashcard.display_question());
+				if flashcard.cloze_index > 0 {
+					format!("(c{}) {}", flashcard.cloze_index, text) // Cloze cards from one text look alike, so the number goes first
+				} else if flashcard.reversed {
+					format!("(Reverse) {}", text)
+				} else {
+					text
+				}
+			})
+			.collect();
+		self.update_labels(&labels, "No flashcards yet!", assets)
+	}
+
+	//* Same as `update`, for a list of anything. `empty` is shown when there is nothing to pick */
+	pub fn update_labels(&mut self, labels: &[String], empty: &str, assets: &StageAssets) -> bool {
+		let panel: Rect = canvas_rect(LIST_X, BOX_Y, LIST_WIDTH, LIST_ROW_HEIGHT * VISIBLE_ROWS as f32);
+		draw_rectangle(panel.x, panel.y, panel.w, panel.h, WHITE);
+
+		if self.selected.is_some_and(|index| index >= labels.len()) {
+			self.selected = None; // No longer exists
+		}
+
+		let max_scroll: usize = labels.len().saturating_sub(VISIBLE_ROWS);
+		if panel.contains(mouse_position().into()) {
+			let wheel: f32 = mouse_wheel().1;
+			if wheel < 0.0 && self.scroll < max_scroll {
+				self.scroll += 1;
+			} else if wheel > 0.0 && self.scroll > 0 {
+				self.scroll -= 1;
+			}
+		}
+		self.scroll = self.scroll.min(max_scroll);
+
+		if labels.is_empty() {
+			draw_label(empty, LIST_X + 30.0, BOX_Y + 20.0, 60.0, GRAY, &assets.font);
+		}
+
+		let mut changed: bool = false;
+		for (row, (index, label)) in labels.iter().enumerate().skip(self.scroll).take(VISIBLE_ROWS).enumerate() {
+			let row_y: f32 = BOX_Y + LIST_ROW_HEIGHT * row as f32;
+			let row_rect: Rect = canvas_rect(LIST_X, row_y, LIST_WIDTH, LIST_ROW_HEIGHT);
+
+			let colour: Color = if self.selected == Some(index) {
+				draw_rectangle(row_rect.x, row_rect.y, row_rect.w, row_rect.h, PURPLE);
+				WHITE
+			} else {
+				BLACK
+			};
+			draw_line(row_rect.x, row_rect.y + row_rect.h, row_rect.x + row_rect.w, row_rect.y + row_rect.h, 2.0, GRAY);
+
+			let text: String = truncate_to_width(label, (LIST_WIDTH - 60.0) * canvas_scale(), 60.0, &assets.font);
+			draw_label(&text, LIST_X + 30.0, row_y + 20.0, 60.0, colour, &assets.font);
+
+			if is_mouse_button_pressed(MouseButton::Left) && row_rect.contains(mouse_position().into()) {
+				self.selected = Some(index);
+				changed = true;
+			}
+		}
+
+		changed
+	}
+}
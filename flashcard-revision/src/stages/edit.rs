@@ -0,0 +1,372 @@
+use macroquad::prelude::*; // Handles window display
+
+use rusqlite::Connection; // Handles SQLite database
+
+use flashcard_core::database::Subject;
+use flashcard_core::flashcards::{self, Flashcard, FlashcardEdit, FlashcardSnapshot};
+use flashcard_core::history::EditHistory;
+use flashcard_core::notes::{self, Note, NoteType};
+use flashcard_core::scheduler::tier_name;
+
+use crate::stages::{
+	bottom_button, box_row, card_kind_label, card_list::CardList, direction_label, draw_frame, draw_row_label, focus_next, inset,
+	side_button, StageAssets, DISTRACTORS_HINT,
+};
+use crate::widgets::{button, disabled_button, TextBox};
+
+pub struct EditScreen {
+	pub subject: Subject,
+	flashcards: Vec<Flashcard>,
+	card_list: CardList,
+	selected: Option<FlashcardSnapshot>, // Stored state of the card being edited
+	question_box: TextBox,
+	answer_box: TextBox,
+	alternates_box: TextBox, // Other accepted answers, one per line
+	distractors_box: TextBox, // Wrong options of a multiple choice card, one per line
+	multiple_choice: bool, // Kind the card will be saved as
+	reverse_key: Option<i32>, // Primary key of the selected card's reverse sibling, if it has one
+	both_ways: bool, // Whether the card will have a reverse sibling once saved
+	note: Option<(Note, NoteType)>, // The note the selected card was made from, if any
+	field_boxes: Vec<TextBox>, // One per field of that note
+	history: EditHistory,
+	message: Option<String>, // Result of the last action
+}
+
+impl EditScreen {
+	pub fn new(conn: &Connection, subject: &Subject) -> EditScreen {
+		let mut screen = EditScreen {
+			subject: subject.clone(),
+			flashcards: Vec::new(),
+			card_list: CardList::new(),
+			selected: None,
+			question_box: TextBox::multiline("Question..."),
+			answer_box: TextBox::multiline("Answer..."),
+			alternates_box: TextBox::multiline("Other accepted answers (One per line)..."),
+			distractors_box: TextBox::multiline(DISTRACTORS_HINT),
+			multiple_choice: false,
+			reverse_key: None,
+			both_ways: false,
+			note: None,
+			field_boxes: Vec::new(),
+			history: EditHistory::new(),
+			message: None,
+		};
+		screen.reload(conn);
+		screen
+	}
+
+	fn reload(&mut self, conn: &Connection) {
+		let result: rusqlite::Result<Vec<Flashcard>> = flashcards::load_all_flashcards(conn, self.subject.id);
+
+		match result {
+			Ok(loaded) => self.flashcards = loaded,
+			Err(e) => {
+				error!("Failed to load flashcards for {}. Error: {:?}", self.subject.name, e);
+				self.message = Some("Failed to load flashcards.".to_owned());
+			}
+		}
+	}
+
+	//* Loads a flashcard into the text boxes */
+	fn select(&mut self, conn: &Connection, primary_key: i32) {
+		let result = flashcards::load_snapshot(conn, primary_key)
+			.and_then(|snapshot| Ok((flashcards::find_reverse(conn, primary_key)?, snapshot)))
+			.and_then(|(reverse_key, snapshot)| {
+				let note: Option<(Note, NoteType)> = match snapshot.note_id {
+					Some(note_id) => {
+						let note: Note = notes::load_note(conn, note_id)?;
+						let note_type: NoteType = notes::load_note_type(conn, note.note_type_id)?;
+						Some((note, note_type))
+					},
+					None => None,
+				};
+				Ok((reverse_key, snapshot, note))
+			});
+		match result {
+			Ok((reverse_key, snapshot, note)) => {
+				self.field_boxes = match &note {
+					Some((note, note_type)) => note_type.fields.iter().enumerate().map(|(position, field)| {
+						let mut field_box: TextBox = TextBox::multiline(&format!("{}...", field));
+						field_box.set_text(note.values.get(position).map(String::as_str).unwrap_or(""));
+						field_box
+					}).collect(),
+					None => Vec::new(),
+				};
+				self.note = note;
+				self.reverse_key = reverse_key;
+				self.both_ways = reverse_key.is_some();
+				self.question_box.set_text(&snapshot.question);
+				self.answer_box.set_text(&snapshot.answer);
+				self.alternates_box.set_text(&snapshot.alternates.join("\n"));
+				self.distractors_box.set_text(&snapshot.distractors.join("\n"));
+				self.multiple_choice = snapshot.multiple_choice;
+				self.card_list.selected = self.flashcards.iter().position(|flashcard| flashcard.primary_key == primary_key);
+				self.selected = Some(snapshot);
+			},
+			Err(e) => {
+				error!("Failed to load flashcard {}. Error: {:?}", primary_key, e);
+				self.message = Some("Failed to load flashcard.".to_owned());
+				self.selected = None;
+			}
+		}
+	}
+
+	//* Draws stage 5 and returns the stage to move to, if it should change */
+	pub fn update(&mut self, conn: &Connection, assets: &StageAssets) -> Option<u8> {
+		draw_frame(assets, &format!("{}: Edit flashcards", self.subject.name));
+
+		if self.card_list.update(&self.flashcards, assets) {
+			if let Some(flashcard) = self.card_list.selected.and_then(|index| self.flashcards.get(index)) {
+				let primary_key: i32 = flashcard.primary_key;
+				self.select(conn, primary_key);
+			}
+		}
+
+		let question_rect: Rect = inset(box_row(0).combine_with(box_row(1)));
+		let answer_rect: Rect = inset(box_row(2));
+		let list_rect: Rect = inset(box_row(3).combine_with(box_row(4)));
+		match &self.selected {
+			Some(snapshot) if self.note.is_some() => {
+				// One row per field of the note, its cards are made from the templates
+				for (row, field_box) in self.field_boxes.iter_mut().enumerate() {
+					let field_rect: Rect = inset(box_row(row as u8));
+					field_box.update(field_rect);
+					field_box.draw(field_rect, &assets.font);
+				}
+				if is_key_pressed(KeyCode::Tab) {
+					focus_next(&mut self.field_boxes.iter_mut().collect::<Vec<&mut TextBox>>());
+				}
+
+				if button(side_button(0), card_kind_label(self.multiple_choice), &assets.font) {
+					self.multiple_choice = !self.multiple_choice;
+				}
+				disabled_button(side_button(1), "Note card", &assets.font);
+
+				draw_row_label(
+					&format!(
+						"Tier: {} ({} days) | Correct: {} | Incorrect: {}",
+						tier_name(snapshot.schedule.tier()), snapshot.schedule.interval, snapshot.correct, snapshot.incorrect
+					),
+					5,
+					assets,
+				);
+			},
+			Some(snapshot) => {
+				let list_box: &mut TextBox = if self.multiple_choice { &mut self.distractors_box } else { &mut self.alternates_box };
+				list_box.update(list_rect);
+				list_box.draw(list_rect, &assets.font);
+				self.question_box.update(question_rect);
+				self.answer_box.update(answer_rect);
+				self.question_box.draw(question_rect, &assets.font);
+				self.answer_box.draw(answer_rect, &assets.font);
+
+				// Options of the selected card, applied when it is saved
+				if button(side_button(0), card_kind_label(self.multiple_choice), &assets.font) {
+					self.multiple_choice = !self.multiple_choice;
+					self.alternates_box.focused = false;
+					self.distractors_box.focused = false;
+				}
+				if snapshot.reversed {
+					disabled_button(side_button(1), "Reverse card", &assets.font);
+				} else if snapshot.cloze_index > 0 {
+					disabled_button(side_button(1), "Cloze card", &assets.font);
+				} else if button(side_button(1), direction_label(self.both_ways), &assets.font) {
+					self.both_ways = !self.both_ways;
+				}
+
+				if is_key_pressed(KeyCode::Tab) {
+					let list_box: &mut TextBox = if self.multiple_choice { &mut self.distractors_box } else { &mut self.alternates_box };
+					focus_next(&mut [&mut self.question_box, &mut self.answer_box, list_box]);
+				}
+
+				draw_row_label(
+					&format!(
+						"Tier: {} ({} days) | Correct: {} | Incorrect: {}",
+						tier_name(snapshot.schedule.tier()), snapshot.schedule.interval, snapshot.correct, snapshot.incorrect
+					),
+					5,
+					assets,
+				);
+			},
+			None => draw_row_label("Pick a flashcard from the list to edit it.", 0, assets),
+		}
+
+		if let Some(message) = &self.message {
+			draw_row_label(message, 6, assets);
+		}
+
+		// Undo/redo with buttons or Ctrl+Z/Ctrl+Y
+		let control: bool = is_key_down(KeyCode::LeftControl) || is_key_down(KeyCode::RightControl)
+			|| is_key_down(KeyCode::LeftSuper) || is_key_down(KeyCode::RightSuper);
+		if !self.history.can_undo() {
+			disabled_button(bottom_button(3, 5), "Undo", &assets.font);
+		} else if button(bottom_button(3, 5), "Undo", &assets.font) || (control && is_key_pressed(KeyCode::Z)) {
+			let result = self.history.undo(conn);
+			self.after_history(conn, result, "Undone.");
+		}
+		if !self.history.can_redo() {
+			disabled_button(bottom_button(4, 5), "Redo", &assets.font);
+		} else if button(bottom_button(4, 5), "Redo", &assets.font) || (control && is_key_pressed(KeyCode::Y)) {
+			let result = self.history.redo(conn);
+			self.after_history(conn, result, "Redone.");
+		}
+
+		if button(bottom_button(0, 5), "Back", &assets.font) {
+			return Some(2);
+		}
+		if self.selected.is_some() {
+			if button(bottom_button(1, 5), "Save", &assets.font) || (control && is_key_pressed(KeyCode::S)) {
+				self.save(conn);
+			}
+			if button(bottom_button(2, 5), "Reset", &assets.font) {
+				self.reset(conn);
+			}
+		} else {
+			disabled_button(bottom_button(1, 5), "Save", &assets.font);
+			disabled_button(bottom_button(2, 5), "Reset", &assets.font);
+		}
+		None
+	}
+
+	fn save(&mut self, conn: &Connection) {
+		let before: FlashcardSnapshot = match &self.selected {
+			Some(snapshot) => snapshot.clone(),
+			None => return,
+		};
+		if let Some((note, note_type)) = self.note.clone() {
+			self.save_note(conn, &before, &note, &note_type);
+			return;
+		}
+
+		let cloze: bool = before.cloze_index > 0;
+		let edit: FlashcardEdit = FlashcardEdit {
+			question: Some(self.question_box.text.clone()),
+			answer: if cloze { None } else { Some(self.answer_box.text.clone()) }, // The answer of a cloze card is whatever its deletion hides
+			alternates: Some(flashcards::parse_alternates(&self.alternates_box.text)),
+			multiple_choice: Some(self.multiple_choice),
+			distractors: Some(flashcards::parse_alternates(&self.distractors_box.text)),
+		};
+		let edited: FlashcardSnapshot = match edit.apply(&before) {
+			Ok(edited) => edited,
+			Err(problem) => {
+				self.message = Some(problem);
+				return;
+			}
+		};
+
+		let result = flashcards::save_edit(conn, &edited)
+			.and_then(|_| self.apply_direction(conn, before.primary_key))
+			.and_then(|_| flashcards::load_snapshot(conn, before.primary_key));
+		match result {
+			Ok(after) => {
+				info!("Flashcard {} edited in {}", before.primary_key, self.subject.name);
+				let after_key: i32 = after.primary_key;
+				self.history.record(before, after.clone());
+				self.answer_box.set_text(&after.answer);
+				self.selected = Some(after);
+				self.message = Some("Flashcard saved!".to_owned());
+				self.reload(conn);
+				// Cloze cards may have been added to or removed from the list
+				self.card_list.selected = self.flashcards.iter().position(|flashcard| flashcard.primary_key == after_key);
+			},
+			Err(e) => {
+				error!("Failed to edit flashcard {}. Error: {:?}", before.primary_key, e);
+				self.message = Some("Failed to save flashcard.".to_owned());
+			}
+		}
+	}
+
+	//* Stores the field boxes into the selected card's note and remakes all of its cards */
+	fn save_note(&mut self, conn: &Connection, before: &FlashcardSnapshot, note: &Note, note_type: &NoteType) {
+		let values: Vec<String> = self.field_boxes.iter().map(|field_box| field_box.text.trim().to_owned()).collect();
+		if notes::generate(note_type, &values).is_empty() {
+			self.message = Some("None of the cards would have anything on the front.".to_owned());
+			return;
+		}
+
+		let distractors: Vec<String> = flashcards::parse_alternates(&self.distractors_box.text);
+		let result = notes::edit_note(conn, note.id, &values).and_then(|card_count| {
+			// The selected card is gone if the field on its front was emptied
+			let remaining: Vec<i32> = notes::cards_of_note(conn, note.id)?;
+			if remaining.contains(&before.primary_key) {
+				flashcards::set_multiple_choice(conn, before.primary_key, self.multiple_choice, &distractors)?;
+			}
+			Ok((card_count, remaining))
+		});
+		match result {
+			Ok((card_count, remaining)) => {
+				info!("Note {} edited in {}", note.id, self.subject.name);
+				self.history = EditHistory::new(); // Undoing older edits would bring back text from before the note changed
+				self.message = Some(format!("Note saved! {} flashcards updated.", card_count));
+				self.reload(conn);
+				match remaining.iter().find(|primary_key| **primary_key == before.primary_key).or(remaining.first()) {
+					Some(primary_key) => self.select(conn, *primary_key),
+					None => {
+						self.selected = None;
+						self.card_list.selected = None;
+					}
+				}
+			},
+			Err(e) => {
+				error!("Failed to edit note {}. Error: {:?}", note.id, e);
+				self.message = Some("Failed to save note.".to_owned());
+			}
+		}
+	}
+
+	//* Adds or removes the reverse sibling of a card to match the direction button */
+	fn apply_direction(&mut self, conn: &Connection, primary_key: i32) -> rusqlite::Result<()> {
+		match (self.both_ways, self.reverse_key) {
+			(true, None) => {
+				self.reverse_key = Some(flashcards::add_reverse_flashcard(conn, primary_key)? as i32);
+				info!("Reverse of flashcard {} added to {}", primary_key, self.subject.name);
+			},
+			(false, Some(reverse_key)) => {
+				flashcards::remove_flashcard(conn, reverse_key)?;
+				self.reverse_key = None;
+				info!("Reverse of flashcard {} removed from {}", primary_key, self.subject.name);
+			},
+			_ => {},
+		}
+		Ok(())
+	}
+
+	fn reset(&mut self, conn: &Connection) {
+		let before: FlashcardSnapshot = match &self.selected {
+			Some(snapshot) => snapshot.clone(),
+			None => return,
+		};
+
+		let result = flashcards::reset_flashcard(conn, before.primary_key)
+			.and_then(|_| flashcards::load_snapshot(conn, before.primary_key));
+		match result {
+			Ok(after) => {
+				info!("Flashcard {} reset in {}", before.primary_key, self.subject.name);
+				self.history.record(before, after.clone());
+				self.selected = Some(after);
+				self.message = Some("Flashcard rescheduled as new and counters cleared.".to_owned());
+				self.reload(conn);
+			},
+			Err(e) => {
+				error!("Failed to reset flashcard {}. Error: {:?}", before.primary_key, e);
+				self.message = Some("Failed to reset flashcard.".to_owned());
+			}
+		}
+	}
+
+	//* Reloads the screen after an undo/redo so the text boxes show the stored card */
+	fn after_history(&mut self, conn: &Connection, result: rusqlite::Result<Option<i32>>, done: &str) {
+		match result {
+			Ok(Some(primary_key)) => {
+				self.reload(conn);
+				self.select(conn, primary_key);
+				self.message = Some(done.to_owned());
+			},
+			Ok(None) => {},
+			Err(e) => {
+				error!("Failed to apply edit history. Error: {:?}", e);
+				self.message = Some("Failed to undo/redo.".to_owned());
+			}
+		}
+	}
+}
@@ -0,0 +1,309 @@
+use macroquad::prelude::*; // Handles window display
+
+use rusqlite::Connection; // Handles SQLite database
+
+use flashcard_core::cloze;
+use flashcard_core::database::Subject;
+use flashcard_core::flashcards::{self, Flashcard};
+use flashcard_core::notes::{self, NoteType};
+
+use crate::stages::{
+	bottom_button, box_row, card_kind_label, card_list::CardList, direction_label, draw_frame, draw_row_label, focus_next, inset,
+	side_button, StageAssets, DISTRACTORS_HINT,
+};
+use crate::widgets::{button, disabled_button, TextBox};
+
+pub struct EditorScreen {
+	pub subject: Subject,
+	flashcards: Vec<Flashcard>,
+	card_list: CardList,
+	question_box: TextBox,
+	answer_box: TextBox,
+	alternates_box: TextBox, // Other accepted answers, one per line
+	distractors_box: TextBox, // Wrong options of a multiple choice card, one per line
+	multiple_choice: bool, // Kind of card being added
+	both_ways: bool, // Also adds the reverse (Answer to question) card
+	note_types: Vec<NoteType>,
+	note_type: Option<usize>, // Index into note_types of the note being added (None = a single card)
+	field_boxes: Vec<TextBox>, // One per field of the note type
+	confirming_delete: bool,
+	message: Option<String>, // Result of the last save/delete
+}
+
+impl EditorScreen {
+	pub fn new(conn: &Connection, subject: &Subject) -> EditorScreen {
+		let mut screen = EditorScreen {
+			subject: subject.clone(),
+			flashcards: Vec::new(),
+			card_list: CardList::new(),
+			question_box: TextBox::multiline("Question..."),
+			answer_box: TextBox::multiline("Answer..."),
+			alternates_box: TextBox::multiline("Other accepted answers (One per line)..."),
+			distractors_box: TextBox::multiline(DISTRACTORS_HINT),
+			multiple_choice: false,
+			both_ways: false,
+			note_types: Vec::new(),
+			note_type: None,
+			field_boxes: Vec::new(),
+			confirming_delete: false,
+			message: None,
+		};
+		screen.question_box.focused = true;
+		screen.reload(conn);
+		screen.reload_note_types(conn);
+		screen
+	}
+
+	//* Reloads the note types, e.g. after they have been edited in stage 6 */
+	pub fn reload_note_types(&mut self, conn: &Connection) {
+		let chosen: Option<i64> = self.note_type.and_then(|index| self.note_types.get(index)).map(|note_type| note_type.id);
+		match notes::load_note_types(conn) {
+			Ok(loaded) => self.note_types = loaded,
+			Err(e) => {
+				error!("Failed to load note types. Error: {:?}", e);
+				self.message = Some("Failed to load note types.".to_owned());
+			}
+		}
+		let index: Option<usize> = chosen.and_then(|id| self.note_types.iter().position(|note_type| note_type.id == id));
+		self.choose_note_type(index);
+	}
+
+	//* Swaps to adding notes of a note type (Or single cards), with a text box for each field */
+	fn choose_note_type(&mut self, index: Option<usize>) {
+		self.note_type = index;
+		self.field_boxes = match index.and_then(|index| self.note_types.get(index)) {
+			Some(note_type) => note_type.fields.iter().map(|field| TextBox::multiline(&format!("{}...", field))).collect(),
+			None => Vec::new(),
+		};
+		match self.field_boxes.first_mut() {
+			Some(first) => first.focused = true,
+			None => self.question_box.focused = true,
+		}
+	}
+
+	pub fn reload(&mut self, conn: &Connection) {
+		let result: rusqlite::Result<Vec<Flashcard>> = flashcards::load_all_flashcards(conn, self.subject.id);
+
+		match result {
+			Ok(loaded) => self.flashcards = loaded,
+			Err(e) => {
+				error!("Failed to load flashcards for {}. Error: {:?}", self.subject.name, e);
+				self.message = Some("Failed to load flashcards.".to_owned());
+			}
+		}
+	}
+
+	//* Draws stage 4 and returns the stage to move to, if it should change */
+	pub fn update(&mut self, conn: &Connection, assets: &StageAssets) -> Option<u8> {
+		draw_frame(assets, &format!("{}: Add/Remove flashcards", self.subject.name));
+
+		if self.confirming_delete {
+			self.confirm_delete(conn, assets);
+			return None;
+		}
+
+		self.card_list.update(&self.flashcards, assets);
+
+		if self.note_type.is_some() {
+			// One row per field
+			for (row, field_box) in self.field_boxes.iter_mut().enumerate() {
+				let field_rect: Rect = inset(box_row(row as u8));
+				field_box.update(field_rect);
+				field_box.draw(field_rect, &assets.font);
+			}
+			if is_key_pressed(KeyCode::Tab) {
+				focus_next(&mut self.field_boxes.iter_mut().collect::<Vec<&mut TextBox>>());
+			}
+		} else {
+			// Question and other accepted answers (Or wrong options) take two rows each, the main answer takes one
+			let question_rect: Rect = inset(box_row(0).combine_with(box_row(1)));
+			let answer_rect: Rect = inset(box_row(2));
+			let list_rect: Rect = inset(box_row(3).combine_with(box_row(4)));
+			let list_box: &mut TextBox = if self.multiple_choice { &mut self.distractors_box } else { &mut self.alternates_box };
+			list_box.update(list_rect);
+			list_box.draw(list_rect, &assets.font);
+			self.question_box.update(question_rect);
+			self.answer_box.update(answer_rect);
+			self.question_box.draw(question_rect, &assets.font);
+			self.answer_box.draw(answer_rect, &assets.font);
+
+			if is_key_pressed(KeyCode::Tab) {
+				let list_box: &mut TextBox = if self.multiple_choice { &mut self.distractors_box } else { &mut self.alternates_box };
+				focus_next(&mut [&mut self.question_box, &mut self.answer_box, list_box]);
+			}
+		}
+
+		// Options of the card being added
+		if button(side_button(0), card_kind_label(self.multiple_choice), &assets.font) {
+			self.multiple_choice = !self.multiple_choice;
+			self.alternates_box.focused = false;
+			self.distractors_box.focused = false;
+		}
+		if self.note_type.is_some() {
+			disabled_button(side_button(1), "From templates", &assets.font); // The note type decides which cards are made
+		} else if button(side_button(1), direction_label(self.both_ways), &assets.font) {
+			self.both_ways = !self.both_ways;
+		}
+		let note_label: String = match self.note_type.and_then(|index| self.note_types.get(index)) {
+			Some(note_type) => format!("Note: {}", note_type.name),
+			None => "Note: Basic".to_owned(),
+		};
+		if button(side_button(2), &note_label, &assets.font) {
+			// Cycles through Basic and then every note type
+			let next: Option<usize> = match self.note_type {
+				None if !self.note_types.is_empty() => Some(0),
+				Some(index) if index + 1 < self.note_types.len() => Some(index + 1),
+				_ => None,
+			};
+			self.choose_note_type(next);
+		}
+		if button(side_button(3), "Note types", &assets.font) {
+			return Some(6);
+		}
+		if button(side_button(4), "Import/Export", &assets.font) {
+			return Some(7);
+		}
+
+		if let Some(message) = &self.message {
+			draw_row_label(message, 5, assets);
+		}
+		if let Some(flashcard) = self.card_list.selected.and_then(|index| self.flashcards.get(index)) {
+			draw_row_label(&format!("Selected: {}", flashcard.display_question().replace('\n', " ")), 6, assets);
+		}
+
+		if button(bottom_button(0, 3), "Back", &assets.font) {
+			return Some(2);
+		}
+		if button(bottom_button(1, 3), "Save", &assets.font) {
+			self.save(conn);
+		}
+		if self.card_list.selected.is_some() && button(bottom_button(2, 3), "Delete", &assets.font) {
+			self.confirming_delete = true;
+		}
+		None
+	}
+
+	fn save(&mut self, conn: &Connection) {
+		if let Some(note_type) = self.note_type.and_then(|index| self.note_types.get(index)).cloned() {
+			self.save_note(conn, &note_type);
+			return;
+		}
+
+		let question: &str = self.question_box.text.trim();
+		let answer: &str = self.answer_box.text.trim();
+		let is_cloze: bool = !cloze::indexes(question).is_empty(); // Cloze cards get their answers from the text
+		if question.is_empty() || (answer.is_empty() && !is_cloze) {
+			self.message = Some("Flashcards need a question and an answer.".to_owned());
+			return;
+		}
+
+		let alternates: Vec<String> = flashcards::parse_alternates(&self.alternates_box.text);
+		let distractors: Vec<String> = flashcards::parse_alternates(&self.distractors_box.text);
+
+		let result: rusqlite::Result<usize> = if is_cloze {
+			flashcards::add_cloze_flashcards(conn, self.subject.id, question).map(|added| added.len())
+		} else {
+			flashcards::add_new_flashcard(conn, self.subject.id, question, answer, &alternates)
+				.and_then(|primary_key| {
+					flashcards::set_multiple_choice(conn, primary_key as i32, self.multiple_choice, &distractors)?;
+					if self.both_ways {
+						flashcards::add_reverse_flashcard(conn, primary_key as i32)?;
+						return Ok(2);
+					}
+					Ok(1)
+				})
+		};
+		match result {
+			Ok(added) => {
+				info!("{} flashcard(s) added to {}", added, self.subject.name);
+				self.message = Some(if is_cloze {
+					format!("{} cloze flashcards added!", added)
+				} else if added == 2 {
+					"Flashcard added both ways!".to_owned()
+				} else {
+					"Flashcard added!".to_owned()
+				});
+				self.question_box = TextBox::multiline("Question...");
+				self.answer_box = TextBox::multiline("Answer...");
+				self.alternates_box = TextBox::multiline("Other accepted answers (One per line)...");
+				self.distractors_box = TextBox::multiline(DISTRACTORS_HINT);
+				self.question_box.focused = true;
+				self.reload(conn);
+				self.card_list.selected = Some(self.flashcards.len().saturating_sub(1));
+			},
+			Err(e) => {
+				error!("Failed to add flashcard to {}. Error: {:?}", self.subject.name, e);
+				self.message = Some("Failed to add flashcard.".to_owned());
+			}
+		}
+	}
+
+	fn save_note(&mut self, conn: &Connection, note_type: &NoteType) {
+		let values: Vec<String> = self.field_boxes.iter().map(|field_box| field_box.text.trim().to_owned()).collect();
+		if notes::generate(note_type, &values).is_empty() {
+			self.message = Some("None of the cards would have anything on the front.".to_owned());
+			return;
+		}
+
+		let result = notes::add_note(conn, self.subject.id, note_type.id, &values).and_then(|(note_id, added)| {
+			for primary_key in notes::cards_of_note(conn, note_id)? {
+				flashcards::set_multiple_choice(conn, primary_key, self.multiple_choice, &[])?;
+			}
+			Ok(added)
+		});
+		match result {
+			Ok(added) => {
+				info!("{} note added to {} ({} cards)", note_type.name, self.subject.name, added);
+				self.message = Some(format!("Note added with {} flashcards!", added));
+				self.choose_note_type(self.note_type); // Empties the field boxes
+				self.reload(conn);
+				self.card_list.selected = Some(self.flashcards.len().saturating_sub(1));
+			},
+			Err(e) => {
+				error!("Failed to add note to {}. Error: {:?}", self.subject.name, e);
+				self.message = Some("Failed to add note.".to_owned());
+			}
+		}
+	}
+
+	fn confirm_delete(&mut self, conn: &Connection, assets: &StageAssets) {
+		let flashcard: Flashcard = match self.card_list.selected.and_then(|index| self.flashcards.get(index)) {
+			Some(flashcard) => flashcard.clone(),
+			None => {
+				self.confirming_delete = false;
+				return;
+			}
+		};
+
+		// VERY SCARY - This is irreversible.
+		draw_row_label("IRREVERSIBLE ACTION - CONFIRMATION REQUIRED:", 0, assets);
+		draw_row_label("Are you sure you want to remove this flashcard? (y/N)", 1, assets);
+		draw_row_label(&format!("Question: {}", flashcard.display_question().replace('\n', " ")), 2, assets);
+		draw_row_label(&format!("Answer: {}", flashcard.answer.replace('\n', " ")), 3, assets);
+		if flashcard.note_id.is_some() {
+			draw_row_label("This removes its note and every flashcard made from it.", 4, assets);
+		} else if !flashcard.alternates.is_empty() {
+			draw_row_label(&format!("Also accepted: {}", flashcard.alternates.join(" / ")), 4, assets);
+		}
+
+		if button(bottom_button(0, 2), "Yes, remove", &assets.font) || is_key_pressed(KeyCode::Y) {
+			match flashcards::remove_flashcard(conn, flashcard.primary_key) {
+				Ok(()) => {
+					info!("Flashcard {} removed from {}", flashcard.primary_key, self.subject.name);
+					self.message = Some("Flashcard removed!".to_owned());
+				},
+				Err(e) => {
+					error!("Failed to remove flashcard from {}. Error: {:?}", self.subject.name, e);
+					self.message = Some("Failed to remove flashcard.".to_owned());
+				}
+			}
+			self.card_list.selected = None;
+			self.confirming_delete = false;
+			self.reload(conn);
+		} else if button(bottom_button(1, 2), "No", &assets.font) || is_key_pressed(KeyCode::N) || is_key_pressed(KeyCode::Escape) {
+			// Default no
+			self.message = Some("Flashcard not removed.".to_owned());
+			self.confirming_delete = false;
+		}
+	}
+}
@@ -0,0 +1,433 @@
+use macroquad::prelude::*; // Handles window display
+
+use rusqlite::Connection; // Handles SQLite database
+
+use flashcard_core::database::{self, Subject};
+use flashcard_core::formats::{self, anki::{self, AnkiExport, AnkiPackage, AnkiReport}, delimited::{self, DelimitedFile}, json::{self, JsonDeck, JsonReport}, markdown::{self, MarkdownDeck, MarkdownReport}, PreviewCounts, PreviewRow, RowStatus};
+
+use crate::stages::{bottom_button, box_row, card_list::CardList, draw_frame, draw_row_label, inset, side_button, StageAssets};
+use crate::widgets::{button, disabled_button, TextBox};
+
+pub struct ImportExportScreen {
+	pub subject: Subject,
+	path_box: TextBox,
+	text: String, // Contents of the file being previewed, kept so it can be read again with another delimiter
+	file: Option<DelimitedFile>, // Set once a file has been previewed
+	rows: Vec<PreviewRow>,
+	anki: Option<AnkiPackage>, // Set once an Anki package has been previewed, instead of file
+	anki_summary: Vec<String>, // Decks and note types of the package, shown in the list
+	markdown: Option<MarkdownDeck>, // Set once a Markdown deck has been previewed, instead of file
+	json: Option<JsonDeck>, // Set once a JSON deck has been previewed, instead of file
+	path: String, // File being previewed, which Markdown imports write card ids into
+	list: CardList,
+	column: usize, // Column of the file that the mapping button changes
+	message: Option<String>, // Result of the last preview/import/export
+}
+
+impl ImportExportScreen {
+	pub fn new(subject: &Subject) -> ImportExportScreen {
+		let mut path_box: TextBox = TextBox::new();
+		path_box.placeholder = "File path (.csv, .tsv, .md, .json, .apkg or .colpkg)...".to_owned();
+		ImportExportScreen {
+			subject: subject.clone(),
+			path_box,
+			text: String::new(),
+			file: None,
+			rows: Vec::new(),
+			anki: None,
+			anki_summary: Vec::new(),
+			markdown: None,
+			json: None,
+			path: String::new(),
+			list: CardList::new(),
+			column: 0,
+			message: None,
+		}
+	}
+
+	//* Reads the file in the path box and previews it, as an Anki package or with a sniffed delimiter */
+	fn load(&mut self, conn: &Connection) {
+		let path: String = self.path_box.text.trim().to_owned();
+		self.file = None;
+		self.rows.clear();
+		self.anki = None;
+		self.anki_summary.clear();
+		self.markdown = None;
+		self.json = None;
+		self.list.selected = None;
+		self.path = path.clone();
+
+		let lowercase: String = path.to_lowercase();
+		if lowercase.ends_with(".apkg") || lowercase.ends_with(".colpkg") {
+			match AnkiPackage::read(&path) {
+				Ok(package) => {
+					self.message = Some(format!(
+						"{} notes and {} cards. Each deck is imported into the subject of the same name.",
+						package.note_count(), package.card_count()
+					));
+					self.anki_summary = package.summary();
+					self.anki = Some(package);
+				},
+				Err(problem) => {
+					error!("Failed to read Anki package {}. Error: {}", path, problem);
+					self.message = Some(problem);
+				}
+			}
+			return;
+		}
+
+		match std::fs::read_to_string(&path) {
+			Ok(text) => {
+				self.text = text.trim_start_matches('\u{feff}').to_owned(); // Spreadsheet apps often start files with a byte order mark
+				self.column = 0;
+				let parsed: Result<(), String> = if lowercase.ends_with(".md") || lowercase.ends_with(".markdown") {
+					MarkdownDeck::parse(&self.text).map(|deck| self.markdown = Some(deck))
+				} else if lowercase.ends_with(".json") {
+					JsonDeck::parse(&self.text).map(|deck| self.json = Some(deck))
+				} else {
+					DelimitedFile::parse(&self.text).map(|file| self.file = Some(file))
+				};
+				match parsed {
+					Ok(()) => self.refresh(conn),
+					Err(problem) => self.message = Some(problem),
+				}
+			},
+			Err(e) => {
+				error!("Failed to read {}. Error: {:?}", path, e);
+				self.message = Some(format!("Couldn't open {}.", path));
+			}
+		}
+	}
+
+	//* Subject a Markdown deck goes into: the one named by its heading, otherwise this one */
+	fn markdown_subject(&self) -> String {
+		self.markdown.as_ref().and_then(|deck| deck.subject.clone()).unwrap_or_else(|| self.subject.name.clone())
+	}
+
+	//* Works out the preview of a Markdown deck, which can update cards as well as add them */
+	fn refresh_markdown(&mut self, conn: &Connection) {
+		let Some(deck) = &self.markdown else { return };
+		let name: String = self.markdown_subject();
+		let result: rusqlite::Result<Vec<PreviewRow>> = database::get_subjects(conn).and_then(|subjects| {
+			let subject_id: Option<i64> = subjects.iter().find(|subject| subject.name == name).map(|subject| subject.id);
+			deck.preview(conn, subject_id)
+		});
+		match result {
+			Ok(rows) => {
+				self.rows = rows;
+				let counts: PreviewCounts = formats::count_rows(&self.rows);
+				self.message = Some(format!(
+					"Into {}: {} new, {} changed and {} unchanged cards. {} duplicates and {} invalid cards will be skipped.",
+					name, counts.ready, counts.updates, counts.unchanged, counts.duplicates, counts.invalid
+				));
+			},
+			Err(e) => {
+				error!("Failed to preview Markdown deck for {}. Error: {:?}", name, e);
+				self.message = Some("Failed to check the deck against the subject.".to_owned());
+			}
+		}
+	}
+
+	//* Works out the preview of a JSON deck, which goes into the subject named in it */
+	fn refresh_json(&mut self, conn: &Connection) {
+		let Some(deck) = &self.json else { return };
+		let name: String = deck.subject.name.trim().to_owned();
+		let result: rusqlite::Result<Vec<PreviewRow>> = database::get_subjects(conn).and_then(|subjects| {
+			let subject_id: Option<i64> = subjects.iter().find(|subject| subject.name == name).map(|subject| subject.id);
+			deck.preview(conn, subject_id)
+		});
+		match result {
+			Ok(rows) => {
+				self.rows = rows;
+				let counts: PreviewCounts = formats::count_rows(&self.rows);
+				self.message = Some(format!(
+					"Into {}: {} new cards. {} cards already imported or duplicated will be skipped.",
+					name, counts.ready, counts.duplicates
+				));
+			},
+			Err(e) => {
+				error!("Failed to preview JSON deck for {}. Error: {:?}", name, e);
+				self.message = Some("Failed to check the deck against the subject.".to_owned());
+			}
+		}
+	}
+
+	//* Works out the preview again after the delimiter, header or column mapping changes */
+	fn refresh(&mut self, conn: &Connection) {
+		self.list.selected = None;
+		if self.markdown.is_some() {
+			self.refresh_markdown(conn);
+			return;
+		}
+		if self.json.is_some() {
+			self.refresh_json(conn);
+			return;
+		}
+		self.rows = match &self.file {
+			Some(file) => file.preview(),
+			None => Vec::new(),
+		};
+		match formats::mark_duplicates(conn, self.subject.id, &mut self.rows) {
+			Ok(()) => {
+				let counts: PreviewCounts = formats::count_rows(&self.rows);
+				self.message = Some(format!(
+					"{} rows ready to import. {} duplicates and {} invalid rows will be skipped.",
+					counts.ready, counts.duplicates, counts.invalid
+				));
+			},
+			Err(e) => {
+				error!("Failed to check for duplicates in {}. Error: {:?}", self.subject.name, e);
+				self.message = Some("Failed to check for duplicates.".to_owned());
+			}
+		}
+	}
+
+	//* Draws stage 7 and returns the stage to move to, if it should change */
+	pub fn update(&mut self, conn: &Connection, assets: &StageAssets) -> Option<u8> {
+		draw_frame(assets, &format!("{}: Import/Export", self.subject.name));
+
+		if self.anki.is_some() {
+			self.list.update_labels(&self.anki_summary, "", assets);
+		} else {
+			let labels: Vec<String> = self.rows.iter().map(PreviewRow::label).collect();
+			self.list.update_labels(&labels, "Preview a file to see its rows.", assets);
+		}
+
+		let path_rect: Rect = inset(box_row(0));
+		self.path_box.update(path_rect);
+		self.path_box.draw(path_rect, &assets.font);
+
+		// The row picked from the list
+		match self.list.selected.and_then(|index| self.rows.get(index)) {
+			Some(row) => {
+				draw_row_label(&format!("Question: {}", row.card.question), 1, assets);
+				draw_row_label(&format!("Answer: {}", row.card.answer), 2, assets);
+				let status: String = match &row.status {
+					RowStatus::Ready => "Will be added.".to_owned(),
+					RowStatus::Duplicate => "Skipped, the subject (Or an earlier row) already has this card.".to_owned(),
+					RowStatus::Invalid(problem) => format!("Skipped: {}.", problem),
+					RowStatus::Update(_) => "The card with this id will be changed.".to_owned(),
+					RowStatus::Unchanged => "The card with this id is already the same.".to_owned(),
+				};
+				draw_row_label(&status, 3, assets);
+			},
+			None => draw_row_label("Preview reads the file, Import adds its ready rows.", 1, assets),
+		}
+
+		let mut changed: bool = false;
+		match self.file.as_mut() {
+			Some(file) => {
+				draw_row_label(
+					&format!("Columns: {}", file.columns.iter().map(|column| column.name()).collect::<Vec<&str>>().join(", ")),
+					5,
+					assets,
+				);
+
+				if button(side_button(0), &format!("Delimiter: {}", delimited::delimiter_name(file.delimiter)), &assets.font) {
+					match DelimitedFile::parse_with(&self.text, delimited::next_delimiter(file.delimiter)) {
+						Ok(reparsed) => {
+							*file = reparsed;
+							self.column = 0;
+							changed = true;
+						},
+						Err(problem) => self.message = Some(problem),
+					}
+				}
+				if button(side_button(1), if file.has_header { "Header row: Yes" } else { "Header row: No" }, &assets.font) {
+					file.has_header = !file.has_header;
+					file.guess_columns();
+					changed = true;
+				}
+				if file.columns.is_empty() {
+					disabled_button(side_button(2), "No columns", &assets.font);
+				} else {
+					self.column = self.column.min(file.columns.len() - 1);
+					if button(side_button(2), &format!("{}: {}", self.column + 1, file.column_name(self.column)), &assets.font) {
+						self.column = (self.column + 1) % file.columns.len();
+					}
+					if button(side_button(3), &format!("Read as: {}", file.columns[self.column].name()), &assets.font) {
+						file.columns[self.column] = file.columns[self.column].next();
+						changed = true;
+					}
+				}
+			},
+			None => {
+				disabled_button(side_button(0), "Delimiter", &assets.font);
+				disabled_button(side_button(1), "Header row", &assets.font);
+			},
+		}
+		if changed {
+			self.refresh(conn);
+		}
+		if button(side_button(4), "Paste text", &assets.font) {
+			return Some(8);
+		}
+
+		if let Some(message) = &self.message {
+			draw_row_label(message, 6, assets);
+		}
+
+		if button(bottom_button(0, 4), "Back", &assets.font) {
+			return Some(4);
+		}
+		if button(bottom_button(1, 4), "Preview", &assets.font) {
+			self.load(conn);
+		}
+		let counts: PreviewCounts = formats::count_rows(&self.rows);
+		if self.anki.is_none() && counts.ready + counts.updates == 0 {
+			disabled_button(bottom_button(2, 4), "Import", &assets.font);
+		} else if button(bottom_button(2, 4), "Import", &assets.font) {
+			if self.anki.is_some() {
+				self.import_anki(conn);
+			} else if self.markdown.is_some() {
+				self.import_markdown(conn);
+			} else if self.json.is_some() {
+				self.import_json(conn);
+			} else {
+				self.import(conn);
+			}
+		}
+		if button(bottom_button(3, 4), "Export", &assets.font) {
+			self.export(conn);
+		}
+		None
+	}
+
+	fn import(&mut self, conn: &Connection) {
+		let counts: PreviewCounts = formats::count_rows(&self.rows);
+		match formats::import_rows(conn, self.subject.id, &self.rows) {
+			Ok(added) => {
+				info!("Imported {} flashcards into {}", added, self.subject.name);
+				self.message = Some(format!(
+					"Imported {} flashcards. Skipped {} duplicates and {} invalid rows.",
+					added, counts.duplicates, counts.invalid
+				));
+				// Importing again would only find duplicates
+				self.file = None;
+				self.rows.clear();
+			},
+			Err(e) => {
+				error!("Failed to import into {}. Error: {:?}", self.subject.name, e);
+				self.message = Some("Failed to import, nothing was added.".to_owned());
+			}
+		}
+	}
+
+	fn import_anki(&mut self, conn: &Connection) {
+		let Some(package) = self.anki.take() else { return };
+		match anki::import_package(conn, &package) {
+			Ok(report) => {
+				let AnkiReport { cards, duplicates, skipped, media, subjects } = report;
+				info!("Imported {} flashcards from an Anki package into {}", cards, subjects.join(", "));
+				self.message = Some(format!(
+					"Imported {} flashcards into {}. Skipped {} duplicate and {} unusable notes. Copied {} media files.",
+					cards, if subjects.is_empty() { "no subjects".to_owned() } else { subjects.join(", ") }, duplicates, skipped, media
+				));
+				self.anki_summary.clear();
+			},
+			Err(problem) => {
+				error!("Failed to import Anki package into {}. Error: {}", self.subject.name, problem);
+				self.message = Some(problem);
+				self.anki = Some(package); // Can be tried again
+			}
+		}
+	}
+
+	fn import_markdown(&mut self, conn: &Connection) {
+		let Some(deck) = self.markdown.take() else { return };
+		let name: String = deck.subject.clone().unwrap_or_else(|| self.subject.name.clone());
+		match markdown::import_deck(conn, &deck, &self.rows, &name) {
+			Ok(report) => {
+				let MarkdownReport { added, updated, unchanged, skipped, ids } = report;
+				info!("Imported Markdown deck {} into {}: {} added, {} updated", self.path, name, added, updated);
+				let mut message: String = format!(
+					"Into {}: added {}, changed {}, left {} unchanged and skipped {} cards.", name, added, updated, unchanged, skipped
+				);
+				// The new cards' ids go into the file, so importing it again updates them
+				if !ids.is_empty() {
+					match std::fs::write(&self.path, markdown::write_ids(&self.text, &ids)) {
+						Ok(()) => message.push_str(&format!(" Wrote {} card ids into the file.", ids.len())),
+						Err(e) => {
+							error!("Failed to write card ids into {}. Error: {:?}", self.path, e);
+							message.push_str(" Couldn't write the card ids into the file, importing it again will add its new cards again.");
+						}
+					}
+				}
+				self.message = Some(message);
+				self.rows.clear();
+			},
+			Err(e) => {
+				error!("Failed to import Markdown deck into {}. Error: {:?}", name, e);
+				self.message = Some("Failed to import, nothing was changed.".to_owned());
+				self.markdown = Some(deck); // Can be tried again
+			}
+		}
+	}
+
+	fn import_json(&mut self, conn: &Connection) {
+		let Some(deck) = self.json.take() else { return };
+		let name: String = deck.subject.name.trim().to_owned();
+		match json::import_deck(conn, &deck, &self.rows) {
+			Ok(JsonReport { added, reviews, skipped }) => {
+				info!("Imported JSON deck {} into {}: {} flashcards and {} reviews", self.path, name, added, reviews);
+				self.message = Some(format!(
+					"Into {}: added {} flashcards with {} reviews. Skipped {} cards already imported or duplicated.",
+					name, added, reviews, skipped
+				));
+				self.rows.clear();
+			},
+			Err(e) => {
+				error!("Failed to import JSON deck into {}. Error: {:?}", name, e);
+				self.message = Some("Failed to import, nothing was added.".to_owned());
+				self.json = Some(deck); // Can be tried again
+			}
+		}
+	}
+
+	fn export(&mut self, conn: &Connection) {
+		let path: String = self.path_box.text.trim().to_owned();
+		if path.is_empty() {
+			self.message = Some("Type the path of the file to export to.".to_owned());
+			return;
+		}
+
+		if path.to_lowercase().ends_with(".apkg") {
+			self.export_anki(conn, &path);
+			return;
+		}
+
+		let lowercase: String = path.to_lowercase();
+		let exported: Result<(String, usize), String> = if lowercase.ends_with(".md") || lowercase.ends_with(".markdown") {
+			markdown::export(conn, &self.subject)
+		} else if lowercase.ends_with(".json") {
+			json::export(conn, &self.subject)
+		} else {
+			delimited::export(conn, self.subject.id, delimited::delimiter_for_path(&path))
+		};
+		let result: Result<usize, String> = exported
+			.and_then(|(text, count)| std::fs::write(&path, text).map(|_| count).map_err(|e| e.to_string()));
+		match result {
+			Ok(count) => {
+				info!("Exported {} flashcards from {} to {}", count, self.subject.name, path);
+				self.message = Some(format!("Exported {} flashcards to {}.", count, path));
+			},
+			Err(e) => {
+				error!("Failed to export {} to {}. Error: {:?}", self.subject.name, path, e);
+				self.message = Some(format!("Failed to export to {}.", path));
+			}
+		}
+	}
+
+	fn export_anki(&mut self, conn: &Connection, path: &str) {
+		match anki::export_package(conn, &self.subject, path) {
+			Ok(AnkiExport { cards, media }) => {
+				info!("Exported {} flashcards and {} media files from {} to {}", cards, media, self.subject.name, path);
+				self.message = Some(format!("Exported {} flashcards and {} media files to {}.", cards, media, path));
+			},
+			Err(problem) => {
+				error!("Failed to export {} to {}. Error: {}", self.subject.name, path, problem);
+				self.message = Some(problem);
+			}
+		}
+	}
+}
@@ -0,0 +1,88 @@
+use macroquad::prelude::*; // Handles window display
+
+use crate::widgets::{canvas_rect, canvas_scale, draw_centred_label, draw_label, draw_stage_element, TextBox};
+
+pub mod card_list;
+pub mod edit;
+pub mod editor;
+pub mod import_export;
+pub mod note_types;
+pub mod paste;
+pub mod results;
+pub mod revision;
+pub mod settings;
+
+// Textures and fonts shared by the stages past subject selection
+pub struct StageAssets {
+	pub font: Font,
+	pub header: Texture2D,
+	pub flashcard_box: Texture2D,
+	pub settings_notification: Texture2D,
+}
+
+// Layout of the stage elements on the 3840x2160 design canvas (Matches stage0_no_blank.png)
+pub const BOX_X: f32 = 1000.0;
+pub const BOX_Y: f32 = 334.0;
+pub const BOX_WIDTH: f32 = 1840.0;
+pub const ROW_HEIGHT: f32 = 208.0; // flashcard_box.png has 7 rows
+pub const BUTTON_Y: f32 = 1820.0;
+pub const BUTTON_HEIGHT: f32 = 240.0;
+
+//* Returns the window rectangle of a row inside the flashcard box */
+pub fn box_row(row: u8) -> Rect {
+	canvas_rect(BOX_X, BOX_Y + ROW_HEIGHT * row as f32, BOX_WIDTH, ROW_HEIGHT)
+}
+
+//* Returns the window rectangle of a button below the flashcard box (Up to 4 per row) */
+pub fn bottom_button(position: u8, count: u8) -> Rect {
+	let gap: f32 = 40.0;
+	let width: f32 = (BOX_WIDTH - gap * (count as f32 - 1.0)) / count as f32;
+	canvas_rect(BOX_X + (width + gap) * position as f32, BUTTON_Y, width, BUTTON_HEIGHT)
+}
+
+//* Draws the header and flashcard box that every stage is built on */
+pub fn draw_frame(assets: &StageAssets, title: &str) {
+	let header_rect: Rect = canvas_rect(BOX_X, 104.0, BOX_WIDTH, 160.0);
+	draw_stage_element(&assets.header, header_rect);
+	draw_centred_label(title, header_rect, 100.0, WHITE, &assets.font);
+
+	draw_stage_element(&assets.flashcard_box, canvas_rect(BOX_X, BOX_Y, BOX_WIDTH, ROW_HEIGHT * 7.0));
+}
+
+//* Draws a line of text inside a row of the flashcard box */
+pub fn draw_row_label(text: &str, row: u8, assets: &StageAssets) {
+	draw_label(text, BOX_X + 60.0, BOX_Y + ROW_HEIGHT * row as f32 + 50.0, 80.0, BLACK, &assets.font);
+}
+
+//* Shrinks a box row so that widgets don't cover the row lines */
+pub fn inset(rect: Rect) -> Rect {
+	let margin: f32 = 40.0 * canvas_scale();
+	Rect::new(rect.x + margin, rect.y + margin, rect.w - margin * 2.0, rect.h - margin * 2.0)
+}
+
+//* Moves focus to the next text box (Wrapping round), used when Tab is pressed */
+pub fn focus_next(boxes: &mut [&mut TextBox]) {
+	let current: usize = boxes.iter().position(|text_box| text_box.focused).unwrap_or(boxes.len() - 1);
+	let next: usize = (current + 1) % boxes.len();
+	for (index, text_box) in boxes.iter_mut().enumerate() {
+		text_box.focused = index == next;
+	}
+}
+
+//* Returns the window rectangle of a button in the panel to the right of the flashcard box (Card options) */
+pub fn side_button(position: u8) -> Rect {
+	canvas_rect(BOX_X + BOX_WIDTH + 60.0, BOX_Y + ROW_HEIGHT * position as f32 + 20.0, 880.0, ROW_HEIGHT - 40.0)
+}
+
+//* Label of the button that swaps a card between typed and multiple choice */
+pub fn card_kind_label(multiple_choice: bool) -> &'static str {
+	if multiple_choice { "Multiple choice" } else { "Typed" }
+}
+
+//* Label of the button that gives a card a reverse sibling (Answer to question) or takes it away */
+pub fn direction_label(both_ways: bool) -> &'static str {
+	if both_ways { "Both ways" } else { "One way" }
+}
+
+// Placeholder of the distractors text box
+pub const DISTRACTORS_HINT: &str = "Wrong options (One per line, or empty to use other answers)...";
@@ -0,0 +1,179 @@
+use macroquad::prelude::*; // Handles window display
+
+use rusqlite::Connection; // Handles SQLite database
+
+use flashcard_core::flashcards;
+use flashcard_core::notes::{self, NoteType, MAX_FIELDS};
+
+use crate::stages::{bottom_button, box_row, card_list::CardList, draw_frame, draw_row_label, focus_next, inset, StageAssets};
+use crate::widgets::{button, disabled_button, TextBox};
+
+const TEMPLATES_HINT: &str = "Cards (One per line), e.g. Recognition: {{Word}} -> {{Meaning}}";
+
+pub struct NoteTypesScreen {
+	note_types: Vec<NoteType>,
+	list: CardList,
+	editing: i64, // Primary key of the note type in the text boxes (0 = a new one)
+	name_box: TextBox,
+	fields_box: TextBox, // One field name per line
+	templates_box: TextBox, // One card template per line
+	message: Option<String>, // Result of the last save/delete
+}
+
+impl NoteTypesScreen {
+	pub fn new(conn: &Connection) -> NoteTypesScreen {
+		let mut screen = NoteTypesScreen {
+			note_types: Vec::new(),
+			list: CardList::new(),
+			editing: 0,
+			name_box: TextBox::multiline("Name..."),
+			fields_box: TextBox::multiline(&format!("Fields (One per line, up to {})...", MAX_FIELDS)),
+			templates_box: TextBox::multiline(TEMPLATES_HINT),
+			message: None,
+		};
+		screen.name_box.focused = true;
+		screen.reload(conn);
+		screen
+	}
+
+	fn reload(&mut self, conn: &Connection) {
+		match notes::load_note_types(conn) {
+			Ok(loaded) => self.note_types = loaded,
+			Err(e) => {
+				error!("Failed to load note types. Error: {:?}", e);
+				self.message = Some("Failed to load note types.".to_owned());
+			}
+		}
+		self.list.selected = self.note_types.iter().position(|note_type| note_type.id == self.editing);
+	}
+
+	//* Loads a note type into the text boxes, or empties them for a new one */
+	fn select(&mut self, note_type: Option<NoteType>) {
+		match note_type {
+			Some(note_type) => {
+				self.editing = note_type.id;
+				self.name_box.set_text(&note_type.name);
+				self.fields_box.set_text(&note_type.fields.join("\n"));
+				self.templates_box.set_text(&notes::format_templates(&note_type.templates));
+			},
+			None => {
+				self.editing = 0;
+				self.list.selected = None;
+				self.name_box.set_text("");
+				self.fields_box.set_text("");
+				self.templates_box.set_text("");
+			}
+		}
+	}
+
+	//* Draws stage 6 and returns the stage to move to, if it should change */
+	pub fn update(&mut self, conn: &Connection, assets: &StageAssets) -> Option<u8> {
+		draw_frame(assets, "Note types");
+
+		let labels: Vec<String> = self.note_types.iter()
+			.map(|note_type| format!("{} ({} fields)", note_type.name, note_type.fields.len()))
+			.collect();
+		if self.list.update_labels(&labels, "No note types yet!", assets) {
+			let picked: Option<NoteType> = self.list.selected.and_then(|index| self.note_types.get(index)).cloned();
+			self.select(picked);
+		}
+
+		// Name takes one row, fields two and card templates three
+		let name_rect: Rect = inset(box_row(0));
+		let fields_rect: Rect = inset(box_row(1).combine_with(box_row(2)));
+		let templates_rect: Rect = inset(box_row(3).combine_with(box_row(5)));
+		self.name_box.update(name_rect);
+		self.fields_box.update(fields_rect);
+		self.templates_box.update(templates_rect);
+		self.name_box.draw(name_rect, &assets.font);
+		self.fields_box.draw(fields_rect, &assets.font);
+		self.templates_box.draw(templates_rect, &assets.font);
+
+		if is_key_pressed(KeyCode::Tab) {
+			focus_next(&mut [&mut self.name_box, &mut self.fields_box, &mut self.templates_box]);
+		}
+
+		if let Some(message) = &self.message {
+			draw_row_label(message, 6, assets);
+		}
+
+		if button(bottom_button(0, 4), "Back", &assets.font) {
+			return Some(4);
+		}
+		if button(bottom_button(1, 4), "New", &assets.font) {
+			self.select(None);
+			self.message = None;
+		}
+		if button(bottom_button(2, 4), "Save", &assets.font) {
+			self.save(conn);
+		}
+		if self.editing == 0 {
+			disabled_button(bottom_button(3, 4), "Delete", &assets.font);
+		} else if button(bottom_button(3, 4), "Delete", &assets.font) {
+			self.delete(conn);
+		}
+		None
+	}
+
+	fn save(&mut self, conn: &Connection) {
+		let templates = match notes::parse_templates(&self.templates_box.text) {
+			Ok(templates) => templates,
+			Err(problem) => {
+				self.message = Some(problem);
+				return;
+			}
+		};
+		let note_type: NoteType = NoteType {
+			id: self.editing,
+			name: self.name_box.text.trim().to_owned(),
+			fields: flashcards::parse_alternates(&self.fields_box.text),
+			templates,
+		};
+		if let Err(problem) = notes::validate(&note_type) {
+			self.message = Some(problem);
+			return;
+		}
+
+		match notes::save_note_type(conn, &note_type).and_then(|id| Ok((id, notes::count_notes(conn, id)?))) {
+			Ok((id, note_count)) => {
+				info!("Note type {} saved", note_type.name);
+				self.editing = id;
+				self.message = Some(if note_count == 0 {
+					"Note type saved!".to_owned()
+				} else {
+					format!("Note type saved! The cards of its {} notes have been remade.", note_count)
+				});
+				self.reload(conn);
+			},
+			Err(e) => {
+				error!("Failed to save note type {}. Error: {:?}", note_type.name, e);
+				self.message = Some("Failed to save note type.".to_owned());
+			}
+		}
+	}
+
+	fn delete(&mut self, conn: &Connection) {
+		let result = notes::count_notes(conn, self.editing).and_then(|note_count| {
+			if note_count == 0 {
+				notes::delete_note_type(conn, self.editing)?;
+			}
+			Ok(note_count)
+		});
+		match result {
+			Ok(0) => {
+				info!("Note type {} deleted", self.editing);
+				self.message = Some("Note type deleted!".to_owned());
+				self.select(None);
+				self.reload(conn);
+			},
+			Ok(note_count) => {
+				// Deleting it would leave notes that can't make cards
+				self.message = Some(format!("{} notes use this note type, so it can't be deleted.", note_count));
+			},
+			Err(e) => {
+				error!("Failed to delete note type {}. Error: {:?}", self.editing, e);
+				self.message = Some("Failed to delete note type.".to_owned());
+			}
+		}
+	}
+}
@@ -0,0 +1,159 @@
+use macroquad::prelude::*; // Handles window display
+
+use rusqlite::Connection; // Handles SQLite database
+
+use flashcard_core::database::{self, Subject};
+use flashcard_core::formats::{self, plain, PreviewCounts, PreviewRow, RowStatus};
+
+use crate::stages::{bottom_button, box_row, card_list::CardList, draw_frame, draw_row_label, focus_next, inset, StageAssets};
+use crate::widgets::{button, disabled_button, TextBox};
+
+pub struct PasteScreen {
+	subject_box: TextBox, // Subject the cards go into, made if there isn't one with this name
+	text_box: TextBox,
+	term_box: TextBox, // Custom separators, detected from the text while empty
+	card_box: TextBox,
+	rows: Vec<PreviewRow>,
+	list: CardList,
+	message: Option<String>, // Result of the last preview/import
+}
+
+impl PasteScreen {
+	pub fn new(subject: &Subject) -> PasteScreen {
+		let mut subject_box: TextBox = TextBox::new();
+		subject_box.placeholder = "Subject name...".to_owned();
+		subject_box.set_text(&subject.name);
+		let mut term_box: TextBox = TextBox::new();
+		term_box.placeholder = "Between term and definition (\\t = tab), or empty to detect...".to_owned();
+		let mut card_box: TextBox = TextBox::new();
+		card_box.placeholder = "Between cards (\\n = new line), or empty to detect...".to_owned();
+		PasteScreen {
+			subject_box,
+			text_box: TextBox::multiline("Paste cards here, e.g. one \"term - definition\" per line..."),
+			term_box,
+			card_box,
+			rows: Vec::new(),
+			list: CardList::new(),
+			message: None,
+		}
+	}
+
+	//* Separators typed into the boxes, or detected from the text for empty boxes */
+	fn separators(&self) -> (String, String) {
+		let (detected_term, detected_card): (String, String) = plain::detect_separators(&self.text_box.text);
+		let typed = |text_box: &TextBox, detected: String| if text_box.text.is_empty() { detected } else { plain::parse_separator(&text_box.text) };
+		(typed(&self.term_box, detected_term), typed(&self.card_box, detected_card))
+	}
+
+	//* The subject with the name in the subject box, if there is one */
+	fn find_subject(&self, conn: &Connection) -> rusqlite::Result<Option<i64>> {
+		let name: &str = self.subject_box.text.trim();
+		Ok(database::get_subjects(conn)?.into_iter().find(|subject| subject.name == name).map(|subject| subject.id))
+	}
+
+	//* Reads the pasted text into preview rows, marking duplicates of cards already in the subject */
+	fn preview(&mut self, conn: &Connection) -> rusqlite::Result<()> {
+		let (term_separator, card_separator): (String, String) = self.separators();
+		self.rows = plain::preview(&self.text_box.text, &term_separator, &card_separator);
+		self.list.selected = None;
+		match self.find_subject(conn)? {
+			Some(subject_id) => formats::mark_duplicates(conn, subject_id, &mut self.rows)?,
+			None => formats::mark_duplicates_within(&mut self.rows), // A new subject has nothing to duplicate
+		}
+
+		let counts: PreviewCounts = formats::count_rows(&self.rows);
+		self.message = Some(format!(
+			"Term: {}, cards: {}. {} ready, {} duplicates and {} invalid.",
+			plain::separator_name(&term_separator), plain::separator_name(&card_separator), counts.ready, counts.duplicates, counts.invalid
+		));
+		Ok(())
+	}
+
+	//* Draws stage 8 and returns the stage to move to, if it should change */
+	pub fn update(&mut self, conn: &Connection, assets: &StageAssets) -> Option<u8> {
+		draw_frame(assets, "Paste flashcards");
+
+		let labels: Vec<String> = self.rows.iter()
+			.map(|row| format!("{} = {}", row.label(), row.card.answer))
+			.collect();
+		self.list.update_labels(&labels, "Preview the pasted text to see its cards.", assets);
+
+		let subject_rect: Rect = inset(box_row(0));
+		let text_rect: Rect = inset(box_row(1).combine_with(box_row(3)));
+		let term_rect: Rect = inset(box_row(4));
+		let card_rect: Rect = inset(box_row(5));
+		if is_key_pressed(KeyCode::Tab) {
+			focus_next(&mut [&mut self.subject_box, &mut self.text_box, &mut self.term_box, &mut self.card_box]);
+		}
+		for (text_box, rect) in [
+			(&mut self.subject_box, subject_rect), (&mut self.text_box, text_rect), (&mut self.term_box, term_rect), (&mut self.card_box, card_rect),
+		] {
+			text_box.update(rect);
+			text_box.draw(rect, &assets.font);
+		}
+
+		// The row picked from the list, otherwise the result of the last preview/import
+		match self.list.selected.and_then(|index| self.rows.get(index)) {
+			Some(row) => {
+				let status: String = match &row.status {
+					RowStatus::Ready => "Will be added".to_owned(),
+					RowStatus::Duplicate => "Skipped, already in the subject".to_owned(),
+					RowStatus::Invalid(problem) => format!("Skipped: {}", problem),
+					RowStatus::Update(_) | RowStatus::Unchanged => "Already in the subject".to_owned(), // Pasted text has no card ids
+				};
+				draw_row_label(&format!("{} = {} ({})", row.card.question, row.card.answer, status), 6, assets);
+			},
+			None => {
+				if let Some(message) = &self.message {
+					draw_row_label(message, 6, assets);
+				}
+			},
+		}
+
+		if button(bottom_button(0, 3), "Back", &assets.font) {
+			return Some(7);
+		}
+		if button(bottom_button(1, 3), "Preview", &assets.font) {
+			if let Err(e) = self.preview(conn) {
+				error!("Failed to preview pasted text. Error: {:?}", e);
+				self.message = Some("Failed to check for duplicates.".to_owned());
+			}
+		}
+		if formats::count_rows(&self.rows).ready == 0 {
+			disabled_button(bottom_button(2, 3), "Import", &assets.font);
+		} else if button(bottom_button(2, 3), "Import", &assets.font) {
+			self.import(conn);
+		}
+		None
+	}
+
+	fn import(&mut self, conn: &Connection) {
+		let name: String = self.subject_box.text.trim().to_owned();
+		if name.is_empty() {
+			self.message = Some("Type the name of the subject to import into.".to_owned());
+			return;
+		}
+
+		let result: rusqlite::Result<(usize, PreviewCounts)> = database::get_or_add_subject(conn, &name).and_then(|subject_id| {
+			self.preview(conn)?; // The text or subject may have changed since the preview
+			let counts: PreviewCounts = formats::count_rows(&self.rows);
+			Ok((formats::import_rows(conn, subject_id, &self.rows)?, counts))
+		});
+		match result {
+			Ok((added, counts)) => {
+				info!("Imported {} pasted flashcards into {}", added, name);
+				self.message = Some(format!(
+					"Imported {} flashcards into {}. Skipped {} duplicates and {} invalid cards.",
+					added, name, counts.duplicates, counts.invalid
+				));
+				// Importing again would only find duplicates
+				self.rows.clear();
+				self.text_box.set_text("");
+			},
+			Err(e) => {
+				error!("Failed to import pasted text into {}. Error: {:?}", name, e);
+				self.message = Some("Failed to import, nothing was added.".to_owned());
+			}
+		}
+	}
+}
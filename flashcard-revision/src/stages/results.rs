@@ -0,0 +1,99 @@
+use macroquad::prelude::*; // Handles window display
+
+use rusqlite::Connection; // Handles SQLite database
+
+use flashcard_core::matching::MatchOptions;
+use flashcard_core::revision::RevisionSummary;
+
+use crate::stages::{bottom_button, draw_frame, draw_row_label, revision::RevisionScreen, StageAssets};
+use crate::widgets::button;
+
+const VISIBLE_LINES: usize = 5; // Rows 2 to 6 of the flashcard box hold the breakdown
+
+pub struct ResultsScreen {
+	pub summary: RevisionSummary,
+	breakdown: Vec<String>, // Learning progress breakdown, one line per row
+	scroll: usize, // First breakdown line shown
+}
+
+impl ResultsScreen {
+	pub fn new(summary: RevisionSummary) -> ResultsScreen {
+		let mut breakdown: Vec<String> = vec!["Cards moving upwards;".to_owned()];
+		if summary.moved_up.is_empty() {
+			breakdown.push("None!".to_owned());
+		}
+		breakdown.extend(summary.moved_up.iter().map(|question| format!("- {}", question)));
+
+		breakdown.push("Cards moving down;".to_owned());
+		if summary.moved_down.is_empty() {
+			breakdown.push("None!".to_owned());
+		}
+		breakdown.extend(summary.moved_down.iter().map(|question| format!("- {}", question)));
+
+		ResultsScreen {
+			summary,
+			breakdown,
+			scroll: 0,
+		}
+	}
+
+	//* Draws stage 3 and returns the stage to move to, if it should change */
+	pub fn update(&mut self, assets: &StageAssets) -> Option<u8> {
+		draw_frame(assets, &format!("{}: Post flashcard breakdown", self.summary.subject.name));
+
+		if self.summary.cards_practised == 0 {
+			draw_row_label("No cards practiced!", 0, assets);
+		} else {
+			let cards: &str = if self.summary.cards_practised == 1 { "card" } else { "cards" };
+			draw_row_label(
+				&format!(
+					"You practiced {0} {1}, and got {2} of those correct. That's {3:.0}%!",
+					self.summary.cards_practised, cards, self.summary.correct_total, self.summary.percent_accuracy()
+				),
+				0,
+				assets,
+			);
+			draw_row_label(
+				&format!(
+					"Subject accuracy: {:.0}% -> {:.0}%",
+					self.summary.accuracy_before * 100.0, self.summary.accuracy_after * 100.0
+				),
+				1,
+				assets,
+			);
+
+			// Scrollable list of cards moving up/down
+			let max_scroll: usize = self.breakdown.len().saturating_sub(VISIBLE_LINES);
+			let wheel: f32 = mouse_wheel().1;
+			if (wheel < 0.0 || is_key_pressed(KeyCode::Down)) && self.scroll < max_scroll {
+				self.scroll += 1;
+			} else if (wheel > 0.0 || is_key_pressed(KeyCode::Up)) && self.scroll > 0 {
+				self.scroll -= 1;
+			}
+
+			for (row, line) in self.breakdown.iter().skip(self.scroll).take(VISIBLE_LINES).enumerate() {
+				draw_row_label(line, row as u8 + 2, assets);
+			}
+		}
+
+		if self.summary.missed.is_empty() {
+			if button(bottom_button(0, 1), "Back to subjects", &assets.font) {
+				return Some(0);
+			}
+		} else {
+			if button(bottom_button(0, 2), &format!("Revise missed ({})", self.summary.missed.len()), &assets.font) {
+				return Some(2);
+			}
+			if button(bottom_button(1, 2), "Back to subjects", &assets.font) {
+				return Some(0);
+			}
+		}
+		None
+	}
+
+	//* Builds a revision stage holding only the cards missed this session */
+	pub fn missed_cards_screen(&self, conn: &Connection, matching: MatchOptions) -> RevisionScreen {
+		// Missed cards have already been rescheduled, so this is extra practice
+		RevisionScreen::with_flashcards(conn, &self.summary.subject, matching, self.summary.missed.clone())
+	}
+}
@@ -0,0 +1,386 @@
+use chrono::{Local, TimeZone}; // Handles due dates
+
+use macroquad::prelude::*; // Handles window display
+
+use rusqlite::Connection; // Handles SQLite database
+
+use flashcard_core::database::Subject;
+use flashcard_core::flashcards::{self, Flashcard};
+use flashcard_core::matching::{diff, AnswerMatch, MatchOptions};
+use flashcard_core::revision::{bury_siblings, RevisionSession, RevisionSummary};
+use flashcard_core::scheduler::{self, tier_name, Grade};
+
+use crate::stages::{bottom_button, box_row, draw_frame, draw_row_label, inset, StageAssets, BOX_WIDTH, BOX_X, BOX_Y, ROW_HEIGHT};
+use crate::widgets::{button, canvas_scale, draw_coloured_label, draw_label, suggested_button, wrap_text, TextBox, PURPLE};
+
+// Steps of stage 2, in the order they are normally seen
+#[derive(Clone)]
+enum Phase {
+	Overview, // Cards due today and the subject's tiers
+	Answering,
+	Grading { result: AnswerMatch, closest: String }, // Matching answers suggest Good
+	Feedback { grade: Grade, accuracy: f64 },
+	Finished, // Moves on to the results stage
+}
+
+// Counts shown on the overview, worked out from every card of the subject
+struct Overview {
+	due_today: usize,
+	buried: usize, // Due today, but a sibling is revised first
+	tiers: [usize; 3], // Cards in the weak, learning and strong tiers
+	next_due: Option<i64>, // Seconds since epoch
+}
+
+pub struct RevisionScreen {
+	pub subject: Subject,
+	pub session: Option<RevisionSession>,
+	phase: Phase,
+	overview: Option<Overview>,
+	answer_box: TextBox,
+	options: Vec<String>, // Options of a multiple choice card (Empty = typed answer)
+	matching: MatchOptions, // How forgiving answer checking is
+	shown_at: f64, // Seconds since the app started when the current question was shown
+	response_ms: i64, // Time taken to submit the current answer
+	message: Option<String>, // Shown on the overview (e.g. nothing due)
+	pub summary: Option<RevisionSummary>, // Set once the session is finished
+}
+
+impl RevisionScreen {
+	pub fn new(conn: &Connection, subject: &Subject, matching: MatchOptions) -> RevisionScreen {
+		let mut screen: RevisionScreen = RevisionScreen {
+			subject: subject.clone(),
+			session: None,
+			phase: Phase::Overview,
+			overview: None,
+			answer_box: TextBox::new(),
+			options: Vec::new(),
+			matching,
+			shown_at: 0.0,
+			response_ms: 0,
+			message: None,
+			summary: None,
+		};
+		screen.refresh(conn);
+		screen
+	}
+
+	//* Skips the overview and revises the given flashcards straight away */
+	pub fn with_flashcards(conn: &Connection, subject: &Subject, matching: MatchOptions, flashcards: Vec<Flashcard>) -> RevisionScreen {
+		let mut screen: RevisionScreen = RevisionScreen::new(conn, subject, matching);
+		match RevisionSession::with_flashcards(conn, subject, flashcards) {
+			Ok(session) => {
+				screen.session = Some(session);
+				screen.next_card(conn);
+			},
+			Err(e) => {
+				error!("Failed to start revision of {}. Error: {:?}", subject.name, e);
+				screen.message = Some("Failed to load flashcards.".to_owned());
+			}
+		}
+		screen
+	}
+
+	//* Recounts the overview, e.g. after cards have been added or edited */
+	pub fn refresh(&mut self, conn: &Connection) {
+		let result = flashcards::load_all_flashcards(conn, self.subject.id)
+			.and_then(|all| Ok((all, flashcards::next_due(conn, self.subject.id)?)));
+
+		match result {
+			Ok((all, next_due)) => {
+				let end_of_today: i64 = scheduler::end_of_today();
+				let mut tiers: [usize; 3] = [0; 3];
+				for flashcard in all.iter() {
+					tiers[flashcard.category() as usize] += 1;
+				}
+				let due: Vec<Flashcard> = all.iter().filter(|flashcard| flashcard.schedule.due < end_of_today).cloned().collect();
+				let due_count: usize = due.len();
+				let due_today: usize = bury_siblings(due, &all, scheduler::start_of_today()).len();
+				self.overview = Some(Overview {
+					due_today,
+					buried: due_count - due_today,
+					tiers,
+					next_due,
+				});
+			},
+			Err(e) => {
+				error!("Failed to count flashcards for {}. Error: {:?}", self.subject.name, e);
+				self.message = Some("Failed to load flashcards.".to_owned());
+				self.overview = None;
+			}
+		}
+	}
+
+	//* Draws stage 2 and returns the stage to move to, if it should change */
+	pub fn update(&mut self, conn: &Connection, assets: &StageAssets) -> Option<u8> {
+		match self.phase.clone() {
+			Phase::Overview => self.overview(conn, assets),
+			Phase::Answering => {
+				self.answer(conn, assets);
+				None
+			},
+			Phase::Grading { result, closest } => {
+				self.grade(conn, assets, result, &closest);
+				None
+			},
+			Phase::Feedback { grade, accuracy } => {
+				self.feedback(conn, assets, grade, accuracy);
+				None
+			},
+			Phase::Finished => Some(3),
+		}
+	}
+
+	fn overview(&mut self, conn: &Connection, assets: &StageAssets) -> Option<u8> {
+		draw_frame(assets, &format!("{}: Due today", self.subject.name));
+
+		let mut start: bool = false;
+		if let Some(overview) = &self.overview {
+			if overview.due_today > 0 {
+				let cards: &str = if overview.due_today == 1 { "card" } else { "cards" };
+				start = button(inset(box_row(1)), &format!("Revise {} {} due today", overview.due_today, cards), &assets.font);
+			} else {
+				let next: String = match overview.next_due.and_then(|due| Local.timestamp_opt(due, 0).single()) {
+					Some(date) => format!("Nothing due today! Next card is due {}.", date.format("%d/%m/%Y")),
+					None => "No cards yet! Add some to start revising.".to_owned(),
+				};
+				draw_row_label(&next, 1, assets);
+			}
+			if overview.buried > 0 {
+				let cards: &str = if overview.buried == 1 { "card is" } else { "cards are" };
+				draw_row_label(&format!("{} sibling {} buried until tomorrow.", overview.buried, cards), 2, assets);
+			}
+
+			draw_row_label(
+				&format!(
+					"{}: {} | {}: {} | {}: {}",
+					tier_name(0), overview.tiers[0], tier_name(1), overview.tiers[1], tier_name(2), overview.tiers[2]
+				),
+				3,
+				assets,
+			);
+		}
+		if start {
+			self.start(conn);
+		}
+
+		if let Some(message) = &self.message {
+			draw_row_label(message, 5, assets);
+		}
+
+		if button(bottom_button(0, 3), "Back", &assets.font) {
+			return Some(0);
+		}
+		if button(bottom_button(1, 3), "Add/Remove cards", &assets.font) {
+			return Some(4);
+		}
+		if button(bottom_button(2, 3), "Edit cards", &assets.font) {
+			return Some(5);
+		}
+		None
+	}
+
+	fn start(&mut self, conn: &Connection) {
+		match RevisionSession::new(conn, &self.subject) {
+			Ok(session) => {
+				if session.flashcards.is_empty() {
+					self.message = Some("No cards due today!".to_owned());
+				} else {
+					info!("Revising {} cards from {}", session.flashcards.len(), self.subject.name);
+					self.session = Some(session);
+					self.next_card(conn);
+				}
+			},
+			Err(e) => {
+				error!("Failed to load flashcards for {}. Error: {:?}", self.subject.name, e);
+				self.message = Some("Failed to load flashcards.".to_owned());
+			}
+		}
+	}
+
+	fn next_card(&mut self, conn: &Connection) {
+		let session: &mut RevisionSession = match self.session.as_mut() {
+			Some(session) => session,
+			None => return,
+		};
+
+		if session.next_flashcard().is_some() {
+			self.options = session.options(&self.matching);
+			self.answer_box = TextBox::new();
+			self.shown_at = get_time();
+			self.phase = Phase::Answering;
+		} else {
+			self.finish(conn);
+		}
+	}
+
+	//* Stores the revision dates and builds the summary for stage 3 */
+	fn finish(&mut self, conn: &Connection) {
+		if let Some(session) = self.session.as_mut() {
+			if let Err(e) = session.finish(conn) {
+				error!("Failed to store revision dates. Error: {:?}", e);
+			}
+			match session.summary(conn) {
+				Ok(summary) => self.summary = Some(summary),
+				Err(e) => error!("Failed to summarise revision. Error: {:?}", e),
+			}
+		}
+		self.phase = Phase::Finished;
+	}
+
+	fn record(&mut self, conn: &Connection, grade: Grade) {
+		if let Some(session) = self.session.as_mut() {
+			match session.record_answer(conn, grade, self.response_ms) {
+				Ok(accuracy) => self.phase = Phase::Feedback { grade, accuracy },
+				Err(e) => {
+					error!("Failed to record answer. Error: {:?}", e);
+					self.phase = Phase::Feedback { grade, accuracy: 0.0 };
+				}
+			}
+		}
+	}
+
+	fn draw_question(&self, assets: &StageAssets) {
+		let question: String = match self.session.as_ref().and_then(|session| session.current_flashcard()) {
+			Some(flashcard) => flashcard.display_question(),
+			None => return,
+		};
+
+		// Question uses the first two rows
+		let lines: Vec<String> = wrap_text(&question, (BOX_WIDTH - 120.0) * canvas_scale(), 90.0, &assets.font);
+		for (index, line) in lines.iter().take(3).enumerate() {
+			draw_label(line, BOX_X + 60.0, BOX_Y + 40.0 + 120.0 * index as f32, 90.0, BLACK, &assets.font);
+		}
+	}
+
+	fn title(&self) -> String {
+		match &self.session {
+			Some(session) => format!("{}: {} of {}", self.subject.name, session.cards_done + 1, session.flashcards.len()),
+			None => self.subject.name.clone(),
+		}
+	}
+
+	fn answer(&mut self, conn: &Connection, assets: &StageAssets) {
+		draw_frame(assets, &self.title());
+		self.draw_question(assets);
+
+		let submitted: bool = if self.options.is_empty() {
+			let answer_rect: Rect = inset(box_row(3));
+			self.answer_box.update(answer_rect);
+			self.answer_box.draw(answer_rect, &assets.font);
+
+			button(bottom_button(1, 2), "Check", &assets.font) || is_key_pressed(KeyCode::Enter)
+		} else {
+			// One option per row, picked by clicking or with 1-4 on the keyboard (Or number pad)
+			let mut picked: Option<String> = None;
+			for (position, option) in self.options.iter().enumerate() {
+				let label: String = format!("{}) {}", position + 1, option.replace('\n', " "));
+				if button(inset(box_row(2 + position as u8)), &label, &assets.font) || number_pressed(position + 1) {
+					picked = Some(option.clone());
+				}
+			}
+			if let Some(option) = &picked {
+				self.answer_box.set_text(option); // Graded like a typed answer
+			}
+			picked.is_some()
+		};
+
+		if button(bottom_button(0, 2), "Finish", &assets.font) {
+			// Ends the session early, keeping the answers given so far
+			self.finish(conn);
+		} else if submitted {
+			self.response_ms = ((get_time() - self.shown_at) * 1000.0) as i64;
+			let (result, closest): (AnswerMatch, String) = self.session.as_ref()
+				.map(|session| session.check_answer(&self.answer_box.text, &self.matching))
+				.unwrap_or((AnswerMatch::Wrong, String::new()));
+			self.phase = Phase::Grading { result, closest };
+		}
+	}
+
+	//* Every accepted answer of the current flashcard, separated by slashes */
+	fn current_answer(&self) -> String {
+		self.session.as_ref()
+			.and_then(|session| session.current_flashcard())
+			.map(|flashcard| flashcard.accepted_answers().join(" / "))
+			.unwrap_or_default()
+	}
+
+	fn grade(&mut self, conn: &Connection, assets: &StageAssets, result: AnswerMatch, closest: &str) {
+		draw_frame(assets, &self.title());
+		self.draw_question(assets);
+
+		// Mistakes in the typed answer are red, parts of the real answer that were missed are purple
+		let (typed, answer) = diff(&self.answer_box.text, closest);
+		let mut typed_runs: Vec<(String, Color)> = vec![("Your answer: ".to_owned(), BLACK)];
+		typed_runs.extend(typed.into_iter().map(|segment| (segment.text, if segment.matched { BLACK } else { RED })));
+		let mut answer_runs: Vec<(String, Color)> = vec![("Actual answer: ".to_owned(), BLACK)];
+		answer_runs.extend(answer.into_iter().map(|segment| (segment.text, if segment.matched { BLACK } else { PURPLE })));
+		draw_coloured_label(&typed_runs, BOX_X + 60.0, BOX_Y + ROW_HEIGHT * 3.0 + 50.0, 80.0, &assets.font);
+		draw_coloured_label(&answer_runs, BOX_X + 60.0, BOX_Y + ROW_HEIGHT * 4.0 + 50.0, 80.0, &assets.font);
+
+		let suggest_good: bool = result != AnswerMatch::Wrong;
+		match result {
+			AnswerMatch::Exact => draw_row_label("Correct! Press enter for Good, or pick a grade (1-4)", 5, assets),
+			AnswerMatch::Close => draw_row_label("Close enough! Press enter for Good, or pick a grade (1-4)", 5, assets),
+			AnswerMatch::Wrong => draw_row_label("How well did you remember it? (1-4)", 5, assets),
+		}
+
+		// One button per grade, with 1-4 on the keyboard (Or number pad) as shortcuts
+		let mut chosen: Option<Grade> = None;
+		for (position, grade) in Grade::ALL.iter().enumerate() {
+			let rect: Rect = bottom_button(position as u8, 4);
+			let label: String = format!("{} ({})", grade.name(), position + 1);
+			let clicked: bool = if suggest_good && *grade == Grade::Good {
+				suggested_button(rect, &label, &assets.font)
+			} else {
+				button(rect, &label, &assets.font)
+			};
+			if clicked || number_pressed(position + 1) {
+				chosen = Some(*grade);
+			}
+		}
+		if suggest_good && is_key_pressed(KeyCode::Enter) {
+			chosen = Some(Grade::Good);
+		}
+
+		if let Some(grade) = chosen {
+			self.record(conn, grade);
+		}
+	}
+
+	fn feedback(&mut self, conn: &Connection, assets: &StageAssets, grade: Grade, accuracy: f64) {
+		draw_frame(assets, &self.title());
+		self.draw_question(assets);
+
+		let interval: i32 = self.session.as_ref()
+			.and_then(|session| session.current_flashcard())
+			.map(|flashcard| flashcard.schedule.interval)
+			.unwrap_or(0);
+		let message: String = if grade == Grade::Again {
+			format!("Whoops! Your accuracy is now {:.0}%.", accuracy * 100.0)
+		} else {
+			format!("Well done! Your accuracy is now {:.0}%.", accuracy * 100.0)
+		};
+		let days: &str = if interval == 1 { "day" } else { "days" };
+		draw_row_label(&format!("Answer: {}", self.current_answer()), 3, assets);
+		draw_row_label(&message, 4, assets);
+		draw_row_label(&format!("Graded {}, due again in {} {}.", grade.name(), interval, days), 5, assets);
+
+		if button(bottom_button(0, 1), "Next", &assets.font) || is_key_pressed(KeyCode::Enter) {
+			self.next_card(conn);
+		}
+	}
+}
+
+//* Whether 1-4 was pressed on the keyboard or number pad */
+fn number_pressed(number: usize) -> bool {
+	let keys: [(KeyCode, KeyCode); 4] = [
+		(KeyCode::Key1, KeyCode::Kp1),
+		(KeyCode::Key2, KeyCode::Kp2),
+		(KeyCode::Key3, KeyCode::Kp3),
+		(KeyCode::Key4, KeyCode::Kp4),
+	];
+	match keys.get(number.wrapping_sub(1)) {
+		Some((key, keypad)) => is_key_pressed(*key) || is_key_pressed(*keypad),
+		None => false,
+	}
+}
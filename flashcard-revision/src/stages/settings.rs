@@ -0,0 +1,301 @@
+use std::thread::{self, JoinHandle}; // Handles optimising FSRS without pausing the window
+
+use macroquad::prelude::*; // Handles window display
+
+use rusqlite::Connection; // Handles SQLite database
+
+use flashcard_core::database::{self, Subject};
+use flashcard_core::fsrs::{self, WEIGHT_COUNT};
+use flashcard_core::reviews;
+use flashcard_core::scheduler::Algorithm;
+use flashcard_core::settings::{check_settings, save_settings, validate_number_of_subjects, Settings};
+
+use crate::stages::{bottom_button, box_row, draw_frame, draw_row_label, inset, StageAssets, BOX_X, BOX_Y, ROW_HEIGHT};
+use crate::widgets::{button, canvas_rect, draw_label, draw_stage_element, TextBox};
+
+const VISIBLE_SUBJECTS: usize = 5; // Rows 0 to 4 of the scheduling page
+
+// Optimised weights and the reviews they were fitted to, or the message to show
+type OptimiseResult = Result<([f64; WEIGHT_COUNT], usize), String>;
+
+// Pages of stage 1, in the order the page button cycles through them
+#[derive(Clone, Copy, PartialEq)]
+enum Page {
+	General,
+	Answers,
+	Scheduling,
+}
+
+impl Page {
+	fn name(&self) -> &'static str {
+		match self {
+			Page::General => "General",
+			Page::Answers => "Answers",
+			Page::Scheduling => "Scheduling",
+		}
+	}
+
+	fn next(&self) -> Page {
+		match self {
+			Page::General => Page::Answers,
+			Page::Answers => Page::Scheduling,
+			Page::Scheduling => Page::General,
+		}
+	}
+}
+
+pub struct SettingsScreen {
+	pub settings: Settings, // Settings as saved in settings.toml
+	pending: Settings, // Settings as shown on screen
+	number_box: TextBox,
+	pub problems: Vec<String>, // Problems found while loading settings.toml
+	subjects: Vec<Subject>, // Subjects as saved in the database
+	pending_algorithms: Vec<Algorithm>, // Algorithm shown on screen for each subject
+	page: Page,
+	scroll: usize, // First subject shown on the scheduling page
+	message: Option<String>,
+	optimising: Option<JoinHandle<OptimiseResult>>, // FSRS optimisation running in the background
+}
+
+impl SettingsScreen {
+	pub fn new(conn: &Connection, settings: &Settings, problems: &[String]) -> SettingsScreen {
+		let mut number_box: TextBox = TextBox::new();
+		number_box.focused = false;
+		number_box.set_text(&settings.number_of_subjects.to_string());
+
+		let mut message: Option<String> = None;
+		let subjects: Vec<Subject> = database::get_subjects(conn).unwrap_or_else(|e| {
+			error!("Failed to load subjects. Error: {:?}", e);
+			message = Some("Failed to load subjects.".to_owned());
+			Vec::new()
+		});
+
+		SettingsScreen {
+			settings: settings.clone(),
+			pending: settings.clone(),
+			number_box,
+			problems: problems.to_vec(),
+			pending_algorithms: subjects.iter().map(|subject| subject.algorithm).collect(),
+			subjects,
+			page: Page::General,
+			scroll: 0,
+			message,
+			optimising: None,
+		}
+	}
+
+	//* Draws stage 1 and returns the stage to move to, if it should change */
+	pub fn update(&mut self, conn: &Connection, assets: &StageAssets) -> Option<u8> {
+		match self.page {
+			Page::General => {
+				draw_frame(assets, "Settings");
+				self.general_page(assets);
+			},
+			Page::Answers => {
+				draw_frame(assets, "Settings: Answers");
+				self.answers_page(assets);
+			},
+			Page::Scheduling => {
+				draw_frame(assets, "Settings: Scheduling");
+				self.scheduling_page(conn, assets);
+			},
+		}
+		let number_result: Result<u16, String> = validate_number_of_subjects(&self.number_box.text);
+		self.poll_optimising();
+
+		if let Some(message) = &self.message {
+			draw_row_label(message, 6, assets);
+		}
+
+		if button(bottom_button(0, 3), "Back", &assets.font) {
+			// Unsaved changes are dropped
+			if self.pending.fullscreen != self.settings.fullscreen {
+				apply_fullscreen(self.settings.fullscreen);
+			}
+			return Some(0);
+		}
+		if button(bottom_button(1, 3), self.page.next().name(), &assets.font) {
+			self.page = self.page.next();
+		}
+		if button(bottom_button(2, 3), "Save", &assets.font) {
+			match number_result {
+				Ok(_) => self.save(conn),
+				Err(_) => self.message = Some("Fix the highlighted settings before saving.".to_owned()),
+			}
+		}
+		None
+	}
+
+	fn save(&mut self, conn: &Connection) {
+		save_settings(self.pending.to_table());
+		info!("Settings saved: {:?}", self.pending);
+		self.settings = self.pending.clone();
+		self.problems.clear(); // settings.toml has been rewritten with valid values
+
+		for (subject, algorithm) in self.subjects.iter_mut().zip(self.pending_algorithms.iter()) {
+			if subject.algorithm == *algorithm {
+				continue;
+			}
+			match database::set_algorithm(conn, subject.id, *algorithm) {
+				Ok(()) => {
+					info!("{} now scheduled by {}", subject.name, algorithm.display_name());
+					subject.algorithm = *algorithm;
+				},
+				Err(e) => {
+					error!("Failed to change the algorithm of {}. Error: {:?}", subject.name, e);
+					self.message = Some("Failed to save scheduling.".to_owned());
+					return;
+				}
+			}
+		}
+		self.message = Some("Settings saved!".to_owned());
+	}
+
+	fn general_page(&mut self, assets: &StageAssets) {
+		// # Fullscreen #
+		draw_row_label("Fullscreen", 0, assets);
+		let toggle_label: &str = if self.pending.fullscreen { "On" } else { "Off" };
+		if button(inset(right_half(box_row(0))), toggle_label, &assets.font) {
+			self.pending.fullscreen = !self.pending.fullscreen;
+			apply_fullscreen(self.pending.fullscreen); // Live apply
+		}
+
+		// # Number of subjects #
+		draw_row_label("Number of subjects", 1, assets);
+		let number_rect: Rect = inset(right_half(box_row(1)));
+		self.number_box.update(number_rect);
+		self.number_box.draw(number_rect, &assets.font);
+		let number_result: Result<u16, String> = validate_number_of_subjects(&self.number_box.text);
+		if let Ok(number) = number_result {
+			self.pending.number_of_subjects = number;
+		}
+
+		if button(inset(right_half(box_row(2))), &format!("Match database ({})", self.subjects.len()), &assets.font) {
+			self.number_box.set_text(&self.subjects.len().to_string());
+		}
+
+		// # Validation and warnings #
+		let mut warnings: Vec<String> = Vec::new();
+		if let Err(e) = &number_result {
+			warnings.push(e.clone());
+		}
+		warnings.extend(self.problems.iter().cloned());
+		warnings.extend(check_settings(&self.pending, self.subjects.len()));
+
+		for (row, warning) in warnings.iter().take(3).enumerate() {
+			let row: u8 = row as u8 + 3;
+			draw_stage_element(
+				&assets.settings_notification,
+				canvas_rect(BOX_X + 40.0, BOX_Y + ROW_HEIGHT * row as f32 + 49.0, 110.0, 110.0),
+			);
+			draw_label_after_icon(warning, row, assets);
+		}
+
+	}
+
+	fn answers_page(&mut self, assets: &StageAssets) {
+		let matching: [(&str, &mut bool); 4] = [
+			("Ignore accents", &mut self.pending.matching.ignore_accents),
+			("Ignore punctuation", &mut self.pending.matching.ignore_punctuation),
+			("Ignore a/an/the", &mut self.pending.matching.ignore_articles),
+			("Allow typos", &mut self.pending.matching.typo_tolerance),
+		];
+		for (row, (label, value)) in matching.into_iter().enumerate() {
+			draw_row_label(label, row as u8, assets);
+			if button(inset(right_half(box_row(row as u8))), if *value { "On" } else { "Off" }, &assets.font) {
+				*value = !*value;
+			}
+		}
+	}
+
+	fn scheduling_page(&mut self, conn: &Connection, assets: &StageAssets) {
+		if self.subjects.is_empty() {
+			draw_row_label("No subjects yet!", 0, assets);
+		}
+
+		let max_scroll: usize = self.subjects.len().saturating_sub(VISIBLE_SUBJECTS);
+		let wheel: f32 = mouse_wheel().1;
+		if wheel < 0.0 && self.scroll < max_scroll {
+			self.scroll += 1;
+		} else if wheel > 0.0 && self.scroll > 0 {
+			self.scroll -= 1;
+		}
+
+		for (row, index) in (self.scroll..self.subjects.len()).take(VISIBLE_SUBJECTS).enumerate() {
+			draw_row_label(&self.subjects[index].name, row as u8, assets);
+			let algorithm: Algorithm = self.pending_algorithms[index];
+			if button(inset(right_half(box_row(row as u8))), algorithm.display_name(), &assets.font) {
+				self.pending_algorithms[index] = algorithm.toggled();
+			}
+		}
+
+		draw_row_label("FSRS weights", 5, assets);
+		let label: &str = if self.optimising.is_some() { "Optimising..." } else { "Optimise" };
+		if button(inset(right_half(box_row(5))), label, &assets.font) && self.optimising.is_none() {
+			self.optimise_fsrs(conn);
+		}
+	}
+
+	//* Starts fitting the FSRS weights to the review log on another thread, as it can take seconds */
+	fn optimise_fsrs(&mut self, conn: &Connection) {
+		// Connections can't be shared between threads, so the thread opens its own
+		let path: String = conn.path().unwrap_or(database::DATABASE_PATH).to_owned();
+		self.message = Some("Optimising FSRS...".to_owned());
+		self.optimising = Some(thread::spawn(move || optimise_in_background(&path)));
+	}
+
+	//* Shows the result of optimising once the thread is done (Checked every frame) */
+	fn poll_optimising(&mut self) {
+		if !self.optimising.as_ref().is_some_and(|handle| handle.is_finished()) {
+			return;
+		}
+		let result: OptimiseResult = match self.optimising.take().map(|handle| handle.join()) {
+			Some(Ok(result)) => result,
+			_ => Err("Failed to optimise FSRS.".to_owned()), // The thread panicked
+		};
+		match result {
+			Ok((optimised, scored)) => {
+				info!("FSRS weights optimised on {} reviews: {:?}", scored, optimised);
+				self.message = Some(format!("FSRS optimised on {} reviews!", scored));
+			},
+			Err(e) => self.message = Some(e),
+		}
+	}
+}
+
+//* Fits the FSRS weights to the review log of the database at `path` and stores them */
+fn optimise_in_background(path: &str) -> OptimiseResult {
+	let conn: Connection = database::open_database(path).map_err(|e| {
+		error!("Failed to open the database to optimise FSRS. Error: {:?}", e);
+		"Failed to load the review log.".to_owned()
+	})?;
+	let (log, weights) = reviews::load_reviews(&conn)
+		.and_then(|log| Ok((log, fsrs::load_weights(&conn)?)))
+		.map_err(|e| {
+			error!("Failed to load the review log. Error: {:?}", e);
+			"Failed to load the review log.".to_owned()
+		})?;
+
+	let (optimised, scored): ([f64; WEIGHT_COUNT], usize) = fsrs::optimise(&log, weights)?;
+	fsrs::save_weights(&conn, &optimised, scored).map_err(|e| {
+		error!("Failed to save FSRS weights. Error: {:?}", e);
+		"Failed to save FSRS weights.".to_owned()
+	})?;
+	Ok((optimised, scored))
+}
+
+//* Switches fullscreen on or off straight away */
+pub fn apply_fullscreen(fullscreen: bool) {
+	set_fullscreen(fullscreen);
+	if !fullscreen {
+		request_new_screen_size(984.0, 668.0); // Back to the windowed size from conf()
+	}
+}
+
+fn right_half(rect: Rect) -> Rect {
+	Rect::new(rect.x + rect.w / 2.0, rect.y, rect.w / 2.0, rect.h)
+}
+
+fn draw_label_after_icon(text: &str, row: u8, assets: &StageAssets) {
+	draw_label(text, BOX_X + 180.0, BOX_Y + ROW_HEIGHT * row as f32 + 50.0, 70.0, BLACK, &assets.font);
+}
@@ -0,0 +1,373 @@
+use macroquad::prelude::*; // Handles window display
+
+// Colours shared by every stage (Taken from the stage textures)
+pub const PURPLE: Color = Color::new(0.4, 0.165, 0.98, 1.0); //rgb(102, 42, 250)
+pub const DARK_PURPLE: Color = Color::new(0.25, 0.1, 0.6, 1.0); //rgb(64, 26, 153)
+pub const GRAY: Color = Color::new(0.5, 0.5, 0.5, 1.0); //rgb(128, 128, 128)
+
+/* All stage elements are designed on a 3840x2160 canvas (Same as the stage textures)
+and are drawn at the width of the window. */
+const CANVAS_WIDTH: f32 = 3840.0;
+
+pub fn canvas_scale() -> f32 {
+	screen_width() / CANVAS_WIDTH
+}
+
+//* Converts a rectangle on the design canvas into window coordinates */
+pub fn canvas_rect(x: f32, y: f32, w: f32, h: f32) -> Rect {
+	let scale: f32 = canvas_scale();
+	Rect::new(x * scale, y * scale, w * scale, h * scale)
+}
+
+//* Converts a font size on the design canvas into a window font size */
+pub fn canvas_font_size(size: f32) -> u16 {
+	(size * canvas_scale()).max(1.0) as u16
+}
+
+pub fn get_centre(font: &Font, font_size: u16, text: &str) -> Vec2 {
+	get_text_center(
+		text,
+		Some(font),
+		font_size,
+		1.0,
+		0.0)
+}
+
+pub fn get_length(text: &str, font_size: u16, font: &Font) -> TextDimensions {
+	measure_text(
+		text,
+		Some(font),
+		font_size,
+		1.0)
+}
+
+//* Draws text with its top left corner at (x, y) on the design canvas */
+pub fn draw_label(text: &str, x: f32, y: f32, size: f32, colour: Color, font: &Font) {
+	let font_size: u16 = canvas_font_size(size);
+	let scale: f32 = canvas_scale();
+	draw_text_ex(
+		text,
+		x * scale,
+		y * scale + get_length(text, font_size, font).offset_y,
+		TextParams {
+			font: Some(font),
+			font_size,
+			color: colour,
+			..Default::default()
+		},
+	);
+}
+
+//* Draws runs of differently coloured text one after another, starting at (x, y) on the design canvas */
+pub fn draw_coloured_label(runs: &[(String, Color)], x: f32, y: f32, size: f32, font: &Font) {
+	let font_size: u16 = canvas_font_size(size);
+	let scale: f32 = canvas_scale();
+	let baseline: f32 = y * scale + get_length("A", font_size, font).offset_y; // Shared so runs line up
+	let mut run_x: f32 = x * scale;
+	for (text, colour) in runs.iter() {
+		draw_text_ex(
+			text,
+			run_x,
+			baseline,
+			TextParams {
+				font: Some(font),
+				font_size,
+				color: *colour,
+				..Default::default()
+			},
+		);
+		run_x += get_length(text, font_size, font).width;
+	}
+}
+
+//* Draws text centred within a rectangle of the window */
+pub fn draw_centred_label(text: &str, rect: Rect, size: f32, colour: Color, font: &Font) {
+	let font_size: u16 = canvas_font_size(size);
+	let centre: Vec2 = get_centre(font, font_size, text);
+	draw_text_ex(
+		text,
+		rect.x + rect.w / 2.0 - centre.x,
+		rect.y + rect.h / 2.0 - centre.y,
+		TextParams {
+			font: Some(font),
+			font_size,
+			color: colour,
+			..Default::default()
+		},
+	);
+}
+
+//* Draws a button and returns true on the frame it is clicked */
+pub fn button(rect: Rect, label: &str, font: &Font) -> bool {
+	let hovered: bool = rect.contains(mouse_position().into());
+	let colour: Color = if hovered && is_mouse_button_down(MouseButton::Left) {
+		DARK_PURPLE
+	} else {
+		PURPLE
+	};
+
+	draw_rectangle(rect.x, rect.y, rect.w, rect.h, colour);
+	draw_centred_label(label, rect, 80.0, WHITE, font);
+
+	hovered && is_mouse_button_pressed(MouseButton::Left) // Pressed, not released, so the click can't carry over to the next stage
+}
+
+//* Draws a button with an outline to show it is the suggested choice */
+pub fn suggested_button(rect: Rect, label: &str, font: &Font) -> bool {
+	let pressed: bool = button(rect, label, font);
+	let thickness: f32 = 16.0 * canvas_scale();
+	draw_rectangle_lines(rect.x, rect.y, rect.w, rect.h, thickness, WHITE);
+	pressed
+}
+
+//* Draws a grayed out button that can't be clicked */
+pub fn disabled_button(rect: Rect, label: &str, font: &Font) {
+	draw_rectangle(rect.x, rect.y, rect.w, rect.h, GRAY);
+	draw_centred_label(label, rect, 80.0, WHITE, font);
+}
+
+//* Collects the characters typed this frame in the order they were typed */
+pub fn get_typed_chars() -> Vec<char> {
+	let mut chars: Vec<char> = Vec::new();
+	while let Some(character) = get_char_pressed() {
+		chars.push(character);
+	}
+	chars.reverse(); // Macroquad hands back the newest character first
+	chars
+}
+
+// Text entry field. Single line boxes submit on enter, multi-line boxes take new lines
+pub struct TextBox {
+	pub text: String,
+	pub focused: bool,
+	pub multiline: bool,
+	pub placeholder: String, // Shown in gray while the box is empty
+	cursor: usize, // Character index that typed text is inserted at
+}
+
+impl TextBox {
+	pub fn new() -> TextBox {
+		TextBox {
+			text: String::new(),
+			focused: true,
+			multiline: false,
+			placeholder: String::new(),
+			cursor: 0,
+		}
+	}
+
+	pub fn multiline(placeholder: &str) -> TextBox {
+		TextBox {
+			text: String::new(),
+			focused: false,
+			multiline: true,
+			placeholder: placeholder.to_owned(),
+			cursor: 0,
+		}
+	}
+
+	//* Replaces the contents of the box, placing the cursor at the end */
+	pub fn set_text(&mut self, text: &str) {
+		self.text = text.to_owned();
+		self.cursor = self.text.chars().count();
+	}
+
+	// Byte index of the cursor (Text may contain multi-byte characters)
+	fn cursor_byte(&self) -> usize {
+		self.text.char_indices().nth(self.cursor).map(|(index, _)| index).unwrap_or(self.text.len())
+	}
+
+	fn insert(&mut self, text: &str) {
+		let index: usize = self.cursor_byte();
+		self.text.insert_str(index, text);
+		self.cursor += text.chars().count();
+	}
+
+	//* Handles focus changes, typing and cursor movement. Must be called once per frame */
+	pub fn update(&mut self, rect: Rect) {
+		if is_mouse_button_pressed(MouseButton::Left) {
+			self.focused = rect.contains(mouse_position().into());
+		}
+
+		if !self.focused {
+			return;
+		}
+
+		let length: usize = self.text.chars().count();
+		self.cursor = self.cursor.min(length); // Text may have been changed directly
+
+		for character in get_typed_chars() {
+			if !character.is_control() {
+				self.insert(&character.to_string());
+			}
+		}
+
+		let control: bool = is_key_down(KeyCode::LeftControl) || is_key_down(KeyCode::RightControl)
+			|| is_key_down(KeyCode::LeftSuper) || is_key_down(KeyCode::RightSuper);
+		if control && is_key_pressed(KeyCode::V) {
+			if let Some(pasted) = miniquad::window::clipboard_get() {
+				let pasted: String = if self.multiline { pasted.replace('\r', "") } else { pasted.replace(['\r', '\n'], " ") };
+				self.insert(&pasted);
+			}
+		}
+
+		if self.multiline && is_key_pressed(KeyCode::Enter) {
+			self.insert("\n");
+		}
+
+		if is_key_pressed(KeyCode::Backspace) && self.cursor > 0 {
+			self.cursor -= 1;
+			let index: usize = self.cursor_byte();
+			self.text.remove(index);
+		}
+		if is_key_pressed(KeyCode::Delete) && self.cursor < self.text.chars().count() {
+			let index: usize = self.cursor_byte();
+			self.text.remove(index);
+		}
+
+		if is_key_pressed(KeyCode::Left) && self.cursor > 0 {
+			self.cursor -= 1;
+		}
+		if is_key_pressed(KeyCode::Right) && self.cursor < self.text.chars().count() {
+			self.cursor += 1;
+		}
+		if is_key_pressed(KeyCode::Home) {
+			self.cursor = 0;
+		}
+		if is_key_pressed(KeyCode::End) {
+			self.cursor = self.text.chars().count();
+		}
+	}
+
+	//* Breaks the text into lines that fit the box, returning the line and column of the cursor */
+	fn layout(&self, max_width: f32, font_size: u16, font: &Font) -> (Vec<String>, usize, usize) {
+		let mut lines: Vec<String> = vec![String::new()];
+		let mut cursor_position: (usize, usize) = (0, 0);
+
+		for (index, character) in self.text.chars().enumerate() {
+			if index == self.cursor {
+				cursor_position = (lines.len() - 1, lines.last().map(|line| line.chars().count()).unwrap_or(0));
+			}
+
+			if character == '\n' {
+				lines.push(String::new());
+				continue;
+			}
+
+			let line: &mut String = lines.last_mut().expect("Layout always has a line");
+			line.push(character);
+			if self.multiline && get_length(line, font_size, font).width > max_width && line.chars().count() > 1 {
+				// Wrap the character that overflowed onto a new line
+				line.pop();
+				lines.push(character.to_string());
+				if index == self.cursor {
+					cursor_position = (lines.len() - 1, 0);
+				}
+			}
+		}
+
+		if self.cursor >= self.text.chars().count() {
+			cursor_position = (lines.len() - 1, lines.last().map(|line| line.chars().count()).unwrap_or(0));
+		}
+
+		(lines, cursor_position.0, cursor_position.1)
+	}
+
+	pub fn draw(&self, rect: Rect, font: &Font) {
+		draw_rectangle(rect.x, rect.y, rect.w, rect.h, WHITE);
+		let outline: Color = if self.focused { PURPLE } else { GRAY };
+		draw_rectangle_lines(rect.x, rect.y, rect.w, rect.h, 4.0, outline);
+
+		let font_size: u16 = canvas_font_size(80.0);
+		let line_height: f32 = font_size as f32 * 1.2;
+		let padding: f32 = 10.0;
+		let text_params = TextParams {
+			font: Some(font),
+			font_size,
+			color: BLACK,
+			..Default::default()
+		};
+
+		if self.text.is_empty() && !self.placeholder.is_empty() {
+			draw_text_ex(&self.placeholder, rect.x + padding, rect.y + padding + font_size as f32, TextParams { color: GRAY, ..text_params.clone() });
+		}
+
+		let (lines, cursor_line, cursor_column) = self.layout(rect.w - padding * 2.0, font_size, font);
+
+		// Single line boxes are centred vertically, multi-line boxes scroll to keep the cursor in view
+		let visible_lines: usize = ((rect.h - padding * 2.0) / line_height).max(1.0) as usize;
+		let first_line: usize = if self.multiline { (cursor_line + 1).saturating_sub(visible_lines) } else { 0 };
+		let top: f32 = if self.multiline {
+			rect.y + padding + font_size as f32
+		} else {
+			rect.y + rect.h / 2.0 - get_centre(font, font_size, "A").y
+		};
+
+		for (row, line) in lines.iter().skip(first_line).take(visible_lines).enumerate() {
+			draw_text_ex(line, rect.x + padding, top + line_height * row as f32, text_params.clone());
+		}
+
+		if self.focused && (get_time() * 2.0) as i64 % 2 == 0 {
+			let before_cursor: String = lines[cursor_line].chars().take(cursor_column).collect();
+			let x: f32 = rect.x + padding + get_length(&before_cursor, font_size, font).width;
+			let y: f32 = top + line_height * (cursor_line - first_line) as f32;
+			draw_line(x, y - font_size as f32 * 0.8, x, y + font_size as f32 * 0.2, 2.0, BLACK);
+		}
+	}
+}
+
+//* Draws a stage element texture stretched over a rectangle of the window */
+pub fn draw_stage_element(texture: &Texture2D, rect: Rect) {
+	draw_texture_ex(
+		texture,
+		rect.x,
+		rect.y,
+		WHITE,
+		DrawTextureParams {
+			dest_size: Some(Vec2::new(rect.w, rect.h)),
+			..Default::default()
+		},
+	);
+}
+
+//* Shortens text with "..." so that it is no wider than max_width (Window pixels) */
+pub fn truncate_to_width(text: &str, max_width: f32, size: f32, font: &Font) -> String {
+	let font_size: u16 = canvas_font_size(size);
+	let single_line: String = text.replace('\n', " ");
+	if get_length(&single_line, font_size, font).width <= max_width {
+		return single_line;
+	}
+
+	let mut shortened: String = single_line;
+	while !shortened.is_empty() && get_length(&format!("{}...", shortened), font_size, font).width > max_width {
+		shortened.pop();
+	}
+	format!("{}...", shortened)
+}
+
+//* Splits text into lines no wider than max_width (Window pixels) */
+pub fn wrap_text(text: &str, max_width: f32, size: f32, font: &Font) -> Vec<String> {
+	let font_size: u16 = canvas_font_size(size);
+	let mut lines: Vec<String> = Vec::new();
+
+	for paragraph in text.lines() {
+		let mut line: String = String::new();
+		for word in paragraph.split_whitespace() {
+			let candidate: String = if line.is_empty() {
+				word.to_owned()
+			} else {
+				format!("{} {}", line, word)
+			};
+
+			if get_length(&candidate, font_size, font).width > max_width && !line.is_empty() {
+				lines.push(line);
+				line = word.to_owned();
+			} else {
+				line = candidate;
+			}
+		}
+		lines.push(line);
+	}
+
+	lines
+}